@@ -0,0 +1,456 @@
+use crate::circuit::Circuit;
+use crate::connection::GateId;
+use crate::gate::GateType;
+use crate::schedule::Schedule;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// A boolean function stored in a [`Manager`].
+///
+/// BDDs are reduced and ordered, so two handles from the same manager are
+/// equal exactly when they represent the same function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Bdd(usize);
+
+/// A decision node: the function is `high` where variable `var` is true and `low` elsewhere.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Node {
+    var: usize,
+    low: Bdd,
+    high: Bdd,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Op {
+    And,
+    Or,
+    Xor,
+}
+
+/// Owner of the nodes of a set of reduced ordered binary decision diagrams.
+///
+/// Variables are numbered from zero, and variable `0` is tested first. Nodes
+/// are shared between all functions of a manager and never freed.
+#[derive(Debug, Clone)]
+pub struct Manager {
+    names: Vec<String>,
+    nodes: Vec<Node>,
+    unique: HashMap<Node, Bdd>,
+    computed: HashMap<(Op, Bdd, Bdd), Bdd>,
+}
+
+impl Manager {
+    /// The constant false function.
+    pub const ZERO: Bdd = Bdd(0);
+    /// The constant true function.
+    pub const ONE: Bdd = Bdd(1);
+
+    /// Creates a manager for variables with the given names, in test order.
+    pub fn new(names: Vec<String>) -> Self {
+        let terminal = |id| Node { var: usize::MAX, low: Bdd(id), high: Bdd(id) };
+        Self {
+            names,
+            nodes: vec![terminal(0), terminal(1)],
+            unique: HashMap::new(),
+            computed: HashMap::new(),
+        }
+    }
+
+    /// Returns the names of the variables, in test order.
+    pub fn variables(&self) -> &[String] {
+        &self.names
+    }
+
+    /// Returns the function that is true where variable `var` is.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no such variable.
+    pub fn var(&mut self, var: usize) -> Bdd {
+        assert!(var < self.names.len(), "Variable {} does not exist", var);
+        self.node(var, Self::ZERO, Self::ONE)
+    }
+
+    /// Returns the function `f` with every output inverted.
+    pub fn not(&mut self, f: Bdd) -> Bdd {
+        self.apply(Op::Xor, f, Self::ONE)
+    }
+
+    pub fn and(&mut self, f: Bdd, g: Bdd) -> Bdd {
+        self.apply(Op::And, f, g)
+    }
+
+    pub fn or(&mut self, f: Bdd, g: Bdd) -> Bdd {
+        self.apply(Op::Or, f, g)
+    }
+
+    pub fn xor(&mut self, f: Bdd, g: Bdd) -> Bdd {
+        self.apply(Op::Xor, f, g)
+    }
+
+    /// Returns `true` if `f` is the constant `0` or `1`.
+    pub fn is_constant(&self, f: Bdd) -> bool {
+        f == Self::ZERO || f == Self::ONE
+    }
+
+    /// Evaluates `f` for a value of every variable.
+    pub fn evaluate(&self, f: Bdd, values: &[bool]) -> bool {
+        let mut current = f;
+        while !self.is_constant(current) {
+            let node = self.nodes[current.0];
+            current = if values[node.var] { node.high } else { node.low };
+        }
+        current == Self::ONE
+    }
+
+    /// Number of decision nodes reachable from `f`.
+    pub fn node_count(&self, f: Bdd) -> usize {
+        let mut seen = HashSet::new();
+        let mut stack = vec![f];
+        while let Some(current) = stack.pop() {
+            if !self.is_constant(current) && seen.insert(current) {
+                let node = self.nodes[current.0];
+                stack.extend([node.low, node.high]);
+            }
+        }
+        seen.len()
+    }
+
+    /// Number of assignments of all variables for which `f` is true,
+    /// saturating at `u128::MAX`.
+    pub fn sat_count(&self, f: Bdd) -> u128 {
+        let mut counts = HashMap::new();
+        let count = self.count_from(f, &mut counts);
+        scale(count, self.level(f))
+    }
+
+    /// Returns the assignment that makes `f` true and is smallest when read
+    /// as a binary number with variable `0` most significant, or `None` if `f`
+    /// is always false.
+    pub fn any_sat(&self, f: Bdd) -> Option<Vec<bool>> {
+        if f == Self::ZERO {
+            return None;
+        }
+        let mut values = vec![false; self.names.len()];
+        let mut current = f;
+        while current != Self::ONE {
+            let node = self.nodes[current.0];
+            // A reduced node never has two false children
+            current = if node.low != Self::ZERO {
+                node.low
+            } else {
+                values[node.var] = true;
+                node.high
+            };
+        }
+        Some(values)
+    }
+
+    /// Enumerates the paths from `f` to `1` as partial assignments, where
+    /// `None` marks a variable whose value does not matter. Every satisfying
+    /// assignment matches exactly one of them.
+    pub fn cubes(&self, f: Bdd) -> Vec<Vec<Option<bool>>> {
+        let mut cubes = vec![];
+        let mut stack = vec![(f, vec![None; self.names.len()])];
+        while let Some((current, cube)) = stack.pop() {
+            if current == Self::ONE {
+                cubes.push(cube);
+            } else if current != Self::ZERO {
+                let node = self.nodes[current.0];
+                for (value, child) in [(true, node.high), (false, node.low)] {
+                    let mut cube = cube.clone();
+                    cube[node.var] = Some(value);
+                    stack.push((child, cube));
+                }
+            }
+        }
+        cubes
+    }
+
+    /// Variable tested at the root of `f`, or the number of variables for constants.
+    fn level(&self, f: Bdd) -> usize {
+        if self.is_constant(f) { self.names.len() } else { self.nodes[f.0].var }
+    }
+
+    /// Number of assignments of the variables from `level(f)` on that make `f` true.
+    fn count_from(&self, f: Bdd, counts: &mut HashMap<Bdd, u128>) -> u128 {
+        if self.is_constant(f) {
+            return u128::from(f == Self::ONE);
+        }
+        if let Some(&count) = counts.get(&f) {
+            return count;
+        }
+        let node = self.nodes[f.0];
+        let mut count: u128 = 0;
+        for child in [node.low, node.high] {
+            let skipped = self.level(child) - node.var - 1;
+            count = count.saturating_add(scale(self.count_from(child, counts), skipped));
+        }
+        counts.insert(f, count);
+        count
+    }
+
+    /// Returns the node testing `var`, reusing an existing one and skipping
+    /// tests whose outcome does not matter.
+    fn node(&mut self, var: usize, low: Bdd, high: Bdd) -> Bdd {
+        if low == high {
+            return low;
+        }
+        let node = Node { var, low, high };
+        if let Some(&existing) = self.unique.get(&node) {
+            return existing;
+        }
+        let id = Bdd(self.nodes.len());
+        self.nodes.push(node);
+        self.unique.insert(node, id);
+        id
+    }
+
+    fn apply(&mut self, op: Op, f: Bdd, g: Bdd) -> Bdd {
+        let (zero, one) = (Self::ZERO, Self::ONE);
+        match op {
+            Op::And if f == zero || g == zero => return zero,
+            Op::And if f == one || f == g => return g,
+            Op::And if g == one => return f,
+            Op::Or if f == one || g == one => return one,
+            Op::Or if f == zero || f == g => return g,
+            Op::Or if g == zero => return f,
+            Op::Xor if f == g => return zero,
+            Op::Xor if f == zero => return g,
+            Op::Xor if g == zero => return f,
+            Op::Xor if f == one && g == one => return zero,
+            _ => {}
+        }
+        // Every operation is commutative
+        let key = (op, f.min(g), f.max(g));
+        if let Some(&result) = self.computed.get(&key) {
+            return result;
+        }
+        let var = self.level(f).min(self.level(g));
+        let (f_low, f_high) = self.cofactors(f, var);
+        let (g_low, g_high) = self.cofactors(g, var);
+        let low = self.apply(op, f_low, g_low);
+        let high = self.apply(op, f_high, g_high);
+        let result = self.node(var, low, high);
+        self.computed.insert(key, result);
+        result
+    }
+
+    /// Returns `f` with `var` set to false and to true, for a `var` tested no
+    /// later than the root of `f`.
+    fn cofactors(&self, f: Bdd, var: usize) -> (Bdd, Bdd) {
+        if self.level(f) == var {
+            let node = self.nodes[f.0];
+            (node.low, node.high)
+        } else {
+            (f, f)
+        }
+    }
+}
+
+/// Multiplies `count` by `2^bits`, saturating at `u128::MAX`.
+fn scale(count: u128, bits: usize) -> u128 {
+    if count == 0 {
+        0
+    } else if bits >= 128 {
+        u128::MAX
+    } else {
+        count.saturating_mul(1 << bits)
+    }
+}
+
+/// How the input bits of a circuit are ordered as BDD variables.
+///
+/// The size of a BDD depends heavily on the order of its variables, and no
+/// order suits every circuit, so a few common heuristics are offered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VariableOrder {
+    /// Input gates in increasing id order, most significant bit first.
+    #[default]
+    Declaration,
+    /// Input gates in the order a depth-first walk from the outputs reaches
+    /// them, which keeps inputs feeding the same gates close together.
+    DepthFirst,
+    /// The most significant bits of all inputs first, then the next bits and
+    /// so on, which keeps bit slices of datapaths such as adders and
+    /// comparators together.
+    Interleaved,
+}
+
+/// Errors that prevent a BDD from being built for a circuit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BddError {
+    /// The gate does not exist.
+    InvalidGate(GateId),
+    /// The gate is a subcircuit instance or another gate without a boolean function.
+    Unsupported(GateId),
+    /// An input of the gate is not connected.
+    Floating { gate: GateId, input_index: usize },
+    /// The gate is part of a feedback loop, so its output depends on stored state.
+    Feedback(GateId),
+}
+
+impl fmt::Display for BddError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BddError::InvalidGate(gate) => write!(f, "Gate {} does not exist", gate),
+            BddError::Unsupported(gate) => write!(f, "Gate {} has no boolean function", gate),
+            BddError::Floating { gate, input_index } => {
+                write!(f, "Input {} of gate {} is not connected", input_index, gate)
+            }
+            BddError::Feedback(gate) => write!(f, "Gate {} is part of a feedback loop", gate),
+        }
+    }
+}
+
+impl std::error::Error for BddError {}
+
+/// BDDs for some gates of a circuit, sharing one manager.
+#[derive(Debug, Clone)]
+pub struct CircuitBdds {
+    pub manager: Manager,
+    /// The input gate and bit each variable stands for.
+    pub inputs: Vec<(GateId, usize)>,
+    /// One BDD per output bit, least significant first, for each requested gate.
+    pub outputs: Vec<Vec<Bdd>>,
+}
+
+impl CircuitBdds {
+    /// Builds BDDs for the first output of each gate in `outputs`, as
+    /// functions of every input gate of the circuit.
+    ///
+    /// Variables are named after their input gate, or `in<id>` for unnamed
+    /// ones, with `_<bit>` appended for the bits of buses. The circuit must
+    /// be free of feedback loops and subcircuit instances, and every input in
+    /// the fan-in of the outputs must be connected.
+    pub fn build(circuit: &Circuit, outputs: &[GateId], order: VariableOrder) -> Result<Self, BddError> {
+        for &gate_id in outputs {
+            if !circuit.contains_gate(gate_id) {
+                return Err(BddError::InvalidGate(gate_id));
+            }
+        }
+        let schedule = Schedule::build(circuit);
+        let inputs = ordered_inputs(circuit, &schedule, outputs, order);
+        let names = inputs.iter().map(|&(gate, bit)| variable_name(circuit, gate, bit)).collect();
+        let mut manager = Manager::new(names);
+        let variables: HashMap<(GateId, usize), usize> =
+            inputs.iter().enumerate().map(|(v, &key)| (key, v)).collect();
+        let variable = |gate, bit| variables.get(&(gate, bit)).copied();
+        let outputs = gate_bdds(&mut manager, circuit, &schedule, outputs, &variable)?;
+        Ok(Self { manager, inputs, outputs })
+    }
+}
+
+/// Name of the variable for a bit of an input gate.
+pub(crate) fn variable_name(circuit: &Circuit, gate_id: GateId, bit: usize) -> String {
+    let gate = circuit.gate(gate_id);
+    let name = gate.name.clone().unwrap_or_else(|| format!("in{}", gate_id));
+    if gate.width == 1 { name } else { format!("{}_{}", name, bit) }
+}
+
+/// Every bit of every input gate, as `(gate, bit)`, in the order `order` prescribes.
+fn ordered_inputs(
+    circuit: &Circuit,
+    schedule: &Schedule,
+    outputs: &[GateId],
+    order: VariableOrder,
+) -> Vec<(GateId, usize)> {
+    let mut gates: Vec<GateId> = circuit
+        .gate_ids()
+        .filter(|&id| circuit.gate(id).gate_type == GateType::Input)
+        .collect();
+    if order == VariableOrder::DepthFirst {
+        let mut reached = vec![];
+        let mut seen = HashSet::new();
+        let mut stack: Vec<GateId> = outputs.iter().rev().copied().collect();
+        while let Some(gate_id) = stack.pop() {
+            if !seen.insert(gate_id) {
+                continue;
+            }
+            if circuit.gate(gate_id).gate_type == GateType::Input {
+                reached.push(gate_id);
+            }
+            stack.extend(schedule.fanin(gate_id).iter().rev().map(|conn| conn.from));
+        }
+        // Inputs the outputs do not depend on go last
+        let unreached: Vec<GateId> = gates.iter().copied().filter(|id| !seen.contains(id)).collect();
+        gates = reached.into_iter().chain(unreached).collect();
+    }
+
+    let bits = |gate_id: GateId| (0..circuit.gate(gate_id).width).rev().map(move |bit| (gate_id, bit));
+    match order {
+        VariableOrder::Declaration | VariableOrder::DepthFirst => gates.into_iter().flat_map(bits).collect(),
+        VariableOrder::Interleaved => {
+            let widest = gates.iter().map(|&id| circuit.gate(id).width).max().unwrap_or(0);
+            (0..widest)
+                .rev()
+                .flat_map(|bit| {
+                    gates
+                        .iter()
+                        .filter(move |&&id| bit < circuit.gate(id).width)
+                        .map(move |&id| (id, bit))
+                })
+                .collect()
+        }
+    }
+}
+
+/// Builds the BDD of every bit of the first output of each gate in `outputs`.
+///
+/// `variable` gives the variable of a bit of an input gate, or `None` if the
+/// input should be treated as unsupported.
+pub(crate) fn gate_bdds(
+    manager: &mut Manager,
+    circuit: &Circuit,
+    schedule: &Schedule,
+    outputs: &[GateId],
+    variable: &dyn Fn(GateId, usize) -> Option<usize>,
+) -> Result<Vec<Vec<Bdd>>, BddError> {
+    // Only the fan-in cone of the outputs is needed
+    let mut cone = HashSet::new();
+    let mut stack = outputs.to_vec();
+    while let Some(gate_id) = stack.pop() {
+        if cone.insert(gate_id) {
+            stack.extend(schedule.fanin(gate_id).iter().map(|conn| conn.from));
+        }
+    }
+
+    let mut bits: HashMap<GateId, Vec<Bdd>> = HashMap::new();
+    for &gate_id in schedule.order().iter().filter(|id| cone.contains(id)) {
+        let gate = circuit.gate(gate_id);
+        let mut inputs = vec![];
+        for input_index in 0..gate.input_count {
+            let conn = schedule
+                .fanin(gate_id)
+                .iter()
+                .find(|conn| conn.input_index == input_index)
+                .ok_or(BddError::Floating { gate: gate_id, input_index })?;
+            if conn.output_index != 0 {
+                return Err(BddError::Unsupported(conn.from));
+            }
+            // Drivers come first in the schedule unless they close a loop
+            inputs.push(bits.get(&conn.from).ok_or(BddError::Feedback(gate_id))?);
+        }
+
+        let bitwise = |manager: &mut Manager, op: fn(&mut Manager, Bdd, Bdd) -> Bdd, identity: Bdd| {
+            (0..gate.width)
+                .map(|bit| inputs.iter().fold(identity, |acc, input| op(manager, acc, input[bit])))
+                .collect::<Vec<Bdd>>()
+        };
+        let output = match gate.gate_type {
+            GateType::Input => (0..gate.width)
+                .map(|bit| variable(gate_id, bit).map(|v| manager.var(v)))
+                .collect::<Option<Vec<Bdd>>>()
+                .ok_or(BddError::Unsupported(gate_id))?,
+            GateType::And => bitwise(manager, Manager::and, Manager::ONE),
+            GateType::Or => bitwise(manager, Manager::or, Manager::ZERO),
+            GateType::Xor => bitwise(manager, Manager::xor, Manager::ZERO),
+            GateType::Not => inputs[0].iter().map(|&bit| manager.not(bit)).collect(),
+            GateType::Splitter => inputs[0][gate.offset..gate.offset + gate.width].to_vec(),
+            GateType::Merger => inputs.iter().flat_map(|input| input.iter().copied()).collect(),
+            _ => return Err(BddError::Unsupported(gate_id)),
+        };
+        bits.insert(gate_id, output);
+    }
+    Ok(outputs.iter().map(|id| bits[id].clone()).collect())
+}
@@ -0,0 +1,73 @@
+//! Headless simulator for scripts and continuous integration.
+//!
+//! ```text
+//! simulate <circuit.json> [vectors]
+//! ```
+//!
+//! Loads a saved circuit and applies each line of the test vector file, or of
+//! standard input when no file or `-` is given. The file format is described
+//! in [`digital_logic_simulator::vectors::parse`]:
+//!
+//! ```text
+//! # comment
+//! a=1 b=0 -> sum=1 carry=-
+//! x=0101 y=0011
+//! ```
+//!
+//! For each line the circuit is settled and the inputs are printed with every
+//! unconnected output in the same format, so the output can be used as a
+//! vector file. The exit status is `1` if an expected output did not match and
+//! `2` if the circuit or the vectors could not be read.
+
+use digital_logic_simulator::circuit::Circuit;
+use digital_logic_simulator::vectors;
+use std::io::{self, Read};
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match run(&args) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::from(1),
+        Err(message) => {
+            eprintln!("simulate: {}", message);
+            ExitCode::from(2)
+        }
+    }
+}
+
+/// Simulates every vector, returning whether all expected outputs matched.
+fn run(args: &[String]) -> Result<bool, String> {
+    let (circuit_path, vectors_path) = match args {
+        [circuit] => (circuit, None),
+        [circuit, vectors] => (circuit, Some(vectors).filter(|path| *path != "-")),
+        _ => return Err("usage: simulate <circuit.json> [vectors]".to_string()),
+    };
+    let mut circuit = Circuit::load(circuit_path).map_err(|err| format!("{}: {}", circuit_path, err))?;
+    let text = match vectors_path {
+        Some(path) => std::fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))?,
+        None => {
+            let mut text = String::new();
+            io::stdin().read_to_string(&mut text).map_err(|err| err.to_string())?;
+            text
+        }
+    };
+
+    let vectors = vectors::parse(&text).map_err(|err| err.to_string())?;
+    let report = circuit.run_vectors(&vectors).map_err(|err| err.to_string())?;
+    for row in &report.rows {
+        let values: Vec<String> = row
+            .values
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value.to_bit_string()))
+            .collect();
+        println!("{}", values.join(" "));
+        if let Some(oscillation) = &row.oscillation {
+            eprintln!("row {} (line {}): {}", row.row, row.line, oscillation);
+        }
+    }
+    for mismatch in &report.mismatches {
+        eprintln!("{}", mismatch);
+    }
+    Ok(report.passed())
+}
@@ -0,0 +1,191 @@
+use crate::logic::Logic;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// An N-bit value carried by a wire, made of one `Logic` signal per bit.
+///
+/// Bit 0 is the least significant bit. A single-bit wire is simply a bus of
+/// width one, so a `Bus` can be compared directly with a `Logic` or `bool`.
+/// Buses are stored in files as bit strings such as `"10XZ"`, most significant bit first.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub struct Bus {
+    bits: Vec<Logic>,
+}
+
+impl Bus {
+    /// Creates a bus of the given width with every bit set to `value`.
+    pub fn filled(width: usize, value: Logic) -> Self {
+        Self {
+            bits: vec![value; width],
+        }
+    }
+
+    /// Creates a bus holding the lowest `width` bits of `value`.
+    pub fn from_u64(value: u64, width: usize) -> Self {
+        (0..width)
+            .map(|i| Logic::from(i < 64 && (value >> i) & 1 == 1))
+            .collect()
+    }
+
+    pub fn width(&self) -> usize {
+        self.bits.len()
+    }
+
+    /// Returns bit `index`, or `Logic::Z` if the bus is not that wide.
+    pub fn bit(&self, index: usize) -> Logic {
+        self.bits.get(index).copied().unwrap_or(Logic::Z)
+    }
+
+    pub fn set_bit(&mut self, index: usize, value: Logic) {
+        self.bits[index] = value;
+    }
+
+    /// Returns all bits, least significant first.
+    pub fn bits(&self) -> &[Logic] {
+        &self.bits
+    }
+
+    /// Returns `true` if every bit is `Zero` or `One`.
+    pub fn is_known(&self) -> bool {
+        self.bits.iter().all(|b| b.is_known())
+    }
+
+    /// Converts the bus to an integer, or `None` if a bit is unknown or it is wider than 64 bits.
+    pub fn to_u64(&self) -> Option<u64> {
+        if self.width() > 64 {
+            return None;
+        }
+        self.bits.iter().rev().try_fold(0u64, |acc, bit| {
+            bit.to_bool().map(|b| (acc << 1) | b as u64)
+        })
+    }
+
+    /// Returns `width` bits starting at `offset`. Bits beyond the end of the bus read as `Z`.
+    pub fn slice(&self, offset: usize, width: usize) -> Bus {
+        (offset..offset + width).map(|i| self.bit(i)).collect()
+    }
+
+    /// Bitwise [`Logic::resolve`] of two buses driving the same wire.
+    ///
+    /// The result is as wide as the wider bus; missing bits count as `Z`.
+    pub fn resolve(&self, other: &Bus) -> Bus {
+        let width = self.width().max(other.width());
+        (0..width).map(|i| self.bit(i).resolve(other.bit(i))).collect()
+    }
+
+    /// Concatenates buses, the first one providing the least significant bits.
+    pub fn concat(parts: &[Bus]) -> Bus {
+        parts.iter().flat_map(|part| part.bits.iter().copied()).collect()
+    }
+
+    /// Formats the bus as one `0`, `1`, `X` or `Z` character per bit, most significant first.
+    pub fn to_bit_string(&self) -> String {
+        self.bits.iter().rev().map(|bit| bit.to_string()).collect()
+    }
+
+    /// Formats the bus as hexadecimal digits, most significant first.
+    ///
+    /// A digit containing an unknown bit is shown as `X`, a fully floating digit as `Z`.
+    pub fn to_hex(&self) -> String {
+        self.bits
+            .chunks(4)
+            .rev()
+            .map(|nibble| {
+                if nibble.iter().all(|&b| b == Logic::Z) {
+                    'Z'
+                } else {
+                    let value = nibble.iter().rev().try_fold(0u32, |acc, bit| {
+                        bit.to_bool().map(|b| (acc << 1) | b as u32)
+                    });
+                    value.and_then(|v| char::from_digit(v, 16)).map_or('X', |c| c.to_ascii_uppercase())
+                }
+            })
+            .collect()
+    }
+}
+
+/// Error returned when a bit string contains a character other than `0`, `1`, `X` or `Z`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseBusError {
+    pub invalid: char,
+}
+
+impl fmt::Display for ParseBusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid signal character '{}'", self.invalid)
+    }
+}
+
+impl std::error::Error for ParseBusError {}
+
+/// Parses a bit string such as `"10XZ"`, most significant bit first.
+impl FromStr for Bus {
+    type Err = ParseBusError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.chars()
+            .rev()
+            .map(|c| Logic::from_char(c).ok_or(ParseBusError { invalid: c }))
+            .collect::<Result<Vec<_>, _>>()
+            .map(|bits| Bus { bits })
+    }
+}
+
+impl TryFrom<String> for Bus {
+    type Error = ParseBusError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Bus> for String {
+    fn from(value: Bus) -> Self {
+        value.to_bit_string()
+    }
+}
+
+impl FromIterator<Logic> for Bus {
+    fn from_iter<I: IntoIterator<Item = Logic>>(iter: I) -> Self {
+        Self {
+            bits: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl From<Logic> for Bus {
+    fn from(value: Logic) -> Self {
+        Self { bits: vec![value] }
+    }
+}
+
+impl From<bool> for Bus {
+    fn from(value: bool) -> Self {
+        Bus::from(Logic::from(value))
+    }
+}
+
+impl PartialEq<Logic> for Bus {
+    fn eq(&self, other: &Logic) -> bool {
+        self.bits == [*other]
+    }
+}
+
+impl PartialEq<bool> for Bus {
+    fn eq(&self, other: &bool) -> bool {
+        *self == Logic::from(*other)
+    }
+}
+
+/// Single-bit buses are shown as their logic value, wider buses in hexadecimal.
+impl fmt::Display for Bus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.width() == 1 {
+            write!(f, "{}", self.bits[0])
+        } else {
+            write!(f, "0x{}", self.to_hex())
+        }
+    }
+}
@@ -1,56 +1,231 @@
+use crate::bdd::{BddError, CircuitBdds, VariableOrder};
+use crate::bus::Bus;
+use crate::cnf::{Cnf, CnfError};
 use crate::connection::{Connection, GateId};
-use crate::gate::{Gate, GateType};
-use std::collections::HashMap;
+use crate::equivalence::{self, Equivalence, EquivalenceError};
+use crate::error::CircuitError;
+use crate::event::{EventQueue, Transition};
+use crate::expression::{Expr, ExpressionError};
+use crate::gate::{ClockTiming, Gate, GateType};
+use crate::library::Component;
+use crate::logic::Logic;
+use crate::memory::{self, ImageError, ImageFormat, LoadError, Memory, MemoryKind, MemorySpec};
+use crate::minimize::SumOfProducts;
+use crate::packed::{self, PackedBus, PackedLogic};
+use crate::persistence::{self, FileError};
+use crate::schedule::{Cycle, Schedule};
+use crate::storage::{self, StorageKind};
+use crate::subcircuit::{Subcircuit, SubcircuitId};
+use crate::truth_table::{TruthTable, TruthTableError};
+use crate::vectors::{self, Report, TestVector, VectorError};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::path::Path;
 
-/// Represents a digital logic circuit composed of gates, connections, and primary inputs.
+/// Number of passes `Circuit::settle` makes by default before giving up on a
+/// circuit that keeps changing.
+pub const DEFAULT_MAX_PASSES: usize = 100;
+
+/// Returned by `Circuit::settle` when the circuit did not reach a fixed point.
+///
+/// This happens for circuits whose feedback loops never stabilize, such as a
+/// ring oscillator built from an odd number of inverters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Oscillation {
+    /// Number of passes that were made before giving up.
+    pub passes: usize,
+    /// Gates whose output still changed during the last pass.
+    pub unstable: Vec<GateId>,
+}
+
+impl fmt::Display for Oscillation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "circuit did not settle after {} passes ({} gates still changing)",
+            self.passes,
+            self.unstable.len()
+        )
+    }
+}
+
+impl std::error::Error for Oscillation {}
+
+/// An input driven by several outputs that pull it to different levels.
+///
+/// [`Circuit::connect`] only allows one driver per input, but circuits saved by
+/// older versions may still contain inputs with several. Their values are
+/// combined with [`Logic::resolve`], so conflicting bits read as `X`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Contention {
+    /// Gate whose input is affected.
+    pub gate: GateId,
+    /// Index of the affected input.
+    pub input_index: usize,
+    /// Gate and output index of every driver of the input.
+    pub drivers: Vec<(GateId, usize)>,
+}
+
+impl fmt::Display for Contention {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "input {} of gate {} is driven to conflicting values by {} outputs",
+            self.input_index,
+            self.gate,
+            self.drivers.len()
+        )
+    }
+}
+
+/// Outputs of every gate for 64 input vectors, as computed by [`Circuit::simulate_packed`].
+///
+/// Maps each gate to one packed bus per output.
+pub type PackedOutputs = HashMap<GateId, Vec<PackedBus>>;
+
+/// Unwraps the result of a `try_*` method for its panicking counterpart.
+fn or_panic<T>(result: Result<T, CircuitError>) -> T {
+    result.unwrap_or_else(|err| panic!("{}", err))
+}
+
+/// Drives the input ports of a subcircuit instance and returns the values of its output ports.
+fn run_instance(instance: &mut Circuit, definition: &Subcircuit, inputs: &[Bus]) -> Vec<Bus> {
+    for (port, value) in definition.inputs().iter().zip(inputs) {
+        instance.set_primary_input_value(port.gate, value.clone());
+    }
+    // An oscillating subcircuit keeps the outputs of its last pass
+    let _ = instance.settle(DEFAULT_MAX_PASSES);
+    definition.outputs().iter().map(|port| instance.get_output(port.gate)).collect()
+}
+
+/// Represents a digital logic circuit composed of gates and the connections between them.
 ///
 /// The circuit manages the evaluation of gate outputs based on connections and external inputs.
+/// External inputs (e.g., switches) are modelled as gates of type `GateType::Input`.
 ///
 /// # Fields
-/// - `gates`: All logic gates contained in the circuit.
+/// - `gates`: All logic gates contained in the circuit, keyed by their id.
+/// - `next_gate_id`: Id given to the next gate added; ids of removed gates are never reused.
 /// - `connections`: Links representing connections between gate outputs and inputs.
-/// - `primary_inputs`: External inputs to the circuit (e.g., switches).
+/// - `time`: Current time of the event-driven simulation.
+/// - `events`: Output changes scheduled for the future.
+/// - `history`: Output changes applied by the event-driven simulation so far.
+/// - `subcircuits`: Definitions of user-defined components that can be instantiated.
+/// - `instances`: State of each subcircuit instance, keyed by the instance's gate.
+/// - `schedule`: Evaluation order compiled from the netlist, rebuilt after it changes.
+/// - `dirty`: Gates whose inputs changed since they were last evaluated.
+/// - `revision`: Counter bumped by every change to the netlist; memory contents are not part of it.
+/// - `ticks`: Number of clock ticks since the circuit was created or loaded.
+/// - `clock_levels`: Clock level each flip-flop and RAM was last evaluated with, to detect edges.
+/// - `memories`: Contents of each ROM and RAM, keyed by the memory's gate.
+///
+/// The event-driven simulation state is not saved to files; a loaded circuit
+/// starts at time zero with no pending events.
+///
+/// Methods taking gate ids or port indices panic when given invalid ones. Each
+/// of them has a `try_*` counterpart that reports a [`CircuitError`] instead.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Circuit {
-    gates: Vec<Gate>,
+    gates: BTreeMap<GateId, Gate>,
+    next_gate_id: GateId,
     connections: Vec<Connection>,
-    primary_inputs: Vec<bool>, // inputs like switches
+    #[serde(skip)]
+    time: u64,
+    #[serde(skip)]
+    events: EventQueue,
+    #[serde(skip)]
+    history: Vec<Transition>,
+    subcircuits: Vec<Subcircuit>,
+    instances: HashMap<GateId, Circuit>,
+    #[serde(skip)]
+    schedule: Option<Schedule>,
+    #[serde(skip)]
+    dirty: BTreeSet<GateId>,
+    #[serde(skip)]
+    revision: u64,
+    #[serde(skip)]
+    ticks: u64,
+    #[serde(skip)]
+    clock_levels: HashMap<GateId, Logic>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    memories: HashMap<GateId, Memory>,
+}
+
+impl Default for Circuit {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Circuit {
-    /// Creates a new empty circuit with no gates, connections, or primary inputs.
+    /// Creates a new empty circuit with no gates or connections.
     pub fn new() -> Self {
         Self {
-            gates: vec![],
+            gates: BTreeMap::new(),
+            next_gate_id: 0,
             connections: vec![],
-            primary_inputs: vec![],
+            time: 0,
+            events: EventQueue::new(),
+            history: vec![],
+            subcircuits: vec![],
+            instances: HashMap::new(),
+            schedule: None,
+            dirty: BTreeSet::new(),
+            revision: 0,
+            ticks: 0,
+            clock_levels: HashMap::new(),
+            memories: HashMap::new(),
         }
     }
 
-    /// Recursively evaluates the output of a gate
-    pub fn evaluate_gate(&self, gate_id: GateId, cache: &mut HashMap<GateId, bool>) -> bool {
-        if let Some(&cached_output) = cache.get(&gate_id) {
-            return cached_output;
+    /// Recursively evaluates the outputs of a gate
+    ///
+    /// Feedback loops are tolerated: while a gate is being evaluated, any path
+    /// leading back to it reads the gate's previous `outputs`, which act as the
+    /// stored state of the loop.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `gate_id` is out of bounds.
+    pub fn evaluate_gate(&self, gate_id: GateId, cache: &mut HashMap<GateId, Vec<Bus>>) -> Vec<Bus> {
+        if let Some(cached_outputs) = cache.get(&gate_id) {
+            return cached_outputs.clone();
         }
 
-        let gate = &self.gates[gate_id];
+        let gate = &self.gates[&gate_id];
 
         // Input gates output is stored directly
         if gate.gate_type == GateType::Input {
-            cache.insert(gate_id, gate.output);
-            return gate.output;
+            cache.insert(gate_id, gate.outputs.clone());
+            return gate.outputs.clone();
         }
 
-        // Gather inputs by following connections
-        let mut inputs = vec![false; gate.input_count];
-        for conn in self.connections.iter().filter(|c| c.to == gate_id) {
-            inputs[conn.input_index] = self.evaluate_gate(conn.from, cache);
+        // Seed the cache with the previous outputs so a cycle back to this gate terminates
+        cache.insert(gate_id, gate.outputs.clone());
+
+        // Gather inputs by following connections; unconnected inputs float
+        let mut inputs = self.floating_inputs(gate_id);
+        for conn in self.drivers(gate_id) {
+            let value = self.evaluate_gate(conn.from, cache).swap_remove(conn.output_index);
+            inputs[conn.input_index] = inputs[conn.input_index].resolve(&value);
         }
 
         // Evaluate this gate with its input values
-        let output = gate.evaluate_with_inputs(&inputs);
+        let outputs = self.compute_outputs(gate_id, &inputs);
+
+        cache.insert(gate_id, outputs.clone());
+        outputs
+    }
 
-        cache.insert(gate_id, output);
-        output
+    /// Like [`Circuit::evaluate_gate`], but reports an unknown gate instead of panicking.
+    pub fn try_evaluate_gate(
+        &self,
+        gate_id: GateId,
+        cache: &mut HashMap<GateId, Vec<Bus>>,
+    ) -> Result<Vec<Bus>, CircuitError> {
+        self.try_gate(gate_id)?;
+        Ok(self.evaluate_gate(gate_id, cache))
     }
 
     /// Adds a new gate of the specified type and input count to the circuit.
@@ -61,29 +236,972 @@ impl Circuit {
     ///
     /// * `gate_type` - The type of logic gate to add.
     /// * `input_count` - Number of inputs this gate accepts.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the gate type cannot have `input_count` inputs, e.g. a `Not`
+    /// gate with two inputs or an `Input` gate with any.
     pub fn add_gate(&mut self, gate_type: GateType, input_count: usize) -> GateId {
-        let gate = Gate::new(gate_type, input_count);
-        self.gates.push(gate);
-        self.gates.len() - 1
+        or_panic(self.try_add_gate(gate_type, input_count))
+    }
+
+    /// Like [`Circuit::add_gate`], but reports an invalid input count instead of panicking.
+    ///
+    /// A `Subcircuit` gate type adds an instance of that subcircuit, which must
+    /// have exactly `input_count` input ports.
+    pub fn try_add_gate(&mut self, gate_type: GateType, input_count: usize) -> Result<GateId, CircuitError> {
+        self.try_add_bus_gate(gate_type, input_count, 1)
+    }
+
+    /// Adds a gate whose output (and, for bitwise gates, every input) is `width` bits wide.
+    ///
+    /// Returns the `GateId` of the newly added gate.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the gate type cannot have `input_count` inputs.
+    pub fn add_bus_gate(&mut self, gate_type: GateType, input_count: usize, width: usize) -> GateId {
+        or_panic(self.try_add_bus_gate(gate_type, input_count, width))
+    }
+
+    /// Like [`Circuit::add_bus_gate`], but reports an invalid input count instead of panicking.
+    ///
+    /// The width of a subcircuit instance is given by its definition, so `width`
+    /// is ignored for `Subcircuit` gate types.
+    pub fn try_add_bus_gate(
+        &mut self,
+        gate_type: GateType,
+        input_count: usize,
+        width: usize,
+    ) -> Result<GateId, CircuitError> {
+        if let GateType::Subcircuit(id) = gate_type {
+            if self.try_subcircuit(id)?.inputs().len() != input_count {
+                return Err(CircuitError::InvalidInputCount { gate_type, input_count });
+            }
+            return self.try_add_subcircuit(id);
+        }
+        if !gate_type.accepts_input_count(input_count) {
+            return Err(CircuitError::InvalidInputCount { gate_type, input_count });
+        }
+        let mut gate = Gate::with_width(gate_type, input_count, width);
+        if let GateType::Clock(timing) = gate_type {
+            if !timing.is_valid() {
+                return Err(CircuitError::InvalidClockTiming { period: timing.period, high: timing.high });
+            }
+            gate.outputs = vec![Bus::filled(width, timing.level(self.ticks))];
+        }
+        if let GateType::Memory(spec) = gate_type {
+            if !spec.is_valid(width) {
+                return Err(CircuitError::InvalidMemorySize { address_width: spec.address_width, data_width: width });
+            }
+            let gate_id = self.insert_gate(gate);
+            self.memories.insert(gate_id, Memory::new(spec, width));
+            return Ok(gate_id);
+        }
+        Ok(self.insert_gate(gate))
+    }
+
+    /// Adds a splitter that outputs `width` bits of its input bus, starting at bit `offset`.
+    pub fn add_splitter(&mut self, offset: usize, width: usize) -> GateId {
+        let id = self.add_bus_gate(GateType::Splitter, 1, width);
+        self.gate_mut(id).offset = offset;
+        id
+    }
+
+    /// Adds a merger that combines `part_count` buses of `part_width` bits into one bus.
+    ///
+    /// Input 0 provides the least significant bits of the output.
+    pub fn add_merger(&mut self, part_count: usize, part_width: usize) -> GateId {
+        self.add_bus_gate(GateType::Merger, part_count, part_count * part_width)
+    }
+
+    /// Adds a single-bit clock with the given period and high time, in ticks.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the clock would not be low and high for at least one tick of
+    /// each period.
+    pub fn add_clock(&mut self, period: u64, high: u64) -> GateId {
+        self.add_gate(GateType::Clock(ClockTiming { period, high }), 0)
+    }
+
+    /// Adds a single-bit flip-flop of the given kind, triggered by the rising
+    /// edge of its clock.
+    ///
+    /// Its inputs are listed by [`StorageKind::input_names`]; its outputs are
+    /// [`Q`](crate::storage::Q) and [`Q_BAR`](crate::storage::Q_BAR).
+    pub fn add_flip_flop(&mut self, kind: StorageKind) -> GateId {
+        self.add_gate(GateType::FlipFlop(kind), kind.input_count())
+    }
+
+    /// Adds a single-bit latch of the given kind, transparent while its clock
+    /// is high.
+    pub fn add_latch(&mut self, kind: StorageKind) -> GateId {
+        self.add_gate(GateType::Latch(kind), kind.input_count())
+    }
+
+    /// Adds a ROM of `1 << address_width` words of `data_width` bits, all zero.
+    ///
+    /// Its inputs are listed by [`MemoryKind::input_names`]. Its contents can
+    /// be loaded with [`Circuit::load_memory`] and changed with
+    /// [`Circuit::write_memory`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the address or data width is zero or too large; see
+    /// [`MemorySpec::is_valid`].
+    pub fn add_rom(&mut self, address_width: usize, data_width: usize) -> GateId {
+        let kind = MemoryKind::Rom;
+        self.add_bus_gate(GateType::Memory(MemorySpec { kind, address_width }), kind.input_count(), data_width)
+    }
+
+    /// Adds a RAM of `1 << address_width` words of `data_width` bits, all
+    /// zero, that writes on the rising edge of its clock.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the address or data width is zero or too large.
+    pub fn add_ram(&mut self, address_width: usize, data_width: usize) -> GateId {
+        let kind = MemoryKind::Ram;
+        self.add_bus_gate(GateType::Memory(MemorySpec { kind, address_width }), kind.input_count(), data_width)
+    }
+
+    /// Registers a subcircuit definition so that it can be instantiated in this circuit.
+    ///
+    /// Returns the `SubcircuitId` used to create instances with [`Circuit::add_subcircuit`].
+    pub fn define_subcircuit(&mut self, definition: Subcircuit) -> SubcircuitId {
+        self.subcircuits.push(definition);
+        self.subcircuits.len() - 1
+    }
+
+    /// Returns the subcircuit definition with the given id.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` is out of bounds.
+    pub fn subcircuit(&self, id: SubcircuitId) -> &Subcircuit {
+        or_panic(self.try_subcircuit(id))
+    }
+
+    /// Like [`Circuit::subcircuit`], but reports an unknown id instead of panicking.
+    pub fn try_subcircuit(&self, id: SubcircuitId) -> Result<&Subcircuit, CircuitError> {
+        self.subcircuits.get(id).ok_or(CircuitError::InvalidSubcircuit(id))
+    }
+
+    /// Adds an instance of a registered subcircuit to the circuit.
+    ///
+    /// The instance has one input per input port and one output per output port
+    /// of the definition, in port order; use [`Subcircuit::input_index`] and
+    /// [`Subcircuit::output_index`] to look them up by name. Each instance keeps
+    /// its own copy of the inner circuit, so stateful subcircuits work independently.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no subcircuit with this id has been defined.
+    pub fn add_subcircuit(&mut self, id: SubcircuitId) -> GateId {
+        or_panic(self.try_add_subcircuit(id))
+    }
+
+    /// Like [`Circuit::add_subcircuit`], but reports an unknown id instead of panicking.
+    pub fn try_add_subcircuit(&mut self, id: SubcircuitId) -> Result<GateId, CircuitError> {
+        let definition = self.try_subcircuit(id)?;
+        let mut gate = Gate::new(GateType::Subcircuit(id), definition.inputs().len());
+        gate.outputs = definition
+            .output_widths()
+            .into_iter()
+            .map(|width| Bus::filled(width, Logic::X))
+            .collect();
+        gate.width = gate.outputs.first().map_or(0, Bus::width);
+        let instance = definition.circuit().clone();
+
+        let gate_id = self.insert_gate(gate);
+        self.instances.insert(gate_id, instance);
+        Ok(gate_id)
+    }
+
+    /// Adds an instance of a library component to the circuit.
+    ///
+    /// The component's definition is registered the first time it is used and
+    /// shared by every later instance with the same parameters; other
+    /// definitions are never used for it, even if they have the same name.
+    /// Look up its ports with [`Subcircuit::input_index`] and
+    /// [`Subcircuit::output_index`], and use [`Circuit::expand`] to replace it
+    /// by its gates.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the component's parameters are not valid.
+    pub fn add_component(&mut self, component: Component) -> GateId {
+        or_panic(self.try_add_component(component))
+    }
+
+    /// Like [`Circuit::add_component`], but reports invalid parameters instead of panicking.
+    pub fn try_add_component(&mut self, component: Component) -> Result<GateId, CircuitError> {
+        if !component.is_valid() {
+            return Err(CircuitError::InvalidComponent(component));
+        }
+        // Matched by component rather than by name, which user definitions may share
+        let id = match self.subcircuits.iter().position(|d| d.component() == Some(component)) {
+            Some(id) => id,
+            None => self.define_subcircuit(component.definition()),
+        };
+        self.try_add_subcircuit(id)
+    }
+
+    /// Returns an equivalent circuit in which every subcircuit instance, at any
+    /// depth, is replaced by the primitive gates it is made of.
+    ///
+    /// Gates outside of subcircuit instances come first and keep their relative
+    /// order, so primary inputs can be found at the same positions as before.
+    /// Ids are renumbered from zero, closing the gaps left by removed gates.
+    /// The input gates backing subcircuit ports are removed and their readers are
+    /// wired directly to whatever drove the instance.
+    pub fn flatten(&self) -> Circuit {
+        self.flatten_with_ids().0
+    }
+
+    /// Like [`Circuit::flatten`], but also returns the id each gate of this
+    /// circuit has in the flattened one.
+    ///
+    /// A subcircuit instance maps to the gate its first output comes from, if
+    /// any, so that output ports naming a nested instance can be resolved.
+    fn flatten_with_ids(&self) -> (Circuit, HashMap<GateId, GateId>) {
+        /// Where a flattened instance output port takes its value from.
+        enum Source {
+            Gate(GateId),
+            InputPort(usize),
+            Floating,
+        }
+
+        let mut flat = Circuit::new();
+        let mut new_ids = HashMap::new();
+        for (&gate_id, gate) in &self.gates {
+            if !matches!(gate.gate_type, GateType::Subcircuit(_)) {
+                let new_id = flat.insert_gate(gate.clone());
+                if let Some(memory) = self.memories.get(&gate_id) {
+                    flat.memories.insert(new_id, memory.clone());
+                }
+                new_ids.insert(gate_id, new_id);
+            }
+        }
+
+        // Inline each instance, remembering what its ports turned into
+        let mut port_readers: HashMap<GateId, Vec<Vec<(GateId, usize)>>> = HashMap::new();
+        let mut port_sources: HashMap<GateId, Vec<Source>> = HashMap::new();
+        for (&gate_id, gate) in &self.gates {
+            let GateType::Subcircuit(id) = gate.gate_type else {
+                continue;
+            };
+            let definition = &self.subcircuits[id];
+            // The flattened instance has its own ids; ports are translated to them
+            let (inner, port_ids) = self.instances[&gate_id].flatten_with_ids();
+            let input_ports: Vec<GateId> = definition.inputs().iter().map(|p| port_ids[&p.gate]).collect();
+            let input_port_of = |inner_id: GateId| input_ports.iter().position(|&p| p == inner_id);
+
+            let mut inner_ids = HashMap::new();
+            for (&inner_id, inner_gate) in &inner.gates {
+                if input_port_of(inner_id).is_none() {
+                    let new_id = flat.insert_gate(inner_gate.clone());
+                    if let Some(memory) = inner.memories.get(&inner_id) {
+                        flat.memories.insert(new_id, memory.clone());
+                    }
+                    inner_ids.insert(inner_id, new_id);
+                }
+            }
+
+            let mut readers = vec![vec![]; definition.inputs().len()];
+            for conn in &inner.connections {
+                let to = inner_ids[&conn.to];
+                match input_port_of(conn.from) {
+                    Some(port) => readers[port].push((to, conn.input_index)),
+                    None => flat.connections.push(Connection {
+                        from: inner_ids[&conn.from],
+                        output_index: conn.output_index,
+                        to,
+                        input_index: conn.input_index,
+                    }),
+                }
+            }
+
+            let sources = definition
+                .outputs()
+                .iter()
+                .map(|port| match port_ids.get(&port.gate) {
+                    Some(&inner_id) => match input_port_of(inner_id) {
+                        Some(input) => Source::InputPort(input),
+                        None => Source::Gate(inner_ids[&inner_id]),
+                    },
+                    None => Source::Floating,
+                })
+                .collect();
+            port_readers.insert(gate_id, readers);
+            port_sources.insert(gate_id, sources);
+        }
+
+        // Finds the flattened output driving what `from` drove in this circuit
+        fn resolve(
+            circuit: &Circuit,
+            new_ids: &HashMap<GateId, GateId>,
+            port_sources: &HashMap<GateId, Vec<Source>>,
+            from: GateId,
+            output_index: usize,
+        ) -> Option<(GateId, usize)> {
+            match port_sources.get(&from) {
+                None => Some((new_ids[&from], output_index)),
+                Some(sources) => match sources[output_index] {
+                    Source::Gate(id) => Some((id, 0)),
+                    Source::InputPort(input) => {
+                        let driver = circuit
+                            .connections
+                            .iter()
+                            .find(|c| c.to == from && c.input_index == input)?;
+                        resolve(circuit, new_ids, port_sources, driver.from, driver.output_index)
+                    }
+                    Source::Floating => None,
+                },
+            }
+        }
+
+        for conn in &self.connections {
+            let Some((from, output_index)) = resolve(self, &new_ids, &port_sources, conn.from, conn.output_index) else {
+                continue;
+            };
+            let destinations = match port_readers.get(&conn.to) {
+                Some(readers) => readers[conn.input_index].clone(),
+                None => vec![(new_ids[&conn.to], conn.input_index)],
+            };
+            for (to, input_index) in destinations {
+                flat.connections.push(Connection {
+                    from,
+                    output_index,
+                    to,
+                    input_index,
+                });
+            }
+        }
+
+        for &gate_id in port_sources.keys() {
+            if let Some((id, 0)) = resolve(self, &new_ids, &port_sources, gate_id, 0) {
+                new_ids.insert(gate_id, id);
+            }
+        }
+        (flat, new_ids)
+    }
+
+    /// Replaces a subcircuit instance by the primitive gates it is made of,
+    /// wired to whatever the instance was wired to, and returns their ids.
+    ///
+    /// Like [`Circuit::flatten`], but for a single instance and in place, so
+    /// the rest of the circuit keeps its ids. Nested instances are expanded
+    /// too, and the new gates keep their current outputs. The input gates
+    /// backing the ports are not copied; their readers are wired to the
+    /// drivers of the instance instead.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `gate_id` is not a subcircuit instance.
+    pub fn expand(&mut self, gate_id: GateId) -> Vec<GateId> {
+        or_panic(self.try_expand(gate_id))
+    }
+
+    /// Like [`Circuit::expand`], but reports a gate that is not a subcircuit
+    /// instance instead of panicking.
+    pub fn try_expand(&mut self, gate_id: GateId) -> Result<Vec<GateId>, CircuitError> {
+        let GateType::Subcircuit(id) = self.try_gate(gate_id)?.gate_type else {
+            return Err(CircuitError::NotAnInstance(gate_id));
+        };
+        let definition = &self.subcircuits[id];
+        // The flattened instance has its own ids; ports are translated to them
+        let (inner, port_ids) = self.instances[&gate_id].flatten_with_ids();
+        let input_ports: Vec<GateId> = definition.inputs().iter().map(|p| port_ids[&p.gate]).collect();
+        let output_ports: Vec<Option<GateId>> =
+            definition.outputs().iter().map(|p| port_ids.get(&p.gate).copied()).collect();
+        let drivers: Vec<Option<(GateId, usize)>> = (0..input_ports.len())
+            .map(|input| self.driver(gate_id, input).map(|c| (c.from, c.output_index)))
+            .collect();
+        let readers: Vec<Connection> = self
+            .connections
+            .iter()
+            .filter(|c| c.from == gate_id && c.to != gate_id)
+            .cloned()
+            .collect();
+        self.remove_gate(gate_id);
+
+        let mut new_ids = HashMap::new();
+        for (&inner_id, gate) in &inner.gates {
+            if !input_ports.contains(&inner_id) {
+                let new_id = self.insert_gate(gate.clone());
+                if let Some(memory) = inner.memories.get(&inner_id) {
+                    self.memories.insert(new_id, memory.clone());
+                }
+                new_ids.insert(inner_id, new_id);
+            }
+        }
+
+        // Finds the new output driving what `from` drove inside the instance
+        let source = |from: GateId, output_index: usize| match input_ports.iter().position(|&p| p == from) {
+            Some(input) => drivers[input],
+            None => Some((new_ids[&from], output_index)),
+        };
+        let mut connections = vec![];
+        for conn in &inner.connections {
+            if let Some((from, output_index)) = source(conn.from, conn.output_index) {
+                let to = new_ids[&conn.to];
+                connections.push(Connection { from, output_index, to, input_index: conn.input_index });
+            }
+        }
+        for conn in readers {
+            if let Some(port) = output_ports[conn.output_index]
+                && let Some((from, output_index)) = source(port, 0)
+            {
+                connections.push(Connection { from, output_index, ..conn });
+            }
+        }
+        self.netlist_changed(connections.iter().map(|c| c.to).collect::<Vec<_>>());
+        self.connections.extend(connections);
+
+        let mut added: Vec<GateId> = new_ids.into_values().collect();
+        added.sort_unstable();
+        Ok(added)
+    }
+
+    /// Serializes the circuit into a versioned JSON document.
+    pub fn to_json(&self) -> Result<String, FileError> {
+        persistence::write_document(self, &[])
+    }
+
+    /// Parses a circuit from a JSON document, upgrading older format versions.
+    ///
+    /// Editor layout information in the document is ignored.
+    pub fn from_json(json: &str) -> Result<Circuit, FileError> {
+        persistence::read_document(json).map(|(circuit, _)| circuit)
+    }
+
+    /// Saves the circuit, including gate outputs and input values, to a file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), FileError> {
+        persistence::save(path, self, &[])
+    }
+
+    /// Loads a circuit previously written by [`Circuit::save`] or the editor.
+    pub fn load(path: impl AsRef<Path>) -> Result<Circuit, FileError> {
+        persistence::load(path).map(|(circuit, _)| circuit)
+    }
+
+    /// Returns the number of gates in the circuit.
+    pub fn gate_count(&self) -> usize {
+        self.gates.len()
+    }
+
+    /// Returns the ids of all gates in the circuit, in increasing order.
+    pub fn gate_ids(&self) -> impl Iterator<Item = GateId> + '_ {
+        self.gates.keys().copied()
+    }
+
+    /// Returns a number that changes whenever gates or connections are added
+    /// or removed, so callers can tell when results derived from the netlist
+    /// are out of date.
+    ///
+    /// Writing to a memory, from the circuit or from outside, leaves it as is.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Returns `true` if the circuit has a gate with this id.
+    pub fn contains_gate(&self, gate_id: GateId) -> bool {
+        self.gates.contains_key(&gate_id)
+    }
+
+    /// Removes a gate together with every connection to or from it.
+    ///
+    /// The ids of all other gates stay the same. Returns the removed gate.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `gate_id` is out of bounds.
+    pub fn remove_gate(&mut self, gate_id: GateId) -> Gate {
+        or_panic(self.try_remove_gate(gate_id))
+    }
+
+    /// Like [`Circuit::remove_gate`], but reports an unknown gate instead of panicking.
+    pub fn try_remove_gate(&mut self, gate_id: GateId) -> Result<Gate, CircuitError> {
+        let readers = self.readers(gate_id);
+        let gate = self.gates.remove(&gate_id).ok_or(CircuitError::InvalidGate(gate_id))?;
+        self.netlist_changed(readers.into_iter().filter(|&id| id != gate_id));
+        self.dirty.remove(&gate_id);
+        self.connections.retain(|c| c.from != gate_id && c.to != gate_id);
+        self.instances.remove(&gate_id);
+        self.clock_levels.remove(&gate_id);
+        self.memories.remove(&gate_id);
+        self.events.remove_gate(gate_id);
+        Ok(gate)
+    }
+
+    /// Returns the gate with the given `GateId`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `gate_id` is out of bounds.
+    pub fn gate(&self, gate_id: GateId) -> &Gate {
+        or_panic(self.try_gate(gate_id))
+    }
+
+    /// Like [`Circuit::gate`], but reports an unknown gate instead of panicking.
+    pub fn try_gate(&self, gate_id: GateId) -> Result<&Gate, CircuitError> {
+        self.gates.get(&gate_id).ok_or(CircuitError::InvalidGate(gate_id))
+    }
+
+    /// Gives a gate a name, such as the variable an input gate stands for.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `gate_id` is out of bounds.
+    pub fn set_name(&mut self, gate_id: GateId, name: impl Into<String>) {
+        or_panic(self.try_set_name(gate_id, name))
+    }
+
+    /// Like [`Circuit::set_name`], but reports an unknown gate instead of panicking.
+    pub fn try_set_name(&mut self, gate_id: GateId, name: impl Into<String>) -> Result<(), CircuitError> {
+        let gate = self.gates.get_mut(&gate_id).ok_or(CircuitError::InvalidGate(gate_id))?;
+        gate.name = Some(name.into());
+        Ok(())
+    }
+
+    /// Returns the lowest id of a gate with the given name.
+    pub fn find_gate(&self, name: &str) -> Option<GateId> {
+        self.gates
+            .iter()
+            .find(|(_, gate)| gate.name.as_deref() == Some(name))
+            .map(|(&id, _)| id)
+    }
+
+    /// Returns the name of every input port of a gate, in port order.
+    ///
+    /// Storage elements name their inputs after their function and subcircuit
+    /// instances after the ports of their definition; other gates number them
+    /// `In0`, `In1`, ...
+    ///
+    /// # Panics
+    ///
+    /// Panics if `gate_id` is out of bounds.
+    pub fn input_names(&self, gate_id: GateId) -> Vec<String> {
+        or_panic(self.try_input_names(gate_id))
+    }
+
+    /// Like [`Circuit::input_names`], but reports an unknown gate instead of panicking.
+    pub fn try_input_names(&self, gate_id: GateId) -> Result<Vec<String>, CircuitError> {
+        let gate = self.try_gate(gate_id)?;
+        Ok(match gate.gate_type {
+            GateType::FlipFlop(kind) | GateType::Latch(kind) => {
+                kind.input_names().into_iter().map(String::from).collect()
+            }
+            GateType::Memory(spec) => spec.kind.input_names().iter().map(|&name| name.to_string()).collect(),
+            GateType::Subcircuit(id) => self.try_subcircuit(id)?.inputs().iter().map(|p| p.name.clone()).collect(),
+            _ => (0..gate.input_count).map(|i| format!("In{}", i)).collect(),
+        })
+    }
+
+    /// Returns the name of every output port of a gate, in port order.
+    ///
+    /// Storage elements have `Q` and `Q̅`, subcircuit instances the output
+    /// ports of their definition and other gates a single `Out`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `gate_id` is out of bounds.
+    pub fn output_names(&self, gate_id: GateId) -> Vec<String> {
+        or_panic(self.try_output_names(gate_id))
+    }
+
+    /// Like [`Circuit::output_names`], but reports an unknown gate instead of panicking.
+    pub fn try_output_names(&self, gate_id: GateId) -> Result<Vec<String>, CircuitError> {
+        let gate = self.try_gate(gate_id)?;
+        Ok(match gate.gate_type {
+            GateType::FlipFlop(_) | GateType::Latch(_) => storage::OUTPUT_NAMES.map(String::from).to_vec(),
+            GateType::Memory(_) => vec![memory::OUTPUT_NAME.to_string()],
+            GateType::Subcircuit(id) => self.try_subcircuit(id)?.outputs().iter().map(|p| p.name.clone()).collect(),
+            _ if gate.output_count() == 1 => vec!["Out".to_string()],
+            _ => (0..gate.output_count()).map(|i| format!("Out{}", i)).collect(),
+        })
+    }
+
+    /// Checks that every gate and connection of the circuit, and of the
+    /// subcircuits it defines and instantiates, is consistent.
+    ///
+    /// Subcircuit ports must name gates of their definition, input ports
+    /// input gates, and every instance must have the port gates of its
+    /// definition with the same types and widths.
+    ///
+    /// Circuits built through the API are always valid; this is meant for
+    /// circuits read from files, which may have been edited by hand.
+    pub fn validate(&self) -> Result<(), CircuitError> {
+        // Instances are checked against the ports, so those come first
+        for (id, definition) in self.subcircuits.iter().enumerate() {
+            let circuit = definition.circuit();
+            for port in definition.inputs().iter().chain(definition.outputs()) {
+                // An output port exports the first output of its gate
+                if circuit.gates.get(&port.gate).is_none_or(|gate| gate.outputs.is_empty()) {
+                    return Err(CircuitError::InvalidPortGate { subcircuit: id, gate: port.gate });
+                }
+            }
+            let not_input = definition.inputs().iter().find(|p| circuit.gates[&p.gate].gate_type != GateType::Input);
+            if let Some(port) = not_input {
+                return Err(CircuitError::PortNotAnInput { subcircuit: id, gate: port.gate });
+            }
+        }
+        for (&gate_id, gate) in &self.gates {
+            match gate.gate_type {
+                GateType::Subcircuit(id) => {
+                    let definition = self.try_subcircuit(id)?;
+                    if definition.inputs().len() != gate.input_count {
+                        return Err(CircuitError::InvalidInputCount {
+                            gate_type: gate.gate_type,
+                            input_count: gate.input_count,
+                        });
+                    }
+                    // Every instance needs its own copy of the inner circuit
+                    let Some(instance) = self.instances.get(&gate_id) else {
+                        return Err(CircuitError::InvalidSubcircuit(id));
+                    };
+                    let width = |gate: &Gate| gate.outputs.first().map(Bus::width);
+                    for port in definition.inputs().iter().chain(definition.outputs()) {
+                        let expected = &definition.circuit().gates[&port.gate];
+                        let matches = instance.gates.get(&port.gate).is_some_and(|actual| {
+                            actual.gate_type == expected.gate_type && width(actual) == width(expected)
+                        });
+                        if !matches {
+                            return Err(CircuitError::InstanceMismatch { gate: gate_id, port_gate: port.gate });
+                        }
+                    }
+                    instance.validate()?;
+                }
+                gate_type if !gate_type.accepts_input_count(gate.input_count) => {
+                    return Err(CircuitError::InvalidInputCount { gate_type, input_count: gate.input_count });
+                }
+                GateType::Memory(spec) => {
+                    // Contents must be present and match the size of the memory
+                    let fits = self.memories.get(&gate_id).is_some_and(|memory| {
+                        memory.len() == spec.word_count() && memory.data_width() == gate.width
+                    });
+                    if !spec.is_valid(gate.width) || !fits {
+                        return Err(CircuitError::InvalidMemorySize {
+                            address_width: spec.address_width,
+                            data_width: gate.width,
+                        });
+                    }
+                }
+                GateType::Clock(timing) if !timing.is_valid() => {
+                    // A zero period would divide by zero on the next tick
+                    return Err(CircuitError::InvalidClockTiming { period: timing.period, high: timing.high });
+                }
+                _ => {}
+            }
+            if gate.outputs.is_empty() && !matches!(gate.gate_type, GateType::Subcircuit(_)) {
+                return Err(CircuitError::InvalidOutputIndex { gate: gate_id, output_index: 0, output_count: 0 });
+            }
+        }
+        if let Some(&gate_id) = self.gates.keys().find(|&&id| id >= self.next_gate_id) {
+            // The id would be handed out again to the next gate added
+            return Err(CircuitError::InvalidGate(gate_id));
+        }
+        // Inputs with several drivers are tolerated; `contention` reports conflicts
+        for conn in &self.connections {
+            self.check_connection(conn.from, conn.output_index, conn.to, conn.input_index)?;
+        }
+        for definition in &self.subcircuits {
+            definition.circuit().validate()?;
+        }
+        Ok(())
+    }
+
+    /// Returns the compiled evaluation schedule, building it if the netlist
+    /// changed since it was last used.
+    pub fn schedule(&mut self) -> &Schedule {
+        if self.schedule.is_none() {
+            self.schedule = Some(Schedule::build(self));
+        }
+        self.schedule.as_ref().expect("schedule was just built")
+    }
+
+    /// Sorts the gates into levels such that every gate only depends on gates
+    /// of lower levels.
+    ///
+    /// Returns one of the feedback loops if the circuit has any.
+    pub fn levelize(&mut self) -> Result<Vec<Vec<GateId>>, Cycle> {
+        self.schedule().levels().map(<[_]>::to_vec).map_err(Cycle::clone)
     }
 
     /// Evaluate the entire circuit by evaluating all gates in order
+    ///
+    /// Gates are evaluated in the order of the compiled [`Schedule`], each from
+    /// the current outputs of its drivers. This is a single pass. Circuits with
+    /// feedback may need several passes to stabilize; use [`Circuit::settle`]
+    /// for those.
+    ///
+    /// Flip-flops and RAMs whose clock changed act at the end of the pass, all
+    /// on the inputs they had before the edge, as in a synchronous circuit.
     pub fn evaluate(&mut self) {
-        let mut cache = HashMap::new();
+        let order = self.schedule().order().to_vec();
+        let mut edges = BTreeMap::new();
+        for gate_id in order {
+            if self.gates[&gate_id].gate_type == GateType::Input {
+                continue;
+            }
+            if let Some(outputs) = self.evaluate_or_sample(gate_id, &mut edges) {
+                self.gate_mut(gate_id).outputs = outputs;
+            }
+        }
+        // Flip-flops and RAMs sharing a clock all act on the values from before the edge
+        self.commit_edges(edges);
+
+        // Subcircuit instances keep the state reached with the inputs of this pass
+        let instance_ids: Vec<GateId> = self.instances.keys().copied().collect();
+        for gate_id in instance_ids {
+            self.update_instance(gate_id);
+        }
+    }
 
-        for gate_id in 0..self.gates.len() {
-            let output = self.evaluate_gate(gate_id, &mut cache);
-            self.gates[gate_id].output = output;
+    /// Repeatedly evaluates the circuit until no gate output changes.
+    ///
+    /// Returns the number of passes that were needed, or an [`Oscillation`] if the
+    /// circuit is still changing after `max_passes` passes. Gates keep the output
+    /// of the last pass either way, so latches built from feedback loops retain
+    /// their state between calls.
+    pub fn settle(&mut self, max_passes: usize) -> Result<usize, Oscillation> {
+        let mut unstable = vec![];
+
+        for pass in 1..=max_passes {
+            let previous: Vec<Vec<Bus>> = self.gates.values().map(|g| g.outputs.clone()).collect();
+            self.evaluate();
+
+            unstable = self
+                .gates
+                .iter()
+                .zip(&previous)
+                .filter(|((_, gate), outputs)| gate.outputs != **outputs)
+                .map(|((&id, _), _)| id)
+                .collect();
+            if unstable.is_empty() {
+                self.dirty.clear();
+                return Ok(pass);
+            }
         }
+
+        Err(Oscillation {
+            passes: max_passes,
+            unstable,
+        })
+    }
+
+    /// Re-evaluates only the gates affected by changes since the circuit was
+    /// last settled, until no gate output changes.
+    ///
+    /// Changing an input with [`Circuit::set_primary_input_value`] or editing
+    /// the netlist marks the gates that read the change. Those are evaluated in
+    /// schedule order, and a gate whose outputs changed marks its own readers in
+    /// turn, so propagation stops wherever an output stays the same. This gives
+    /// the same result as [`Circuit::settle`] for a circuit that was settled
+    /// before the changes, while touching only the fan-out cone of the changes.
+    ///
+    /// Returns the number of gate evaluations, or an [`Oscillation`] listing the
+    /// gates still pending after `max_passes` evaluations per gate.
+    pub fn propagate(&mut self, max_passes: usize) -> Result<usize, Oscillation> {
+        let limit = max_passes * self.gates.len().max(1);
+        let dirty = std::mem::take(&mut self.dirty);
+        let schedule = self.schedule();
+        let mut pending: BTreeSet<(usize, GateId)> = dirty.into_iter().map(|id| (schedule.rank(id), id)).collect();
+
+        let mut edges = BTreeMap::new();
+        let mut evaluations = 0;
+        loop {
+            let Some((_, gate_id)) = pending.pop_first() else {
+                // Clock edges take effect together once the logic before them is stable
+                if edges.is_empty() {
+                    break;
+                }
+                let changed = self.commit_edges(std::mem::take(&mut edges));
+                let schedule = self.schedule.as_ref().expect("netlist is unchanged");
+                pending.extend(changed.iter().flat_map(|&id| schedule.fanout(id)).map(|&id| (schedule.rank(id), id)));
+                continue;
+            };
+            if evaluations == limit {
+                pending.insert((0, gate_id));
+                // Edges already seen are committed so that they are not lost
+                let changed = self.commit_edges(edges);
+                let mut unstable: Vec<GateId> = pending.into_iter().map(|(_, id)| id).collect();
+                for reader in changed.into_iter().flat_map(|id| self.readers(id)) {
+                    if !unstable.contains(&reader) {
+                        unstable.push(reader);
+                    }
+                }
+                self.dirty.extend(&unstable);
+                return Err(Oscillation {
+                    passes: max_passes,
+                    unstable,
+                });
+            }
+            evaluations += 1;
+
+            if self.gates[&gate_id].gate_type == GateType::Input {
+                continue;
+            }
+            let outputs = if self.instances.contains_key(&gate_id) {
+                Some(self.update_instance(gate_id))
+            } else {
+                self.evaluate_or_sample(gate_id, &mut edges)
+            };
+            if let Some(outputs) = outputs
+                && outputs != self.gates[&gate_id].outputs
+            {
+                self.gate_mut(gate_id).outputs = outputs;
+                let schedule = self.schedule.as_ref().expect("netlist is unchanged");
+                pending.extend(schedule.fanout(gate_id).iter().map(|&id| (schedule.rank(id), id)));
+            }
+        }
+        Ok(evaluations)
+    }
+
+    /// Simulates 64 input vectors at once without changing the circuit.
+    ///
+    /// `inputs` gives the packed values of some input gates; lane `i` of every
+    /// word belongs to vector `i`. Other input gates keep their current value on
+    /// all lanes, and every other gate starts from its current outputs, so
+    /// latches hold their state in each lane. Acyclic circuits are evaluated in
+    /// a single pass over the schedule; circuits with feedback are evaluated
+    /// until no lane changes, for at most `max_passes` passes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a key of `inputs` is not an input gate or its value has the
+    /// wrong width.
+    pub fn simulate_packed(
+        &mut self,
+        inputs: &HashMap<GateId, PackedBus>,
+        max_passes: usize,
+    ) -> Result<PackedOutputs, Oscillation> {
+        or_panic(self.try_simulate_packed(inputs, max_passes))
+    }
+
+    /// Like [`Circuit::simulate_packed`], but reports invalid inputs instead of
+    /// panicking. The inner result reports an oscillation.
+    pub fn try_simulate_packed(
+        &mut self,
+        inputs: &HashMap<GateId, PackedBus>,
+        max_passes: usize,
+    ) -> Result<Result<PackedOutputs, Oscillation>, CircuitError> {
+        for (&gate_id, value) in inputs {
+            let gate = self.try_gate(gate_id)?;
+            if gate.gate_type != GateType::Input {
+                return Err(CircuitError::NotAnInput(gate_id));
+            }
+            if value.len() != gate.width {
+                return Err(CircuitError::InputWidthMismatch {
+                    gate: gate_id,
+                    expected: gate.width,
+                    actual: value.len(),
+                });
+            }
+        }
+        Ok(match self.run_packed(inputs, max_passes) {
+            (values, None) => Ok(values),
+            (_, Some(oscillation)) => Err(oscillation),
+        })
+    }
+
+    /// Tabulates `outputs` for every combination of values of the circuit's
+    /// input gates, taken in increasing id order.
+    ///
+    /// Fails if an output gate does not exist, if the inputs have more than
+    /// [`MAX_INPUT_BITS`](crate::truth_table::MAX_INPUT_BITS) bits in total, or if the circuit
+    /// oscillates for some combination.
+    pub fn truth_table(&mut self, outputs: &[GateId]) -> Result<TruthTable, TruthTableError> {
+        let inputs: Vec<GateId> = self
+            .gates
+            .iter()
+            .filter(|(_, gate)| gate.gate_type == GateType::Input)
+            .map(|(&id, _)| id)
+            .collect();
+        TruthTable::generate(self, &inputs, outputs)
+    }
+
+    /// Computes a minimal sum of products for each bit of a gate's output, as a
+    /// function of all input gates of the circuit.
+    ///
+    /// Fails under the same conditions as [`Circuit::truth_table`].
+    pub fn minimize(&mut self, gate_id: GateId) -> Result<Vec<SumOfProducts>, TruthTableError> {
+        let table = self.truth_table(&[gate_id])?;
+        let width = self.gate(gate_id).width;
+        Ok((0..width).map(|bit| SumOfProducts::from_truth_table(&table, 0, bit)).collect())
+    }
+
+    /// Builds BDDs for the first output of each gate in `outputs`.
+    ///
+    /// See [`CircuitBdds::build`] for the circuits that are supported.
+    pub fn bdds(&self, outputs: &[GateId], order: VariableOrder) -> Result<CircuitBdds, BddError> {
+        CircuitBdds::build(self, outputs, order)
+    }
+
+    /// Applies test vectors one after another and compares the outputs with
+    /// the expected values.
+    ///
+    /// See [`vectors::run`] for details.
+    pub fn run_vectors(&mut self, vectors: &[TestVector]) -> Result<Report, VectorError> {
+        vectors::run(self, vectors)
+    }
+
+    /// Decides whether this circuit computes the same function as `other`,
+    /// matching inputs and outputs by gate name.
+    ///
+    /// See [`equivalence::check`] for how the circuits are compared.
+    pub fn check_equivalence(&mut self, other: &mut Circuit) -> Result<Equivalence, EquivalenceError> {
+        equivalence::check(self, other)
+    }
+
+    /// Encodes the circuit as a CNF formula for SAT solvers.
+    ///
+    /// See [`Cnf::encode`] for the encoding.
+    pub fn to_cnf(&self) -> Result<Cnf, CnfError> {
+        Cnf::encode(self)
+    }
+
+    /// Derives the boolean expression computed by a gate from its fan-in.
+    ///
+    /// See [`Expr::from_gate`] for the gates that can be expressed.
+    pub fn expression(&self, gate_id: GateId) -> Result<Expr, ExpressionError> {
+        Expr::from_gate(self, gate_id)
     }
 
     /// Set the output value of an input gate
-    pub fn set_primary_input_value(&mut self, gate_id: GateId, value: bool) {
-        if self.gates[gate_id].gate_type == GateType::Input {
-            self.gates[gate_id].output = value;
-        } else {
-            panic!("Gate {} is not an input gate", gate_id);
+    ///
+    /// Accepts a `Bus` matching the gate's width, or a `Logic` value or plain
+    /// `bool` for single-bit inputs.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the gate is not an input gate or the value has the wrong width.
+    pub fn set_primary_input_value(&mut self, gate_id: GateId, value: impl Into<Bus>) {
+        or_panic(self.try_set_primary_input_value(gate_id, value))
+    }
+
+    /// Like [`Circuit::set_primary_input_value`], but reports errors instead of panicking.
+    pub fn try_set_primary_input_value(&mut self, gate_id: GateId, value: impl Into<Bus>) -> Result<(), CircuitError> {
+        let value = value.into();
+        let gate = self.gates.get_mut(&gate_id).ok_or(CircuitError::InvalidGate(gate_id))?;
+        if gate.gate_type != GateType::Input {
+            return Err(CircuitError::NotAnInput(gate_id));
         }
+        if value.width() != gate.width {
+            return Err(CircuitError::InputWidthMismatch {
+                gate: gate_id,
+                expected: gate.width,
+                actual: value.width(),
+            });
+        }
+        if gate.outputs[0] != value {
+            gate.outputs[0] = value;
+            let readers = self.readers(gate_id);
+            self.dirty.extend(readers);
+        }
+        Ok(())
     }
 
     /// Returns the output value of the gate with the given `GateId`.
@@ -94,25 +1212,916 @@ impl Circuit {
     ///
     /// # Returns
     ///
-    /// Boolean value representing the gate's output.
+    /// Bus holding the gate's (first) output, one bit wide for ordinary gates.
+    /// Bits of gates that have not been evaluated yet are `Logic::X`.
     ///
     /// # Panics
     ///
     /// Panics if `gate_id` is out of bounds.
-    pub fn get_output(&self, gate_id: GateId) -> bool {
-        self.gates[gate_id].output
+    pub fn get_output(&self, gate_id: GateId) -> Bus {
+        self.get_port_output(gate_id, 0)
+    }
+
+    /// Like [`Circuit::get_output`], but reports an unknown gate instead of panicking.
+    pub fn try_get_output(&self, gate_id: GateId) -> Result<Bus, CircuitError> {
+        self.try_get_port_output(gate_id, 0)
+    }
+
+    /// Returns the value of output `output_index` of a gate.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `gate_id` or `output_index` is out of bounds.
+    pub fn get_port_output(&self, gate_id: GateId, output_index: usize) -> Bus {
+        or_panic(self.try_get_port_output(gate_id, output_index))
+    }
+
+    /// Like [`Circuit::get_port_output`], but reports invalid ids or indices instead of panicking.
+    pub fn try_get_port_output(&self, gate_id: GateId, output_index: usize) -> Result<Bus, CircuitError> {
+        let gate = self.try_gate(gate_id)?;
+        gate.outputs.get(output_index).cloned().ok_or(CircuitError::InvalidOutputIndex {
+            gate: gate_id,
+            output_index,
+            output_count: gate.outputs.len(),
+        })
+    }
+
+    /// Returns `true` if the output width of `from` fits the inputs of `to`.
+    pub fn widths_match(&self, from: GateId, to: GateId) -> bool {
+        self.port_widths_match(from, 0, to, 0)
+    }
+
+    /// Returns `true` if output `output_index` of `from` fits input `input_index` of `to`.
+    ///
+    /// Returns `false` if either gate or port does not exist.
+    pub fn port_widths_match(&self, from: GateId, output_index: usize, to: GateId, input_index: usize) -> bool {
+        let Some(width) = self.gates.get(&from).and_then(|g| g.outputs.get(output_index)).map(Bus::width) else {
+            return false;
+        };
+        match self.gates.get(&to).map(|g| g.gate_type) {
+            Some(GateType::Subcircuit(id)) => self
+                .subcircuits
+                .get(id)
+                .is_some_and(|def| def.input_widths().get(input_index) == Some(&width)),
+            Some(_) => self.gates[&to].accepts_port_width(input_index, width),
+            None => false,
+        }
+    }
+
+    /// Checks that output `output_index` of `from` can drive input `input_index` of `to`.
+    fn check_connection(
+        &self,
+        from: GateId,
+        output_index: usize,
+        to: GateId,
+        input_index: usize,
+    ) -> Result<(), CircuitError> {
+        let width = self.try_get_port_output(from, output_index)?.width();
+        let input_count = self.try_gate(to)?.input_count;
+        if input_index >= input_count {
+            return Err(CircuitError::InvalidInputIndex { gate: to, input_index, input_count });
+        }
+        if !self.port_widths_match(from, output_index, to, input_index) {
+            return Err(CircuitError::ConnectionWidthMismatch { from, output_index, width, to, input_index });
+        }
+        Ok(())
     }
 
+    /// Connects the output of `from` to input `input_index` of `to`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either gate does not exist, `to` has no input `input_index`,
+    /// the input is already driven, or the width of `from`'s output does not
+    /// match the input of `to`.
     pub fn connect(&mut self, from: GateId, to: GateId, input_index: usize) {
+        self.connect_port(from, 0, to, input_index);
+    }
+
+    /// Like [`Circuit::connect`], but reports errors instead of panicking.
+    pub fn try_connect(&mut self, from: GateId, to: GateId, input_index: usize) -> Result<(), CircuitError> {
+        self.try_connect_port(from, 0, to, input_index)
+    }
+
+    /// Connects output `output_index` of `from` to input `input_index` of `to`.
+    ///
+    /// Gates with several outputs, such as subcircuit instances, use this to
+    /// choose which output drives the connection. Every input has at most one
+    /// driver; use [`Circuit::replace_driver`] to rewire an input that is
+    /// already connected.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either gate or port does not exist, the input is already
+    /// driven, or the width of the output does not match the input of `to`.
+    pub fn connect_port(&mut self, from: GateId, output_index: usize, to: GateId, input_index: usize) {
+        or_panic(self.try_connect_port(from, output_index, to, input_index))
+    }
+
+    /// Like [`Circuit::connect_port`], but reports errors instead of panicking.
+    pub fn try_connect_port(
+        &mut self,
+        from: GateId,
+        output_index: usize,
+        to: GateId,
+        input_index: usize,
+    ) -> Result<(), CircuitError> {
+        self.check_connection(from, output_index, to, input_index)?;
+        if let Some(existing) = self.driver(to, input_index) {
+            return Err(CircuitError::InputAlreadyDriven { to, input_index, driver: existing.from });
+        }
+        self.netlist_changed([to]);
+        self.connections.push(Connection {
+            from,
+            output_index,
+            to,
+            input_index,
+        });
+        Ok(())
+    }
+
+    /// Connects output `output_index` of `from` to input `input_index` of `to`,
+    /// disconnecting whatever drove that input before.
+    ///
+    /// Returns the connection that was replaced, if any.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either gate or port does not exist, or the width of the output
+    /// does not match the input of `to`.
+    pub fn replace_driver(
+        &mut self,
+        from: GateId,
+        output_index: usize,
+        to: GateId,
+        input_index: usize,
+    ) -> Option<Connection> {
+        or_panic(self.try_replace_driver(from, output_index, to, input_index))
+    }
+
+    /// Like [`Circuit::replace_driver`], but reports errors instead of panicking.
+    pub fn try_replace_driver(
+        &mut self,
+        from: GateId,
+        output_index: usize,
+        to: GateId,
+        input_index: usize,
+    ) -> Result<Option<Connection>, CircuitError> {
+        self.check_connection(from, output_index, to, input_index)?;
+        let replaced = self.driver(to, input_index).cloned();
+        self.netlist_changed([to]);
+        self.connections.retain(|c| c.to != to || c.input_index != input_index);
         self.connections.push(Connection {
             from,
+            output_index,
             to,
             input_index,
         });
+        Ok(replaced)
+    }
+
+    /// Returns the connection driving input `input_index` of `gate_id`, if any.
+    pub fn driver(&self, gate_id: GateId, input_index: usize) -> Option<&Connection> {
+        self.connections
+            .iter()
+            .find(|c| c.to == gate_id && c.input_index == input_index)
+    }
+
+    /// Reports every input whose drivers currently pull some bit both to `0` and to `1`.
+    pub fn contention(&self) -> Vec<Contention> {
+        let mut nets: BTreeMap<(GateId, usize), Vec<(GateId, usize)>> = BTreeMap::new();
+        for conn in &self.connections {
+            nets.entry((conn.to, conn.input_index))
+                .or_default()
+                .push((conn.from, conn.output_index));
+        }
+
+        nets.into_iter()
+            .filter(|(_, drivers)| drivers.len() > 1)
+            .filter(|(_, drivers)| {
+                let values: Vec<&Bus> = drivers
+                    .iter()
+                    .map(|&(gate, output)| &self.gates[&gate].outputs[output])
+                    .collect();
+                let width = values.iter().map(|v| v.width()).max().unwrap_or(0);
+                (0..width).any(|i| {
+                    values.iter().any(|v| v.bit(i) == Logic::Zero) && values.iter().any(|v| v.bit(i) == Logic::One)
+                })
+            })
+            .map(|((gate, input_index), drivers)| Contention {
+                gate,
+                input_index,
+                drivers,
+            })
+            .collect()
+    }
+
+    /// Removes the connection from the output of `from` to input `input_index` of `to`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no such connection.
+    pub fn disconnect(&mut self, from: GateId, to: GateId, input_index: usize) {
+        self.disconnect_port(from, 0, to, input_index);
+    }
+
+    /// Like [`Circuit::disconnect`], but reports a missing connection instead of panicking.
+    pub fn try_disconnect(&mut self, from: GateId, to: GateId, input_index: usize) -> Result<(), CircuitError> {
+        self.try_disconnect_port(from, 0, to, input_index)
+    }
+
+    /// Removes the connection from output `output_index` of `from` to input `input_index` of `to`.
+    ///
+    /// The input is left floating. Both gates keep their ids and other connections.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no such connection.
+    pub fn disconnect_port(&mut self, from: GateId, output_index: usize, to: GateId, input_index: usize) {
+        or_panic(self.try_disconnect_port(from, output_index, to, input_index))
+    }
+
+    /// Like [`Circuit::disconnect_port`], but reports a missing connection instead of panicking.
+    pub fn try_disconnect_port(
+        &mut self,
+        from: GateId,
+        output_index: usize,
+        to: GateId,
+        input_index: usize,
+    ) -> Result<(), CircuitError> {
+        let connection = Connection {
+            from,
+            output_index,
+            to,
+            input_index,
+        };
+        let count = self.connections.len();
+        self.connections.retain(|c| *c != connection);
+        if self.connections.len() == count {
+            return Err(CircuitError::NotConnected { from, output_index, to, input_index });
+        }
+        self.netlist_changed([to]);
+        Ok(())
     }
 
     pub fn connections(&self) -> Vec<(GateId, GateId, usize)> {
         self.connections.iter().map(|c| (c.from, c.to, c.input_index)).collect()
     }
 
+    /// Returns every connection including the output port it starts from.
+    pub fn port_connections(&self) -> &[Connection] {
+        &self.connections
+    }
+
+    /// Sets the propagation delay of a gate used by the event-driven simulation.
+    ///
+    /// A delay of zero makes the gate react within the same time step, so a
+    /// feedback loop made only of zero-delay gates can keep [`Circuit::run_until`]
+    /// busy forever.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `gate_id` is out of bounds.
+    pub fn set_delay(&mut self, gate_id: GateId, delay: u64) {
+        or_panic(self.try_set_delay(gate_id, delay))
+    }
+
+    /// Like [`Circuit::set_delay`], but reports an unknown gate instead of panicking.
+    pub fn try_set_delay(&mut self, gate_id: GateId, delay: u64) -> Result<(), CircuitError> {
+        let gate = self.gates.get_mut(&gate_id).ok_or(CircuitError::InvalidGate(gate_id))?;
+        gate.delay = delay;
+        Ok(())
+    }
+
+    /// Returns the current time of the event-driven simulation.
+    pub fn now(&self) -> u64 {
+        self.time
+    }
+
+    /// Schedules an input gate to change its value at `time`.
+    ///
+    /// The change is applied by [`Circuit::step`] or [`Circuit::run_until`], which
+    /// then schedule the affected gates to change at `time + delay`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the gate is not an input gate, the value has the wrong width,
+    /// or `time` lies in the past.
+    pub fn schedule_input(&mut self, gate_id: GateId, value: impl Into<Bus>, time: u64) {
+        or_panic(self.try_schedule_input(gate_id, value, time))
+    }
+
+    /// Like [`Circuit::schedule_input`], but reports errors instead of panicking.
+    pub fn try_schedule_input(&mut self, gate_id: GateId, value: impl Into<Bus>, time: u64) -> Result<(), CircuitError> {
+        let value = value.into();
+        let gate = self.try_gate(gate_id)?;
+        if gate.gate_type != GateType::Input {
+            return Err(CircuitError::NotAnInput(gate_id));
+        }
+        if value.width() != gate.width {
+            return Err(CircuitError::InputWidthMismatch {
+                gate: gate_id,
+                expected: gate.width,
+                actual: value.width(),
+            });
+        }
+        if time < self.time {
+            return Err(CircuitError::EventInPast { time, now: self.time });
+        }
+        self.events.push(time, gate_id, 0, value);
+        Ok(())
+    }
+
+    /// Returns `true` if there are events left to process.
+    pub fn has_pending_events(&self) -> bool {
+        !self.events.is_empty()
+    }
+
+    /// Processes all events scheduled for the earliest pending time.
+    ///
+    /// The simulation time advances to that point. Gates whose inputs changed are
+    /// re-evaluated and their new output is scheduled after their delay. Returns
+    /// the time that was processed, or `None` if no events are pending.
+    pub fn step(&mut self) -> Option<u64> {
+        let time = self.events.next_time()?;
+        self.time = time;
+        self.schedule();
+
+        // The last event scheduled for an output at this time determines its value
+        let mut updates = BTreeMap::new();
+        while let Some(event) = self.events.pop_at(time) {
+            updates.insert((event.gate, event.output_index), event.value);
+        }
+
+        let mut changed = vec![];
+        for ((gate_id, output_index), value) in updates {
+            let outputs = &mut self.gate_mut(gate_id).outputs;
+            if outputs[output_index] != value {
+                outputs[output_index] = value.clone();
+                self.history.push(Transition { time, gate: gate_id, output_index, value });
+                changed.push(gate_id);
+            }
+        }
+
+        let mut fanout: Vec<GateId> = changed.iter().flat_map(|&id| self.readers(id)).collect();
+        fanout.sort_unstable();
+        fanout.dedup();
+
+        for gate_id in fanout {
+            let outputs = if self.instances.contains_key(&gate_id) {
+                self.update_instance(gate_id)
+            } else {
+                let inputs = self.current_inputs(gate_id);
+                self.store_writes(gate_id, &inputs);
+                let outputs = self.compute_outputs(gate_id, &inputs);
+                self.record_clock(gate_id, &inputs);
+                outputs
+            };
+            let at = time + self.gates[&gate_id].delay;
+            for (output_index, output) in outputs.into_iter().enumerate() {
+                self.events.push(at, gate_id, output_index, output);
+            }
+        }
+
+        Some(time)
+    }
+
+    /// Processes all events scheduled up to and including `time`, then advances
+    /// the simulation time to `time`.
+    pub fn run_until(&mut self, time: u64) {
+        while self.events.next_time().is_some_and(|t| t <= time) {
+            self.step();
+        }
+        self.time = self.time.max(time);
+    }
+
+    /// Returns every output change applied by the event-driven simulation so far.
+    pub fn history(&self) -> &[Transition] {
+        &self.history
+    }
+
+    /// Clears the recorded output changes.
+    pub fn clear_history(&mut self) {
+        self.history.clear();
+    }
+
+    /// Returns the number of clock ticks since the circuit was created or loaded.
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    /// Changes the period and high time of a clock gate.
+    ///
+    /// The clock takes its level for the current tick right away.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the gate is not a clock or the timing is invalid.
+    pub fn set_clock_timing(&mut self, gate_id: GateId, timing: ClockTiming) {
+        or_panic(self.try_set_clock_timing(gate_id, timing))
+    }
+
+    /// Like [`Circuit::set_clock_timing`], but reports errors instead of panicking.
+    pub fn try_set_clock_timing(&mut self, gate_id: GateId, timing: ClockTiming) -> Result<(), CircuitError> {
+        if !matches!(self.try_gate(gate_id)?.gate_type, GateType::Clock(_)) {
+            return Err(CircuitError::NotAClock(gate_id));
+        }
+        if !timing.is_valid() {
+            return Err(CircuitError::InvalidClockTiming { period: timing.period, high: timing.high });
+        }
+        self.gate_mut(gate_id).gate_type = GateType::Clock(timing);
+        self.netlist_changed([]);
+        self.update_clocks();
+        Ok(())
+    }
+
+    /// Advances every clock by `count` ticks.
+    ///
+    /// After each tick the clocks take their level for the new tick and the
+    /// changes are propagated as by [`Circuit::propagate`], so storage elements
+    /// see every clock edge. Ticks are independent of the time of the
+    /// event-driven simulation, and clocks inside subcircuit definitions do
+    /// not tick.
+    pub fn tick(&mut self, count: u64) -> Result<(), Oscillation> {
+        for _ in 0..count {
+            self.ticks += 1;
+            self.update_clocks();
+            self.propagate(DEFAULT_MAX_PASSES)?;
+        }
+        Ok(())
+    }
+
+    /// Runs `cycles` periods of a clock and returns the values of `outputs` at
+    /// the end of each period.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `clock` is not a clock gate or an output gate does not exist.
+    pub fn run_cycles(
+        &mut self,
+        clock: GateId,
+        cycles: u64,
+        outputs: &[GateId],
+    ) -> Result<Vec<Vec<Bus>>, Oscillation> {
+        or_panic(self.try_run_cycles(clock, cycles, outputs))
+    }
+
+    /// Like [`Circuit::run_cycles`], but reports an invalid clock or output
+    /// gate instead of panicking. The inner result reports an oscillation.
+    pub fn try_run_cycles(
+        &mut self,
+        clock: GateId,
+        cycles: u64,
+        outputs: &[GateId],
+    ) -> Result<Result<Vec<Vec<Bus>>, Oscillation>, CircuitError> {
+        let GateType::Clock(timing) = self.try_gate(clock)?.gate_type else {
+            return Err(CircuitError::NotAClock(clock));
+        };
+        for &gate_id in outputs {
+            self.try_gate(gate_id)?;
+        }
+        let mut samples = vec![];
+        for _ in 0..cycles {
+            if let Err(oscillation) = self.tick(timing.period) {
+                return Ok(Err(oscillation));
+            }
+            samples.push(outputs.iter().map(|&gate_id| self.get_output(gate_id)).collect());
+        }
+        Ok(Ok(samples))
+    }
+
+    /// Returns the contents of a ROM or RAM.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `gate_id` is not a memory.
+    pub fn memory(&self, gate_id: GateId) -> &Memory {
+        or_panic(self.try_memory(gate_id))
+    }
+
+    /// Like [`Circuit::memory`], but reports an unknown gate or one that is
+    /// not a memory instead of panicking.
+    pub fn try_memory(&self, gate_id: GateId) -> Result<&Memory, CircuitError> {
+        self.try_gate(gate_id)?;
+        self.memories.get(&gate_id).ok_or(CircuitError::NotAMemory(gate_id))
+    }
+
+    /// Stores a word in a ROM or RAM from outside the circuit, such as from a
+    /// memory inspector.
+    ///
+    /// The memory's output changes on the next [`Circuit::propagate`] or
+    /// [`Circuit::settle`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `gate_id` is not a memory, it has no word at `address` or the
+    /// value is not as wide as a word.
+    pub fn write_memory(&mut self, gate_id: GateId, address: usize, value: impl Into<Bus>) {
+        or_panic(self.try_write_memory(gate_id, address, value))
+    }
+
+    /// Like [`Circuit::write_memory`], but reports errors instead of panicking.
+    pub fn try_write_memory(
+        &mut self,
+        gate_id: GateId,
+        address: usize,
+        value: impl Into<Bus>,
+    ) -> Result<(), CircuitError> {
+        let value = value.into();
+        let contents = self.try_memory(gate_id)?;
+        if address >= contents.len() {
+            return Err(CircuitError::InvalidAddress { gate: gate_id, address, word_count: contents.len() });
+        }
+        if value.width() != contents.data_width() {
+            return Err(CircuitError::WordWidthMismatch {
+                gate: gate_id,
+                expected: contents.data_width(),
+                actual: value.width(),
+            });
+        }
+        self.memories.get_mut(&gate_id).expect("memory contents exist").set_word(address, value);
+        self.memory_changed(gate_id);
+        Ok(())
+    }
+
+    /// Replaces the contents of a ROM or RAM with an image in the given format;
+    /// see [`Memory::load`].
+    ///
+    /// Returns the number of words the image sets. The memory's output changes
+    /// on the next [`Circuit::propagate`] or [`Circuit::settle`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `gate_id` is not a memory.
+    pub fn load_memory(&mut self, gate_id: GateId, format: ImageFormat, bytes: &[u8]) -> Result<usize, ImageError> {
+        self.try_load_memory(gate_id, format, bytes).map_err(|err| match err {
+            LoadError::Circuit(err) => or_panic(Err(err)),
+            LoadError::Image(err) => err,
+        })
+    }
+
+    /// Like [`Circuit::load_memory`], but reports a gate that is not a memory
+    /// instead of panicking.
+    pub fn try_load_memory(&mut self, gate_id: GateId, format: ImageFormat, bytes: &[u8]) -> Result<usize, LoadError> {
+        self.try_memory(gate_id)?;
+        let loaded = self.memories.get_mut(&gate_id).expect("memory contents exist").load(format, bytes)?;
+        self.memory_changed(gate_id);
+        Ok(loaded)
+    }
+
+    /// Evaluates packed values until they are stable, returning the last values
+    /// even if the circuit oscillates.
+    fn run_packed(
+        &mut self,
+        inputs: &HashMap<GateId, PackedBus>,
+        max_passes: usize,
+    ) -> (PackedOutputs, Option<Oscillation>) {
+        let mut values: PackedOutputs = self
+            .gates
+            .iter()
+            .map(|(&id, gate)| {
+                let outputs = match inputs.get(&id) {
+                    Some(value) => vec![value.clone()],
+                    None => gate.outputs.iter().map(packed::splat_bus).collect(),
+                };
+                (id, outputs)
+            })
+            .collect();
+        let schedule = self.schedule();
+        let order = schedule.order().to_vec();
+        let acyclic = schedule.levels().is_ok();
+        // Clock level each flip-flop was last evaluated with, per lane
+        let mut clocks: HashMap<GateId, PackedLogic> = self
+            .clock_levels
+            .iter()
+            .map(|(&id, &level)| (id, PackedLogic::splat(level)))
+            .collect();
+
+        let mut unstable = vec![];
+        for _ in 0..max_passes {
+            unstable.clear();
+            // Flip-flops whose clock changed in some lane, with the clock they had before
+            let mut edges = vec![];
+            for &gate_id in &order {
+                if self.gates[&gate_id].gate_type == GateType::Input {
+                    continue;
+                }
+                let gate_inputs = self.packed_inputs(gate_id, &values);
+                let outputs = match self.gates[&gate_id].gate_type {
+                    GateType::FlipFlop(kind) => {
+                        let clock = gate_inputs[kind.clock_index()][0];
+                        // A flip-flop evaluated for the first time sees no edge
+                        match clocks.insert(gate_id, clock) {
+                            Some(previous) if previous != clock => {
+                                edges.push((gate_id, previous));
+                                continue;
+                            }
+                            _ => self.packed_storage_outputs(gate_id, &gate_inputs, clock, &values[&gate_id][0]),
+                        }
+                    }
+                    GateType::Latch(kind) => {
+                        // Latches ignore the previous clock
+                        let clock = gate_inputs[kind.clock_index()][0];
+                        self.packed_storage_outputs(gate_id, &gate_inputs, clock, &values[&gate_id][0])
+                    }
+                    // Packed simulation only reads memories; writes would differ per lane
+                    GateType::Memory(_) => packed::map_lanes(&gate_inputs, |_, lane_inputs| {
+                        vec![memory::read_port(&self.memories[&gate_id], lane_inputs)]
+                    }),
+                    _ => self.packed_outputs(gate_id, &gate_inputs, max_passes),
+                };
+                if values[&gate_id] != outputs {
+                    values.insert(gate_id, outputs);
+                    unstable.push(gate_id);
+                }
+            }
+            // As in `commit_edges`, every flip-flop reads its inputs before any of them changes
+            let sampled: Vec<(GateId, Vec<PackedBus>, PackedLogic)> = edges
+                .into_iter()
+                .map(|(gate_id, previous)| (gate_id, self.packed_inputs(gate_id, &values), previous))
+                .collect();
+            let mut committed = false;
+            for (gate_id, gate_inputs, previous) in sampled {
+                let outputs = self.packed_storage_outputs(gate_id, &gate_inputs, previous, &values[&gate_id][0]);
+                if values[&gate_id] != outputs {
+                    values.insert(gate_id, outputs);
+                    unstable.push(gate_id);
+                    committed = true;
+                }
+            }
+            // A single pass in schedule order already settles an acyclic circuit,
+            // unless flip-flops changed at its end
+            if (acyclic && !committed) || unstable.is_empty() {
+                return (values, None);
+            }
+        }
+        let oscillation = Oscillation {
+            passes: max_passes,
+            unstable,
+        };
+        (values, Some(oscillation))
+    }
+
+    /// Packed counterpart of `current_inputs`, reading drivers from `values`.
+    fn packed_inputs(&self, gate_id: GateId, values: &PackedOutputs) -> Vec<PackedBus> {
+        let mut inputs: Vec<PackedBus> = self.floating_inputs(gate_id).iter().map(packed::splat_bus).collect();
+        for conn in self.drivers(gate_id) {
+            let value = &values[&conn.from][conn.output_index];
+            let input = &mut inputs[conn.input_index];
+            let width = input.len().max(value.len());
+            *input = (0..width)
+                .map(|i| {
+                    let bit = |bus: &PackedBus| bus.get(i).copied().unwrap_or(PackedLogic::Z);
+                    bit(input).resolve(bit(value))
+                })
+                .collect();
+        }
+        inputs
+    }
+
+    /// Packed counterpart of `compute_outputs`.
+    fn packed_outputs(&mut self, gate_id: GateId, inputs: &[PackedBus], max_passes: usize) -> Vec<PackedBus> {
+        match self.gates[&gate_id].gate_type {
+            GateType::Subcircuit(id) => {
+                let ports: HashMap<GateId, PackedBus> = self.subcircuits[id]
+                    .inputs()
+                    .iter()
+                    .zip(inputs)
+                    .map(|(port, value)| (port.gate, value.clone()))
+                    .collect();
+                let mut instance = self.instances.remove(&gate_id).expect("instance state exists");
+                // An oscillating subcircuit keeps the outputs of its last pass
+                let (values, _) = instance.run_packed(&ports, max_passes);
+                self.instances.insert(gate_id, instance);
+                self.subcircuits[id]
+                    .outputs()
+                    .iter()
+                    .map(|port| values[&port.gate][0].clone())
+                    .collect()
+            }
+            _ => vec![self.gates[&gate_id].evaluate_packed_bus(inputs)],
+        }
+    }
+
+    /// Sets every clock to its level for the current tick and marks the
+    /// readers of those that changed for re-evaluation.
+    fn update_clocks(&mut self) {
+        let mut changed = vec![];
+        for (&gate_id, gate) in &mut self.gates {
+            if let GateType::Clock(timing) = gate.gate_type {
+                let level = Bus::filled(gate.width, timing.level(self.ticks));
+                if gate.outputs[0] != level {
+                    gate.outputs[0] = level;
+                    changed.push(gate_id);
+                }
+            }
+        }
+        for gate_id in changed {
+            let readers = self.readers(gate_id);
+            self.dirty.extend(readers);
+        }
+    }
+
+    /// Packed counterpart of [`Gate::evaluate_storage`], evaluating each lane
+    /// from its own state `q`.
+    fn packed_storage_outputs(
+        &self,
+        gate_id: GateId,
+        inputs: &[PackedBus],
+        previous_clock: PackedLogic,
+        q: &PackedBus,
+    ) -> Vec<PackedBus> {
+        let (kind, edge_triggered) = match self.gates[&gate_id].gate_type {
+            GateType::FlipFlop(kind) => (kind, true),
+            GateType::Latch(kind) => (kind, false),
+            _ => unreachable!("gate {} is not a storage element", gate_id),
+        };
+        packed::map_lanes(inputs, |lane, lane_inputs| {
+            let state = packed::lane_bus(q, lane);
+            storage::next_outputs(kind, edge_triggered, lane_inputs, previous_clock.lane(lane), &state)
+        })
+    }
+
+    /// Stores a new gate under the next unused id and returns that id.
+    fn insert_gate(&mut self, gate: Gate) -> GateId {
+        let gate_id = self.next_gate_id;
+        self.next_gate_id += 1;
+        self.gates.insert(gate_id, gate);
+        self.netlist_changed([gate_id]);
+        gate_id
+    }
+
+    /// Marks a memory whose contents changed from outside the circuit for
+    /// re-evaluation.
+    fn memory_changed(&mut self, gate_id: GateId) {
+        self.dirty.insert(gate_id);
+    }
+
+    /// Returns the gate with the given id for modification; the id must exist.
+    fn gate_mut(&mut self, gate_id: GateId) -> &mut Gate {
+        self.gates.get_mut(&gate_id).expect("gate exists")
+    }
+
+    /// Discards the compiled schedule and marks `gates` for re-evaluation by
+    /// [`Circuit::propagate`].
+    fn netlist_changed(&mut self, gates: impl IntoIterator<Item = GateId>) {
+        self.schedule = None;
+        self.revision += 1;
+        self.dirty.extend(gates);
+    }
+
+    /// Connections driving the inputs of a gate, from the schedule if it is compiled.
+    fn drivers(&self, gate_id: GateId) -> impl Iterator<Item = &Connection> {
+        let compiled = self.schedule.as_ref().map(|s| s.fanin(gate_id));
+        let scanned = compiled
+            .is_none()
+            .then(|| self.connections.iter().filter(move |c| c.to == gate_id));
+        compiled.into_iter().flatten().chain(scanned.into_iter().flatten())
+    }
+
+    /// Gates reading an output of `gate_id`, from the schedule if it is compiled.
+    fn readers(&self, gate_id: GateId) -> Vec<GateId> {
+        match &self.schedule {
+            Some(schedule) => schedule.fanout(gate_id).to_vec(),
+            None => self.connections.iter().filter(|c| c.from == gate_id).map(|c| c.to).collect(),
+        }
+    }
+
+    /// Reads the current input values of a gate from the outputs driving it.
+    fn current_inputs(&self, gate_id: GateId) -> Vec<Bus> {
+        let mut inputs = self.floating_inputs(gate_id);
+        for conn in self.drivers(gate_id) {
+            let value = &self.gates[&conn.from].outputs[conn.output_index];
+            inputs[conn.input_index] = inputs[conn.input_index].resolve(value);
+        }
+        inputs
+    }
+
+    /// Returns the values seen on the inputs of a gate when nothing is connected to them.
+    fn floating_inputs(&self, gate_id: GateId) -> Vec<Bus> {
+        let gate = &self.gates[&gate_id];
+        if let GateType::Subcircuit(id) = gate.gate_type {
+            return self.subcircuits[id]
+                .input_widths()
+                .into_iter()
+                .map(|width| Bus::filled(width, Logic::Z))
+                .collect();
+        }
+        (0..gate.input_count)
+            .map(|input_index| {
+                let width = gate.input_port_width(input_index).unwrap_or(gate.offset + gate.width);
+                Bus::filled(width, Logic::Z)
+            })
+            .collect()
+    }
+
+    /// Computes the outputs of a gate from the given input values without changing any state.
+    fn compute_outputs(&self, gate_id: GateId, inputs: &[Bus]) -> Vec<Bus> {
+        match self.gates[&gate_id].gate_type {
+            GateType::Subcircuit(id) => {
+                let mut instance = self.instances[&gate_id].clone();
+                run_instance(&mut instance, &self.subcircuits[id], inputs)
+            }
+            GateType::FlipFlop(kind) | GateType::Latch(kind) => {
+                // A flip-flop evaluated for the first time sees no edge
+                let clock = inputs[kind.clock_index()].bit(0);
+                let previous = self.clock_levels.get(&gate_id).copied().unwrap_or(clock);
+                self.gates[&gate_id].evaluate_storage(inputs, previous)
+            }
+            GateType::Memory(_) => vec![memory::read_port(&self.memories[&gate_id], inputs)],
+            _ => vec![self.gates[&gate_id].evaluate_bus(inputs)],
+        }
+    }
+
+    /// Index of the clock input of a flip-flop or RAM, the elements that
+    /// change state on clock edges.
+    fn edge_clock_index(&self, gate_id: GateId) -> Option<usize> {
+        match self.gates[&gate_id].gate_type {
+            GateType::FlipFlop(kind) => Some(kind.clock_index()),
+            GateType::Memory(MemorySpec { kind: MemoryKind::Ram, .. }) => Some(memory::CLOCK),
+            _ => None,
+        }
+    }
+
+    /// Remembers the clock level a flip-flop or RAM was just evaluated with,
+    /// so that later evaluations only trigger on new edges.
+    fn record_clock(&mut self, gate_id: GateId, inputs: &[Bus]) {
+        if let Some(clock_index) = self.edge_clock_index(gate_id) {
+            self.clock_levels.insert(gate_id, inputs[clock_index].bit(0));
+        }
+    }
+
+    /// Computes the outputs of a gate other than a subcircuit instance, unless
+    /// it is a flip-flop or RAM whose clock changed.
+    ///
+    /// Such an element is added to `edges` with the clock level it had before
+    /// and keeps its outputs until [`Circuit::commit_edges`], so that elements
+    /// sharing a clock all see the values from before the edge.
+    fn evaluate_or_sample(&mut self, gate_id: GateId, edges: &mut BTreeMap<GateId, Logic>) -> Option<Vec<Bus>> {
+        let inputs = self.current_inputs(gate_id);
+        if let Some(clock_index) = self.edge_clock_index(gate_id) {
+            // An element evaluated for the first time sees no edge
+            let clock = inputs[clock_index].bit(0);
+            if let Some(previous) = self.clock_levels.insert(gate_id, clock)
+                && previous != clock
+            {
+                edges.entry(gate_id).or_insert(previous);
+            }
+            if edges.contains_key(&gate_id) {
+                return None;
+            }
+        }
+        Some(self.compute_outputs(gate_id, &inputs))
+    }
+
+    /// Lets the flip-flops and RAMs in `edges` act on their clock edge and
+    /// returns those whose outputs changed.
+    ///
+    /// Every element reads its inputs before any of them changes, so a
+    /// register feeding another one on the same clock passes on its old value.
+    fn commit_edges(&mut self, edges: BTreeMap<GateId, Logic>) -> Vec<GateId> {
+        let sampled: Vec<(GateId, Vec<Bus>, Logic)> = edges
+            .into_iter()
+            .map(|(gate_id, previous)| (gate_id, self.current_inputs(gate_id), previous))
+            .collect();
+        let mut changed = vec![];
+        for (gate_id, inputs, previous) in sampled {
+            let outputs = match self.gates[&gate_id].gate_type {
+                GateType::Memory(_) => {
+                    let contents = self.memories.get_mut(&gate_id).expect("memory contents exist");
+                    memory::write_port(contents, &inputs, previous);
+                    vec![memory::read_port(contents, &inputs)]
+                }
+                _ => self.gates[&gate_id].evaluate_storage(&inputs, previous),
+            };
+            if outputs != self.gates[&gate_id].outputs {
+                self.gate_mut(gate_id).outputs = outputs;
+                changed.push(gate_id);
+            }
+        }
+        changed
+    }
+
+    /// Performs the write of a RAM about to be evaluated with the given inputs,
+    /// so that its output already shows the written word.
+    fn store_writes(&mut self, gate_id: GateId, inputs: &[Bus]) {
+        if let GateType::Memory(MemorySpec { kind: MemoryKind::Ram, .. }) = self.gates[&gate_id].gate_type {
+            // A RAM evaluated for the first time sees no edge
+            let clock = inputs[memory::CLOCK].bit(0);
+            let previous = self.clock_levels.get(&gate_id).copied().unwrap_or(clock);
+            let contents = self.memories.get_mut(&gate_id).expect("memory contents exist");
+            memory::write_port(contents, inputs, previous);
+        }
+    }
+
+    /// Applies the current inputs of a subcircuit instance to its stored state
+    /// and returns the resulting outputs.
+    fn update_instance(&mut self, gate_id: GateId) -> Vec<Bus> {
+        let inputs = self.current_inputs(gate_id);
+        let GateType::Subcircuit(id) = self.gates[&gate_id].gate_type else {
+            unreachable!("gate {} is not a subcircuit instance", gate_id);
+        };
+        let instance = self.instances.get_mut(&gate_id).expect("instance state exists");
+        run_instance(instance, &self.subcircuits[id], &inputs)
+    }
 }
@@ -0,0 +1,287 @@
+use crate::circuit::Circuit;
+use crate::connection::GateId;
+use crate::equivalence::{EquivalenceError, Pairing};
+use crate::gate::GateType;
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+/// Errors that prevent a circuit from being encoded as CNF.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CnfError {
+    /// The gate is a subcircuit instance or another gate without a boolean
+    /// function; flatten the circuit first.
+    Unsupported(GateId),
+    /// An input of the gate is not connected.
+    Floating { gate: GateId, input_index: usize },
+    /// The inputs and outputs of the two circuits of a miter do not match.
+    Interface(EquivalenceError),
+}
+
+impl fmt::Display for CnfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CnfError::Unsupported(gate) => write!(f, "Gate {} has no boolean function", gate),
+            CnfError::Floating { gate, input_index } => {
+                write!(f, "Input {} of gate {} is not connected", input_index, gate)
+            }
+            CnfError::Interface(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for CnfError {}
+
+impl From<EquivalenceError> for CnfError {
+    fn from(err: EquivalenceError) -> Self {
+        CnfError::Interface(err)
+    }
+}
+
+/// What a CNF variable stands for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Origin {
+    /// Bit `bit` of the first output of `gate`. `circuit` is `0`, or `1` for
+    /// the second circuit of a miter.
+    Gate { circuit: usize, gate: GateId, bit: usize, name: Option<String> },
+    /// An intermediate result of the encoding, such as a partial parity of an
+    /// Xor gate or whether an output of a miter differs.
+    Auxiliary,
+}
+
+/// A formula in conjunctive normal form, built from a circuit by the Tseitin
+/// transformation.
+///
+/// Every bit of every gate output gets a variable, numbered from 1 in
+/// increasing gate id order, and each gate adds clauses that hold exactly
+/// when its output variables equal its function of its input variables. The
+/// satisfying assignments are therefore the consistent states of the circuit;
+/// for circuits with feedback these are the stable states of each loop.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cnf {
+    /// Clauses as DIMACS literals: variable `v` is `v` and its negation `-v`.
+    pub clauses: Vec<Vec<i64>>,
+    /// The origin of variable `v` is at index `v - 1`.
+    pub origins: Vec<Origin>,
+    literals: HashMap<(usize, GateId), Vec<i64>>,
+}
+
+impl Cnf {
+    /// Encodes every gate of a circuit.
+    pub fn encode(circuit: &Circuit) -> Result<Self, CnfError> {
+        let mut cnf = Self::empty();
+        cnf.add_circuit(0, circuit, &HashMap::new())?;
+        Ok(cnf)
+    }
+
+    /// Encodes a miter of two circuits: a formula that is satisfiable exactly
+    /// when some input values make the circuits produce different outputs.
+    ///
+    /// Inputs and outputs are paired by name as for
+    /// [`check`](crate::equivalence::check). Paired inputs share the
+    /// variables of `left`, and a satisfying assignment of those variables is
+    /// a counterexample.
+    pub fn miter(left: &Circuit, right: &Circuit) -> Result<Self, CnfError> {
+        let pairing = Pairing::of(left, right)?;
+        let mut cnf = Self::empty();
+        cnf.add_circuit(0, left, &HashMap::new())?;
+        let shared: HashMap<GateId, Vec<i64>> = pairing
+            .inputs
+            .iter()
+            .map(|(_, left_id, right_id, _)| (*right_id, cnf.literals[&(0, *left_id)].clone()))
+            .collect();
+        cnf.add_circuit(1, right, &shared)?;
+
+        let mut differences = vec![];
+        for (_, left_id, right_id) in &pairing.outputs {
+            let bits: Vec<(i64, i64)> = cnf.literals[&(0, *left_id)]
+                .iter()
+                .copied()
+                .zip(cnf.literals[&(1, *right_id)].iter().copied())
+                .collect();
+            for (l, r) in bits {
+                let difference = cnf.new_variable(Origin::Auxiliary);
+                cnf.xor(difference, l, r);
+                differences.push(difference);
+            }
+        }
+        cnf.clauses.push(differences);
+        Ok(cnf)
+    }
+
+    /// Number of variables.
+    pub fn variable_count(&self) -> usize {
+        self.origins.len()
+    }
+
+    /// Returns the variable of an output bit of a gate, where `circuit` is
+    /// `0`, or `1` for the second circuit of a miter.
+    pub fn variable(&self, circuit: usize, gate: GateId, bit: usize) -> Option<i64> {
+        self.literals.get(&(circuit, gate))?.get(bit).copied()
+    }
+
+    /// Formats the formula in the DIMACS CNF format read by SAT solvers.
+    pub fn to_dimacs(&self) -> String {
+        let mut dimacs = format!("p cnf {} {}\n", self.variable_count(), self.clauses.len());
+        for clause in &self.clauses {
+            for literal in clause {
+                dimacs += &format!("{} ", literal);
+            }
+            dimacs += "0\n";
+        }
+        dimacs
+    }
+
+    /// Formats the origin of every variable, one per line.
+    ///
+    /// Gate bits are written as `<variable> <circuit> <gate> <bit>` followed by
+    /// the name of the gate if it has one, and auxiliary variables as
+    /// `<variable> auxiliary`. The first line is a comment starting with `c`.
+    pub fn to_mapping(&self) -> String {
+        let mut mapping = String::from("c variable circuit gate bit name\n");
+        for (index, origin) in self.origins.iter().enumerate() {
+            let variable = index + 1;
+            mapping += &match origin {
+                Origin::Gate { circuit, gate, bit, name: Some(name) } => {
+                    format!("{} {} {} {} {}\n", variable, circuit, gate, bit, name)
+                }
+                Origin::Gate { circuit, gate, bit, name: None } => {
+                    format!("{} {} {} {}\n", variable, circuit, gate, bit)
+                }
+                Origin::Auxiliary => format!("{} auxiliary\n", variable),
+            };
+        }
+        mapping
+    }
+
+    /// Writes the formula and the variable mapping to two files.
+    pub fn save(&self, dimacs: impl AsRef<Path>, mapping: impl AsRef<Path>) -> io::Result<()> {
+        std::fs::write(dimacs, self.to_dimacs())?;
+        std::fs::write(mapping, self.to_mapping())
+    }
+
+    fn empty() -> Self {
+        Self {
+            clauses: vec![],
+            origins: vec![],
+            literals: HashMap::new(),
+        }
+    }
+
+    fn new_variable(&mut self, origin: Origin) -> i64 {
+        self.origins.push(origin);
+        self.origins.len() as i64
+    }
+
+    /// Adds the clauses of every gate of a circuit. Gates in `shared` reuse
+    /// the given variables instead of getting new ones.
+    fn add_circuit(
+        &mut self,
+        index: usize,
+        circuit: &Circuit,
+        shared: &HashMap<GateId, Vec<i64>>,
+    ) -> Result<(), CnfError> {
+        for gate_id in circuit.gate_ids() {
+            let gate = circuit.gate(gate_id);
+            if matches!(gate.gate_type, GateType::Subcircuit(_)) {
+                return Err(CnfError::Unsupported(gate_id));
+            }
+            let literals = match shared.get(&gate_id) {
+                Some(literals) => literals.clone(),
+                None => (0..gate.width)
+                    .map(|bit| {
+                        self.new_variable(Origin::Gate {
+                            circuit: index,
+                            gate: gate_id,
+                            bit,
+                            name: gate.name.clone(),
+                        })
+                    })
+                    .collect(),
+            };
+            self.literals.insert((index, gate_id), literals);
+        }
+
+        for gate_id in circuit.gate_ids() {
+            let gate = circuit.gate(gate_id);
+            let mut inputs = vec![];
+            for input_index in 0..gate.input_count {
+                let conn = circuit
+                    .driver(gate_id, input_index)
+                    .ok_or(CnfError::Floating { gate: gate_id, input_index })?;
+                if conn.output_index != 0 {
+                    return Err(CnfError::Unsupported(conn.from));
+                }
+                inputs.push(self.literals[&(index, conn.from)].clone());
+            }
+            let outputs = self.literals[&(index, gate_id)].clone();
+            let bit_inputs = |bit: usize| inputs.iter().map(|input| input[bit]).collect::<Vec<i64>>();
+
+            match gate.gate_type {
+                GateType::Input => {}
+                GateType::Not => {
+                    for (&y, &a) in outputs.iter().zip(&inputs[0]) {
+                        self.equal(y, -a);
+                    }
+                }
+                GateType::And | GateType::Or => {
+                    // Or is And with every literal negated
+                    let sign = if gate.gate_type == GateType::And { 1 } else { -1 };
+                    for (bit, &y) in outputs.iter().enumerate() {
+                        let operands = bit_inputs(bit);
+                        let mut all = vec![sign * y];
+                        for &a in &operands {
+                            self.clauses.push(vec![-sign * y, sign * a]);
+                            all.push(-sign * a);
+                        }
+                        self.clauses.push(all);
+                    }
+                }
+                GateType::Xor => {
+                    for (bit, &y) in outputs.iter().enumerate() {
+                        let operands = bit_inputs(bit);
+                        let (&last, rest) = operands.split_last().expect("Xor gates have inputs");
+                        let Some((&first, middle)) = rest.split_first() else {
+                            self.equal(y, last);
+                            continue;
+                        };
+                        let mut parity = first;
+                        for &a in middle {
+                            let partial = self.new_variable(Origin::Auxiliary);
+                            self.xor(partial, parity, a);
+                            parity = partial;
+                        }
+                        self.xor(y, parity, last);
+                    }
+                }
+                GateType::Splitter => {
+                    for (&y, &a) in outputs.iter().zip(&inputs[0][gate.offset..]) {
+                        self.equal(y, a);
+                    }
+                }
+                GateType::Merger => {
+                    for (&y, &a) in outputs.iter().zip(inputs.iter().flatten()) {
+                        self.equal(y, a);
+                    }
+                }
+                _ => return Err(CnfError::Unsupported(gate_id)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Clauses for `y == a`.
+    fn equal(&mut self, y: i64, a: i64) {
+        self.clauses.push(vec![-y, a]);
+        self.clauses.push(vec![y, -a]);
+    }
+
+    /// Clauses for `y == a ^ b`.
+    fn xor(&mut self, y: i64, a: i64, b: i64) {
+        self.clauses.push(vec![-y, a, b]);
+        self.clauses.push(vec![-y, -a, -b]);
+        self.clauses.push(vec![y, -a, b]);
+        self.clauses.push(vec![y, a, -b]);
+    }
+}
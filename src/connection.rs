@@ -1,20 +1,25 @@
-/// Alias for identifying a gate within a circuit by its index.
+use serde::{Deserialize, Serialize};
+
+/// Alias for identifying a gate within a circuit.
 ///
 /// Using `GateId` improves code readability by clarifying that the `usize`
-/// represents a unique gate identifier.
+/// represents a unique gate identifier. Ids are handed out in increasing order
+/// and never reused, so removing a gate does not change the id of any other.
 pub type GateId = usize;
 
 /// Represents a directed connection from the output of one gate to the input of another.
 ///
 /// Each connection specifies:
 /// - `from`: The source gate ID whose output is sent.
+/// - `output_index`: The output port of the source gate; `0` for single-output gates.
 /// - `to`: The destination gate ID receiving the signal as input.
 /// - `input_index`: The input slot index on the destination gate that this connection drives.
 ///
 /// This struct is used to model wiring between gates inside a circuit.
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Connection {
     pub from: GateId,
+    pub output_index: usize,
     pub to: GateId,
     pub input_index: usize,
 }
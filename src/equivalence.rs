@@ -0,0 +1,328 @@
+use crate::bdd::{self, Bdd, BddError, Manager};
+use crate::bus::Bus;
+use crate::circuit::{Circuit, Oscillation, DEFAULT_MAX_PASSES};
+use crate::connection::GateId;
+use crate::gate::GateType;
+use crate::logic::Logic;
+use crate::packed::{self, PackedBus, PackedLogic, LANES};
+use crate::schedule::Schedule;
+use crate::truth_table::MAX_INPUT_BITS;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// Errors that prevent two circuits from being compared.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EquivalenceError {
+    /// An input gate has no name to match it with the other circuit.
+    UnnamedInput(GateId),
+    /// Two gates of the same circuit share a name.
+    DuplicateName(String),
+    /// An input name exists in only one of the circuits.
+    InputMismatch(String),
+    /// An output name exists in only one of the circuits.
+    OutputMismatch(String),
+    /// A named gate has a different width in each circuit.
+    WidthMismatch { name: String, left: usize, right: usize },
+    /// The circuits have too many inputs to simulate exhaustively and cannot
+    /// be compared through BDDs.
+    Bdd(BddError),
+    /// One of the circuits did not settle for some input combination.
+    Oscillation(Oscillation),
+}
+
+impl fmt::Display for EquivalenceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EquivalenceError::UnnamedInput(gate) => write!(f, "Input gate {} has no name to match it by", gate),
+            EquivalenceError::DuplicateName(name) => write!(f, "More than one gate is named {}", name),
+            EquivalenceError::InputMismatch(name) => write!(f, "Input {} exists in only one circuit", name),
+            EquivalenceError::OutputMismatch(name) => write!(f, "Output {} exists in only one circuit", name),
+            EquivalenceError::WidthMismatch { name, left, right } => {
+                write!(f, "{} is {} bits wide in one circuit and {} in the other", name, left, right)
+            }
+            EquivalenceError::Bdd(err) => write!(f, "Cannot compare the circuits symbolically: {}", err),
+            EquivalenceError::Oscillation(oscillation) => oscillation.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for EquivalenceError {}
+
+impl From<BddError> for EquivalenceError {
+    fn from(err: BddError) -> Self {
+        EquivalenceError::Bdd(err)
+    }
+}
+
+impl From<Oscillation> for EquivalenceError {
+    fn from(oscillation: Oscillation) -> Self {
+        EquivalenceError::Oscillation(oscillation)
+    }
+}
+
+/// An output that differs between the two circuits for a counterexample.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputMismatch {
+    pub name: String,
+    pub left: Bus,
+    pub right: Bus,
+}
+
+/// An assignment of the inputs for which the circuits produce different outputs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Counterexample {
+    pub inputs: Vec<(String, Bus)>,
+    pub outputs: Vec<OutputMismatch>,
+}
+
+impl fmt::Display for Counterexample {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let inputs: Vec<String> = self.inputs.iter().map(|(name, value)| format!("{} = {}", name, value)).collect();
+        let outputs: Vec<String> = self
+            .outputs
+            .iter()
+            .map(|o| format!("{} is {} and {}", o.name, o.left, o.right))
+            .collect();
+        write!(f, "for {}: {}", inputs.join(", "), outputs.join(", "))
+    }
+}
+
+/// The result of comparing two circuits.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Equivalence {
+    Equivalent,
+    Different(Counterexample),
+}
+
+/// Named gates of a circuit that take part in the comparison, as
+/// `(name, gate, width)` in increasing gate id order.
+struct Interface {
+    inputs: Vec<(String, GateId, usize)>,
+    outputs: Vec<(String, GateId, usize)>,
+}
+
+impl Interface {
+    /// Input gates, which must all be named, and named gates of other types.
+    fn of(circuit: &Circuit) -> Result<Self, EquivalenceError> {
+        let mut interface = Interface { inputs: vec![], outputs: vec![] };
+        let mut names = HashSet::new();
+        for gate_id in circuit.gate_ids() {
+            let gate = circuit.gate(gate_id);
+            let is_input = gate.gate_type == GateType::Input;
+            let Some(name) = &gate.name else {
+                if is_input {
+                    return Err(EquivalenceError::UnnamedInput(gate_id));
+                }
+                continue;
+            };
+            if !names.insert(name.clone()) {
+                return Err(EquivalenceError::DuplicateName(name.clone()));
+            }
+            let list = if is_input { &mut interface.inputs } else { &mut interface.outputs };
+            list.push((name.clone(), gate_id, gate.width));
+        }
+        Ok(interface)
+    }
+}
+
+/// Pairs each named gate of `left` with the gate of the same name in `right`.
+fn match_names(
+    left: &[(String, GateId, usize)],
+    right: &[(String, GateId, usize)],
+    missing: fn(String) -> EquivalenceError,
+) -> Result<Vec<GateId>, EquivalenceError> {
+    let by_name: HashMap<&str, (GateId, usize)> = right.iter().map(|(n, id, w)| (n.as_str(), (*id, *w))).collect();
+    let ids = left
+        .iter()
+        .map(|(name, _, width)| match by_name.get(name.as_str()) {
+            None => Err(missing(name.clone())),
+            Some(&(_, right_width)) if right_width != *width => Err(EquivalenceError::WidthMismatch {
+                name: name.clone(),
+                left: *width,
+                right: right_width,
+            }),
+            Some(&(id, _)) => Ok(id),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    match right.iter().find(|(n, ..)| !left.iter().any(|(l, ..)| l == n)) {
+        Some((name, ..)) => Err(missing(name.clone())),
+        None => Ok(ids),
+    }
+}
+
+/// Gates of the two circuits paired by name.
+pub(crate) struct Pairing {
+    /// `(name, left gate, right gate, width)` of each input.
+    pub inputs: Vec<(String, GateId, GateId, usize)>,
+    /// `(name, left gate, right gate)` of each output.
+    pub outputs: Vec<(String, GateId, GateId)>,
+}
+
+impl Pairing {
+    /// Pairs the named inputs and outputs of two circuits as described for [`check`].
+    pub fn of(left: &Circuit, right: &Circuit) -> Result<Self, EquivalenceError> {
+        let left_interface = Interface::of(left)?;
+        let right_interface = Interface::of(right)?;
+        let right_input_ids =
+            match_names(&left_interface.inputs, &right_interface.inputs, EquivalenceError::InputMismatch)?;
+        let right_output_ids =
+            match_names(&left_interface.outputs, &right_interface.outputs, EquivalenceError::OutputMismatch)?;
+        Ok(Self {
+            inputs: left_interface
+                .inputs
+                .into_iter()
+                .zip(right_input_ids)
+                .map(|((name, left_id, width), right_id)| (name, left_id, right_id, width))
+                .collect(),
+            outputs: left_interface
+                .outputs
+                .into_iter()
+                .zip(right_output_ids)
+                .map(|((name, left_id, _), right_id)| (name, left_id, right_id))
+                .collect(),
+        })
+    }
+}
+
+/// Decides whether two circuits compute the same function of their inputs.
+///
+/// Input gates are matched by name and must all be named; the outputs
+/// compared are the named gates of any other type. Both circuits need the same
+/// names with the same widths.
+///
+/// Circuits with at most [`MAX_INPUT_BITS`] input bits are simulated for
+/// every combination of input values, 64 at a time, and `X` and `Z` outputs
+/// only match the same value. Larger circuits are compared through binary
+/// decision diagrams, which treat every signal as `0` or `1` and require both
+/// circuits to be free of feedback, subcircuit instances and unconnected
+/// inputs. Either way the answer is exact. A counterexample reports a
+/// differing combination together with every output that differs for it; for
+/// simulated circuits it is the first one, counting up with the first input of
+/// `left` as the most significant part.
+pub fn check(left: &mut Circuit, right: &mut Circuit) -> Result<Equivalence, EquivalenceError> {
+    let pairing = Pairing::of(left, right)?;
+    let bits: usize = pairing.inputs.iter().map(|&(.., width)| width).sum();
+    if bits > MAX_INPUT_BITS {
+        check_symbolically(left, right, &pairing)
+    } else {
+        check_exhaustively(left, right, &pairing)
+    }
+}
+
+/// Simulates both circuits for every combination of input values.
+fn check_exhaustively(
+    left: &mut Circuit,
+    right: &mut Circuit,
+    pairing: &Pairing,
+) -> Result<Equivalence, EquivalenceError> {
+    let widths: Vec<usize> = pairing.inputs.iter().map(|&(.., width)| width).collect();
+    // Bit `k` of input `j` is bit `shifts[j] + k` of the combination number
+    let shifts: Vec<usize> = (0..widths.len()).map(|j| widths[j + 1..].iter().sum()).collect();
+    let count = 1u64 << widths.iter().sum::<usize>();
+    for first in (0..count).step_by(LANES) {
+        let values: Vec<PackedBus> = widths
+            .iter()
+            .zip(&shifts)
+            .map(|(&width, &shift)| (0..width).map(|k| PackedLogic::counting(shift + k, first)).collect())
+            .collect();
+        let left_values: HashMap<GateId, PackedBus> =
+            pairing.inputs.iter().map(|&(_, id, ..)| id).zip(values.iter().cloned()).collect();
+        let right_values: HashMap<GateId, PackedBus> =
+            pairing.inputs.iter().map(|&(_, _, id, _)| id).zip(values.iter().cloned()).collect();
+        let left_results = left.simulate_packed(&left_values, DEFAULT_MAX_PASSES)?;
+        let right_results = right.simulate_packed(&right_values, DEFAULT_MAX_PASSES)?;
+
+        let lanes = (count - first).min(LANES as u64) as u32;
+        let valid = if lanes == 64 { !0 } else { (1u64 << lanes) - 1 };
+        let outputs: Vec<(&str, &PackedBus, &PackedBus)> = pairing
+            .outputs
+            .iter()
+            .map(|(name, left_id, right_id)| (name.as_str(), &left_results[left_id][0], &right_results[right_id][0]))
+            .collect();
+        let differing = outputs
+            .iter()
+            .flat_map(|(_, l, r)| l.iter().zip(r.iter()))
+            .fold(0, |acc, (l, r)| acc | (l.known ^ r.known) | (l.value ^ r.value))
+            & valid;
+        if differing != 0 {
+            let lane = differing.trailing_zeros() as usize;
+            let inputs = pairing
+                .inputs
+                .iter()
+                .zip(&values)
+                .map(|((name, ..), value)| (name.clone(), packed::lane_bus(value, lane)))
+                .collect();
+            let outputs = outputs
+                .iter()
+                .map(|&(name, l, r)| OutputMismatch {
+                    name: name.to_string(),
+                    left: packed::lane_bus(l, lane),
+                    right: packed::lane_bus(r, lane),
+                })
+                .filter(|o| o.left != o.right)
+                .collect();
+            return Ok(Equivalence::Different(Counterexample { inputs, outputs }));
+        }
+    }
+    Ok(Equivalence::Equivalent)
+}
+
+/// Builds BDDs of both circuits over shared variables and compares them.
+fn check_symbolically(left: &Circuit, right: &Circuit, pairing: &Pairing) -> Result<Equivalence, EquivalenceError> {
+    // Interleaving the bits of all inputs keeps the BDDs of datapaths small
+    let widest = pairing.inputs.iter().map(|&(.., width)| width).max().unwrap_or(0);
+    let mut variables = vec![];
+    for bit in (0..widest).rev() {
+        for (j, &(_, left_id, _, width)) in pairing.inputs.iter().enumerate() {
+            if bit < width {
+                variables.push((j, bit, bdd::variable_name(left, left_id, bit)));
+            }
+        }
+    }
+    let index: HashMap<(usize, usize), usize> =
+        variables.iter().enumerate().map(|(v, &(j, bit, _))| ((j, bit), v)).collect();
+    let mut manager = Manager::new(variables.into_iter().map(|(.., name)| name).collect());
+
+    let mut build = |circuit: &Circuit, right_side: bool| {
+        let gate_of = |left_id: GateId, right_id: GateId| if right_side { right_id } else { left_id };
+        let inputs: HashMap<GateId, usize> =
+            pairing.inputs.iter().enumerate().map(|(j, &(_, l, r, _))| (gate_of(l, r), j)).collect();
+        let outputs: Vec<GateId> = pairing.outputs.iter().map(|&(_, l, r)| gate_of(l, r)).collect();
+        let variable = |gate: GateId, bit: usize| inputs.get(&gate).map(|&j| index[&(j, bit)]);
+        bdd::gate_bdds(&mut manager, circuit, &Schedule::build(circuit), &outputs, &variable)
+    };
+    let left_bits = build(left, false)?;
+    let right_bits = build(right, true)?;
+
+    let mut differing = Manager::ZERO;
+    for (&l, &r) in left_bits.iter().flatten().zip(right_bits.iter().flatten()) {
+        let difference = manager.xor(l, r);
+        differing = manager.or(differing, difference);
+    }
+    let Some(values) = manager.any_sat(differing) else {
+        return Ok(Equivalence::Equivalent);
+    };
+
+    let inputs = pairing
+        .inputs
+        .iter()
+        .enumerate()
+        .map(|(j, (name, .., width))| {
+            let value = (0..*width).map(|bit| Logic::from(values[index[&(j, bit)]])).collect();
+            (name.clone(), value)
+        })
+        .collect();
+    let bus = |bits: &[Bdd]| bits.iter().map(|&f| Logic::from(manager.evaluate(f, &values))).collect::<Bus>();
+    let outputs = pairing
+        .outputs
+        .iter()
+        .zip(left_bits.iter().zip(&right_bits))
+        .map(|((name, ..), (l, r))| OutputMismatch {
+            name: name.clone(),
+            left: bus(l),
+            right: bus(r),
+        })
+        .filter(|o| o.left != o.right)
+        .collect();
+    Ok(Equivalence::Different(Counterexample { inputs, outputs }))
+}
@@ -0,0 +1,144 @@
+use crate::connection::GateId;
+use crate::gate::GateType;
+use crate::library::Component;
+use crate::subcircuit::SubcircuitId;
+use std::fmt;
+
+/// Errors reported by the `try_*` methods of [`Circuit`](crate::circuit::Circuit).
+///
+/// The panicking methods of the circuit use the `Display` text of these errors
+/// as their panic message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CircuitError {
+    /// No gate with this id exists in the circuit.
+    InvalidGate(GateId),
+    /// No subcircuit definition with this id has been registered.
+    InvalidSubcircuit(SubcircuitId),
+    /// The operation requires a subcircuit instance.
+    NotAnInstance(GateId),
+    /// A port of a subcircuit definition names a gate its circuit does not have.
+    InvalidPortGate { subcircuit: SubcircuitId, gate: GateId },
+    /// An input port of a subcircuit definition names a gate that is not an input gate.
+    PortNotAnInput { subcircuit: SubcircuitId, gate: GateId },
+    /// The stored state of a subcircuit instance lacks a port gate of its
+    /// definition or has it with a different type or width.
+    InstanceMismatch { gate: GateId, port_gate: GateId },
+    /// A library component was given parameters outside of its limits.
+    InvalidComponent(Component),
+    /// The gate type cannot have the requested number of inputs.
+    InvalidInputCount { gate_type: GateType, input_count: usize },
+    /// The gate has no input with this index.
+    InvalidInputIndex { gate: GateId, input_index: usize, input_count: usize },
+    /// The gate has no output with this index.
+    InvalidOutputIndex { gate: GateId, output_index: usize, output_count: usize },
+    /// The operation requires an input gate.
+    NotAnInput(GateId),
+    /// A value of the wrong width was given to an input gate.
+    InputWidthMismatch { gate: GateId, expected: usize, actual: usize },
+    /// The output and input of a connection have different widths.
+    ConnectionWidthMismatch { from: GateId, output_index: usize, width: usize, to: GateId, input_index: usize },
+    /// The input already has a driver; disconnect it or use `replace_driver`.
+    InputAlreadyDriven { to: GateId, input_index: usize, driver: GateId },
+    /// There is no connection between the given output and input.
+    NotConnected { from: GateId, output_index: usize, to: GateId, input_index: usize },
+    /// An event was scheduled before the current simulation time.
+    EventInPast { time: u64, now: u64 },
+    /// A clock must be low and high for at least one tick of each period.
+    InvalidClockTiming { period: u64, high: u64 },
+    /// The operation requires a clock gate.
+    NotAClock(GateId),
+    /// A memory must have 1 to 16 address bits and 1 to 64 data bits.
+    InvalidMemorySize { address_width: usize, data_width: usize },
+    /// The operation requires a memory.
+    NotAMemory(GateId),
+    /// The memory has no word at this address.
+    InvalidAddress { gate: GateId, address: usize, word_count: usize },
+    /// A value of the wrong width was written to a memory.
+    WordWidthMismatch { gate: GateId, expected: usize, actual: usize },
+}
+
+impl fmt::Display for CircuitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CircuitError::InvalidGate(gate) => write!(f, "Gate {} does not exist", gate),
+            CircuitError::InvalidSubcircuit(id) => write!(f, "Subcircuit {} is not defined", id),
+            CircuitError::NotAnInstance(gate) => write!(f, "Gate {} is not a subcircuit instance", gate),
+            CircuitError::InvalidPortGate { subcircuit, gate } => {
+                write!(f, "Subcircuit {} has a port on gate {}, which it does not contain", subcircuit, gate)
+            }
+            CircuitError::PortNotAnInput { subcircuit, gate } => {
+                write!(f, "Subcircuit {} has an input port on gate {}, which is not an input gate", subcircuit, gate)
+            }
+            CircuitError::InstanceMismatch { gate, port_gate } => {
+                write!(f, "Instance {} does not match its definition at port gate {}", gate, port_gate)
+            }
+            CircuitError::InvalidComponent(component) => {
+                write!(f, "Cannot build a {} with these parameters", component.label().to_lowercase())
+            }
+            CircuitError::InvalidInputCount { gate_type, input_count } => {
+                write!(f, "{:?} gates cannot have {} inputs", gate_type, input_count)
+            }
+            CircuitError::InvalidInputIndex { gate, input_index, input_count } => write!(
+                f,
+                "Gate {} has no input {} (it has {} inputs)",
+                gate, input_index, input_count
+            ),
+            CircuitError::InvalidOutputIndex { gate, output_index, output_count } => write!(
+                f,
+                "Gate {} has no output {} (it has {} outputs)",
+                gate, output_index, output_count
+            ),
+            CircuitError::NotAnInput(gate) => write!(f, "Gate {} is not an input gate", gate),
+            CircuitError::InputWidthMismatch { gate, expected, actual } => write!(
+                f,
+                "Cannot set {}-bit input gate {} to a {}-bit value",
+                expected, gate, actual
+            ),
+            CircuitError::ConnectionWidthMismatch { from, output_index, width, to, input_index } => {
+                if *output_index == 0 {
+                    write!(f, "Cannot connect {}-bit output of gate {} ", width, from)?;
+                } else {
+                    write!(f, "Cannot connect {}-bit output {} of gate {} ", width, output_index, from)?;
+                }
+                write!(f, "to input {} of gate {}", input_index, to)
+            }
+            CircuitError::InputAlreadyDriven { to, input_index, driver } => write!(
+                f,
+                "Input {} of gate {} is already driven by gate {}",
+                input_index, to, driver
+            ),
+            CircuitError::NotConnected { from, output_index, to, input_index } => write!(
+                f,
+                "Output {} of gate {} is not connected to input {} of gate {}",
+                output_index, from, input_index, to
+            ),
+            CircuitError::EventInPast { time, now } => {
+                write!(f, "Cannot schedule an event at {} before the current time {}", time, now)
+            }
+            CircuitError::InvalidClockTiming { period, high } => write!(
+                f,
+                "A clock cannot be high for {} of every {} ticks",
+                high, period
+            ),
+            CircuitError::NotAClock(gate) => write!(f, "Gate {} is not a clock", gate),
+            CircuitError::InvalidMemorySize { address_width, data_width } => write!(
+                f,
+                "A memory cannot have {} address bits and {} data bits",
+                address_width, data_width
+            ),
+            CircuitError::NotAMemory(gate) => write!(f, "Gate {} is not a memory", gate),
+            CircuitError::InvalidAddress { gate, address, word_count } => write!(
+                f,
+                "Memory {} has no address {} (it has {} words)",
+                gate, address, word_count
+            ),
+            CircuitError::WordWidthMismatch { gate, expected, actual } => write!(
+                f,
+                "Cannot store a {}-bit value in {}-bit memory {}",
+                actual, expected, gate
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CircuitError {}
@@ -0,0 +1,93 @@
+use crate::connection::GateId;
+use crate::bus::Bus;
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+
+/// A scheduled change of one of a gate's outputs at a point in simulated time.
+///
+/// Events scheduled for the same time are applied in the order they were scheduled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    pub time: u64,
+    pub gate: GateId,
+    pub output_index: usize,
+    pub value: Bus,
+    seq: u64,
+}
+
+impl Ord for Event {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.time, self.seq).cmp(&(other.time, other.seq))
+    }
+}
+
+impl PartialOrd for Event {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// A recorded change of a gate's output during event-driven simulation.
+///
+/// The history of transitions makes short pulses (glitches) visible that the
+/// zero-delay `Circuit::evaluate` never produces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transition {
+    pub time: u64,
+    pub gate: GateId,
+    pub output_index: usize,
+    pub value: Bus,
+}
+
+/// Priority queue of pending events, ordered by time.
+#[derive(Debug, Clone, Default)]
+pub struct EventQueue {
+    heap: BinaryHeap<Reverse<Event>>,
+    next_seq: u64,
+}
+
+impl EventQueue {
+    /// Creates an empty event queue.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Schedules output `output_index` of `gate` to change to `value` at `time`.
+    pub fn push(&mut self, time: u64, gate: GateId, output_index: usize, value: Bus) {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.heap.push(Reverse(Event { time, gate, output_index, value, seq }));
+    }
+
+    /// Returns the time of the earliest pending event, if any.
+    pub fn next_time(&self) -> Option<u64> {
+        self.heap.peek().map(|Reverse(event)| event.time)
+    }
+
+    /// Removes and returns the earliest pending event if it is due at `time`.
+    pub fn pop_at(&mut self, time: u64) -> Option<Event> {
+        if self.next_time() == Some(time) {
+            self.heap.pop().map(|Reverse(event)| event)
+        } else {
+            None
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    /// Discards all pending events of `gate`.
+    pub fn remove_gate(&mut self, gate: GateId) {
+        self.heap.retain(|Reverse(event)| event.gate != gate);
+    }
+
+    /// Discards all pending events.
+    pub fn clear(&mut self) {
+        self.heap.clear();
+    }
+}
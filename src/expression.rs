@@ -0,0 +1,361 @@
+use crate::circuit::Circuit;
+use crate::connection::GateId;
+use crate::error::CircuitError;
+use crate::gate::GateType;
+use crate::logic::Logic;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+/// A boolean expression over named single-bit variables.
+///
+/// Expressions are written with `!` (or `~`) for NOT, `&` for AND, `^` for XOR
+/// and `|` for OR, binding in that order from tightest to loosest, and with
+/// parentheses for grouping. Variable names start with a letter or underscore
+/// and may contain digits. A chain of the same operator such as `a & b & c`
+/// is parsed into one operator with several operands, which becomes a single
+/// gate with several inputs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    Var(String),
+    Not(Box<Expr>),
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+    Xor(Vec<Expr>),
+}
+
+/// Errors reported when parsing an [`Expr`]. Positions are byte offsets into the text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// A character that is not part of the expression syntax.
+    UnexpectedCharacter { position: usize, character: char },
+    /// The text ended where an operand or `)` was expected.
+    UnexpectedEnd,
+    /// A valid token appeared where something else was expected.
+    Expected { position: usize, expected: &'static str },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::UnexpectedCharacter { position, character } => {
+                write!(f, "Unexpected character '{}' at position {}", character, position)
+            }
+            ParseError::UnexpectedEnd => write!(f, "Unexpected end of expression"),
+            ParseError::Expected { position, expected } => write!(f, "Expected {} at position {}", expected, position),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Errors reported when deriving an [`Expr`] from the gates of a circuit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExpressionError {
+    /// The gate does not exist.
+    Circuit(CircuitError),
+    /// The gate is a bus, a wiring component or a subcircuit instance.
+    Unsupported(GateId),
+    /// An input of the gate is not connected.
+    Floating { gate: GateId, input_index: usize },
+    /// The gate is part of a feedback loop, which has no finite expression.
+    Feedback(GateId),
+}
+
+impl fmt::Display for ExpressionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExpressionError::Circuit(err) => err.fmt(f),
+            ExpressionError::Unsupported(gate) => {
+                write!(f, "Gate {} is not a single-bit And, Or, Xor, Not or Input gate", gate)
+            }
+            ExpressionError::Floating { gate, input_index } => {
+                write!(f, "Input {} of gate {} is not connected", input_index, gate)
+            }
+            ExpressionError::Feedback(gate) => write!(f, "Gate {} is part of a feedback loop", gate),
+        }
+    }
+}
+
+impl std::error::Error for ExpressionError {}
+
+impl From<CircuitError> for ExpressionError {
+    fn from(err: CircuitError) -> Self {
+        ExpressionError::Circuit(err)
+    }
+}
+
+/// Binary operators from loosest to tightest binding.
+const OPERATORS: [char; 3] = ['|', '^', '&'];
+
+/// Recursive-descent parser over the text of an expression.
+struct Parser<'a> {
+    text: &'a str,
+    position: usize,
+}
+
+impl Parser<'_> {
+    /// Returns the next character that is not whitespace without consuming it.
+    fn peek(&mut self) -> Option<char> {
+        let rest = &self.text[self.position..];
+        let trimmed = rest.trim_start();
+        self.position += rest.len() - trimmed.len();
+        trimmed.chars().next()
+    }
+
+    /// Consumes `expected` if it is the next character.
+    fn eat(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.position += expected.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Parses operands joined by the operator at `level` or tighter ones.
+    fn binary(&mut self, level: usize) -> Result<Expr, ParseError> {
+        let Some(&operator) = OPERATORS.get(level) else {
+            return self.unary();
+        };
+        let mut operands = vec![self.binary(level + 1)?];
+        while self.eat(operator) {
+            operands.push(self.binary(level + 1)?);
+        }
+        Ok(match (operands.len(), operator) {
+            (1, _) => operands.pop().expect("one operand"),
+            (_, '|') => Expr::Or(operands),
+            (_, '^') => Expr::Xor(operands),
+            _ => Expr::And(operands),
+        })
+    }
+
+    /// Parses a variable, a negation or a parenthesized expression.
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        if self.eat('!') || self.eat('~') {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        if self.eat('(') {
+            let expr = self.binary(0)?;
+            if !self.eat(')') {
+                return Err(self.unexpected("')'"));
+            }
+            return Ok(expr);
+        }
+        match self.peek() {
+            Some(c) if c.is_alphabetic() || c == '_' => {
+                let rest = &self.text[self.position..];
+                let end = rest.find(|c: char| !c.is_alphanumeric() && c != '_').unwrap_or(rest.len());
+                self.position += end;
+                Ok(Expr::Var(rest[..end].to_string()))
+            }
+            _ => Err(self.unexpected("an operand")),
+        }
+    }
+
+    /// Describes whatever stands where `expected` should have been.
+    fn unexpected(&mut self, expected: &'static str) -> ParseError {
+        match self.peek() {
+            None => ParseError::UnexpectedEnd,
+            Some(c) if is_token_start(c) => ParseError::Expected { position: self.position, expected },
+            Some(character) => ParseError::UnexpectedCharacter { position: self.position, character },
+        }
+    }
+}
+
+/// Returns `true` if a token of the expression syntax can start with `c`.
+fn is_token_start(c: char) -> bool {
+    c.is_alphanumeric() || "_!~()".contains(c) || OPERATORS.contains(&c)
+}
+
+impl FromStr for Expr {
+    type Err = ParseError;
+
+    fn from_str(text: &str) -> Result<Self, ParseError> {
+        let mut parser = Parser { text, position: 0 };
+        let expr = parser.binary(0)?;
+        match parser.peek() {
+            None => Ok(expr),
+            Some(_) => Err(parser.unexpected("an operator")),
+        }
+    }
+}
+
+impl Expr {
+    /// Binding strength of the top-level operator, higher binding tighter.
+    fn precedence(&self) -> usize {
+        match self {
+            Expr::Or(_) => 0,
+            Expr::Xor(_) => 1,
+            Expr::And(_) => 2,
+            Expr::Var(_) | Expr::Not(_) => 3,
+        }
+    }
+
+    /// Returns the names of the variables in the order they first appear.
+    pub fn variables(&self) -> Vec<&str> {
+        fn collect<'a>(expr: &'a Expr, names: &mut Vec<&'a str>) {
+            match expr {
+                Expr::Var(name) => {
+                    if !names.contains(&name.as_str()) {
+                        names.push(name);
+                    }
+                }
+                Expr::Not(operand) => collect(operand, names),
+                Expr::And(operands) | Expr::Or(operands) | Expr::Xor(operands) => {
+                    for operand in operands {
+                        collect(operand, names);
+                    }
+                }
+            }
+        }
+        let mut names = vec![];
+        collect(self, &mut names);
+        names
+    }
+
+    /// Evaluates the expression; variables missing from `values` are `X`.
+    pub fn evaluate(&self, values: &HashMap<String, Logic>) -> Logic {
+        let evaluate_all = |operands: &[Expr]| operands.iter().map(|e| e.evaluate(values)).collect::<Vec<_>>();
+        match self {
+            Expr::Var(name) => values.get(name).copied().unwrap_or(Logic::X),
+            Expr::Not(operand) => !operand.evaluate(values),
+            Expr::And(operands) => Logic::all(&evaluate_all(operands)),
+            Expr::Or(operands) => Logic::any(&evaluate_all(operands)),
+            Expr::Xor(operands) => Logic::parity(&evaluate_all(operands)),
+        }
+    }
+
+    /// Builds a new circuit computing the expression and returns it together
+    /// with the gate producing the result.
+    pub fn to_circuit(&self) -> (Circuit, GateId) {
+        let mut circuit = Circuit::new();
+        let output = self.add_to(&mut circuit);
+        (circuit, output)
+    }
+
+    /// Adds gates computing the expression to `circuit` and returns the gate
+    /// producing the result.
+    ///
+    /// Each variable is a single-bit input gate named after it. Input gates
+    /// that already have the name are reused, so several expressions can share
+    /// their inputs; missing ones are added in the order the variables first
+    /// appear.
+    pub fn add_to(&self, circuit: &mut Circuit) -> GateId {
+        for name in self.variables() {
+            if named_input(circuit, name).is_none() {
+                let gate_id = circuit.add_gate(GateType::Input, 0);
+                circuit.set_name(gate_id, name);
+            }
+        }
+        self.add_gates(circuit)
+    }
+
+    fn add_gates(&self, circuit: &mut Circuit) -> GateId {
+        let (gate_type, operands) = match self {
+            Expr::Var(name) => return named_input(circuit, name).expect("inputs are added first"),
+            Expr::Not(operand) => (GateType::Not, std::slice::from_ref(operand.as_ref())),
+            Expr::And(operands) => (GateType::And, operands.as_slice()),
+            Expr::Or(operands) => (GateType::Or, operands.as_slice()),
+            Expr::Xor(operands) => (GateType::Xor, operands.as_slice()),
+        };
+        let inputs: Vec<GateId> = operands.iter().map(|operand| operand.add_gates(circuit)).collect();
+        let gate_id = circuit.add_gate(gate_type, inputs.len());
+        for (input_index, input) in inputs.into_iter().enumerate() {
+            circuit.connect(input, gate_id, input_index);
+        }
+        gate_id
+    }
+
+    /// Derives the expression computed by the first output of a gate by
+    /// walking its fan-in back to the input gates.
+    ///
+    /// Input gates become variables named after the gate, or `in<id>` if it
+    /// has no name. Only single-bit And, Or, Xor and Not gates can be
+    /// expressed, and every one of their inputs must be connected.
+    pub fn from_gate(circuit: &Circuit, gate_id: GateId) -> Result<Expr, ExpressionError> {
+        derive(circuit, gate_id, &mut HashMap::new(), &mut HashSet::new())
+    }
+}
+
+/// Returns the lowest single-bit input gate with the given name.
+fn named_input(circuit: &Circuit, name: &str) -> Option<GateId> {
+    circuit.gate_ids().find(|&id| {
+        let gate = circuit.gate(id);
+        gate.gate_type == GateType::Input && gate.width == 1 && gate.name.as_deref() == Some(name)
+    })
+}
+
+/// Derives the expression of a gate, reusing the expressions of gates already
+/// seen and tracking the gates on the current path to detect feedback.
+fn derive(
+    circuit: &Circuit,
+    gate_id: GateId,
+    done: &mut HashMap<GateId, Expr>,
+    visiting: &mut HashSet<GateId>,
+) -> Result<Expr, ExpressionError> {
+    if let Some(expr) = done.get(&gate_id) {
+        return Ok(expr.clone());
+    }
+    let gate = circuit.try_gate(gate_id)?;
+    if gate.width != 1 {
+        return Err(ExpressionError::Unsupported(gate_id));
+    }
+    if gate.gate_type == GateType::Input {
+        let name = gate.name.clone().unwrap_or_else(|| format!("in{}", gate_id));
+        return Ok(Expr::Var(name));
+    }
+    if !matches!(gate.gate_type, GateType::And | GateType::Or | GateType::Xor | GateType::Not) {
+        return Err(ExpressionError::Unsupported(gate_id));
+    }
+    if !visiting.insert(gate_id) {
+        return Err(ExpressionError::Feedback(gate_id));
+    }
+
+    let mut operands = vec![];
+    for input_index in 0..gate.input_count {
+        let conn = circuit
+            .driver(gate_id, input_index)
+            .ok_or(ExpressionError::Floating { gate: gate_id, input_index })?;
+        if conn.output_index != 0 {
+            return Err(ExpressionError::Unsupported(conn.from));
+        }
+        operands.push(derive(circuit, conn.from, done, visiting)?);
+    }
+    visiting.remove(&gate_id);
+
+    let expr = match gate.gate_type {
+        GateType::Not => Expr::Not(Box::new(operands.pop().expect("Not gates have one input"))),
+        GateType::And => Expr::And(operands),
+        GateType::Or => Expr::Or(operands),
+        _ => Expr::Xor(operands),
+    };
+    done.insert(gate_id, expr.clone());
+    Ok(expr)
+}
+
+impl fmt::Display for Expr {
+    /// Writes the expression with as few parentheses as needed to parse it
+    /// back into the same structure.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (operator, operands) = match self {
+            Expr::Var(name) => return f.write_str(name),
+            Expr::Not(operand) if operand.precedence() < 3 => return write!(f, "!({})", operand),
+            Expr::Not(operand) => return write!(f, "!{}", operand),
+            Expr::And(operands) => (" & ", operands),
+            Expr::Or(operands) => (" | ", operands),
+            Expr::Xor(operands) => (" ^ ", operands),
+        };
+        for (i, operand) in operands.iter().enumerate() {
+            if i > 0 {
+                f.write_str(operator)?;
+            }
+            if operand.precedence() <= self.precedence() {
+                write!(f, "({})", operand)?;
+            } else {
+                write!(f, "{}", operand)?;
+            }
+        }
+        Ok(())
+    }
+}
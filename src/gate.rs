@@ -1,52 +1,325 @@
+use crate::bus::Bus;
+use crate::logic::Logic;
+use crate::memory::{self, MemorySpec};
+use crate::packed::{self, PackedBus, PackedLogic};
+use crate::storage::{self, StorageKind};
+use crate::subcircuit::SubcircuitId;
+use serde::{Deserialize, Serialize};
 use strum_macros::EnumIter;
 
+/// Propagation delay given to newly created gates, in simulation time units.
+pub const DEFAULT_DELAY: u64 = 1;
+
+/// Period and high time of a `Clock` gate, in clock ticks.
+///
+/// Each period starts with `period - high` low ticks followed by `high` high
+/// ticks, so a clock starting at tick zero rises on its first tick.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ClockTiming {
+    pub period: u64,
+    pub high: u64,
+}
+
+impl ClockTiming {
+    /// Returns `true` if both phases last at least one tick.
+    pub fn is_valid(self) -> bool {
+        self.high > 0 && self.high < self.period
+    }
+
+    /// Fraction of each period the clock is high.
+    pub fn duty_cycle(self) -> f64 {
+        self.high as f64 / self.period as f64
+    }
+
+    /// Level of the clock during the given tick.
+    pub fn level(self, tick: u64) -> Logic {
+        Logic::from(tick % self.period >= self.period - self.high)
+    }
+}
+
+/// A square wave with a period of two ticks.
+impl Default for ClockTiming {
+    fn default() -> Self {
+        Self { period: 2, high: 1 }
+    }
+}
+
 /// Represents the different types of logic gates supported by the simulator.
-#[derive(EnumIter, PartialEq, Debug, Clone, Copy)]
+///
+/// `And`, `Or`, `Not`, `Xor` and `Input` work bitwise on buses of any width.
+/// `Clock` is a source like `Input` whose value is driven by the circuit's
+/// clock ticks instead of being set from outside.
+/// `Splitter` and `Merger` only rearrange bits between buses of different widths.
+/// `FlipFlop` and `Latch` are storage elements with `Q` and `Q̅` outputs; see
+/// [`StorageKind`] for their inputs. Flip-flops change on the rising edge of
+/// their clock, latches while it is high.
+/// `Memory` is a ROM or RAM whose contents are kept by the containing
+/// `Circuit`; see [`MemoryKind`](crate::memory::MemoryKind) for its ports.
+/// `Subcircuit` is an instance of a user-defined component registered with the
+/// containing `Circuit`; it is not offered as a primitive and evaluated by the circuit.
+#[derive(EnumIter, PartialEq, Eq, Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum GateType {
     And,
     Or,
     Not,
     Xor,
     Input,
+    Splitter,
+    Merger,
+    Clock(ClockTiming),
+    FlipFlop(StorageKind),
+    Latch(StorageKind),
+    Memory(MemorySpec),
+    #[strum(disabled)]
+    Subcircuit(SubcircuitId),
 }
 
-/// A logic gate with a specific type, input signals, and an output signal.
+impl GateType {
+    /// Returns `true` if a primitive gate of this type can have `input_count` inputs.
+    ///
+    /// The number of inputs of a subcircuit instance is fixed by its definition,
+    /// so this always returns `false` for `Subcircuit`.
+    pub fn accepts_input_count(self, input_count: usize) -> bool {
+        match self {
+            GateType::Input | GateType::Clock(_) => input_count == 0,
+            GateType::Not | GateType::Splitter => input_count == 1,
+            GateType::And | GateType::Or | GateType::Xor | GateType::Merger => input_count >= 1,
+            GateType::FlipFlop(kind) | GateType::Latch(kind) => input_count == kind.input_count(),
+            GateType::Memory(spec) => input_count == spec.kind.input_count(),
+            GateType::Subcircuit(_) => false,
+        }
+    }
+}
+
+/// A logic gate with a specific type, input signals, and one or more output signals.
 ///
-/// The gate evaluates its output based on the type and the current inputs.
-#[derive(Debug)]
+/// The gate evaluates its outputs based on the type and the current inputs.
+/// Storage elements have `Q` and `Q̅` outputs, other primitive gates a single
+/// output, and subcircuit instances one output per output port of their
+/// definition.
+/// `width` is the number of bits on the (first) output. A `Splitter` outputs the `width`
+/// bits of its input starting at `offset`; a `Merger` concatenates its inputs,
+/// each `width / input_count` bits wide, into its output.
+/// `delay` is the time an input change takes to reach the output during
+/// event-driven simulation; zero-delay evaluation ignores it.
+/// `name` is an optional label, such as the variable an input gate stands for.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Gate {
     pub gate_type: GateType,
     pub input_count: usize,
-    pub output: bool,
+    pub width: usize,
+    pub offset: usize,
+    pub outputs: Vec<Bus>,
+    pub delay: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
 }
 
 
 impl Gate {
     pub fn new(gate_type: GateType, input_count: usize) -> Self {
+        Self::with_width(gate_type, input_count, 1)
+    }
+
+    /// Creates a gate whose output is `width` bits wide.
+    pub fn with_width(gate_type: GateType, input_count: usize, width: usize) -> Self {
+        let output_count = match gate_type {
+            GateType::FlipFlop(_) | GateType::Latch(_) => 2,
+            _ => 1,
+        };
         Self {
             gate_type,
             input_count,
-            output: false,
+            width,
+            offset: 0,
+            outputs: vec![Bus::filled(width, Logic::X); output_count],
+            delay: DEFAULT_DELAY,
+            name: None,
+        }
+    }
+
+    /// Returns the value of the first output.
+    pub fn output(&self) -> &Bus {
+        &self.outputs[0]
+    }
+
+    pub fn output_count(&self) -> usize {
+        self.outputs.len()
+    }
+
+    /// Returns the width every input of this gate must have, or `None` if any
+    /// width is accepted or the inputs differ in width.
+    pub fn input_width(&self) -> Option<usize> {
+        match self.gate_type {
+            GateType::Splitter => None,
+            GateType::Merger => Some(self.width / self.input_count.max(1)),
+            // The definition of a subcircuit knows the width of each input port
+            GateType::Subcircuit(_) => None,
+            // Data inputs are as wide as the gate, control inputs are single bits
+            GateType::FlipFlop(_) | GateType::Latch(_) => None,
+            // Memories have an address, data and control inputs
+            GateType::Memory(_) => None,
+            _ => Some(self.width),
+        }
+    }
+
+    /// Returns the width input `input_index` must have, or `None` if any width
+    /// is accepted or the gate is a subcircuit instance.
+    pub fn input_port_width(&self, input_index: usize) -> Option<usize> {
+        match self.gate_type {
+            GateType::FlipFlop(kind) | GateType::Latch(kind) => {
+                Some(if input_index < kind.clock_index() { self.width } else { 1 })
+            }
+            GateType::Memory(spec) => Some(match input_index {
+                memory::ADDRESS => spec.address_width,
+                memory::DATA => self.width,
+                _ => 1,
+            }),
+            _ => self.input_width(),
+        }
+    }
+
+    /// Returns `true` if a bus of the given width may be connected to an input of this gate.
+    pub fn accepts_input_width(&self, width: usize) -> bool {
+        match self.gate_type {
+            GateType::Splitter => width >= self.offset + self.width,
+            GateType::Subcircuit(_) => true,
+            GateType::FlipFlop(_) | GateType::Latch(_) => width == self.width || width == 1,
+            GateType::Memory(spec) => width == spec.address_width || width == self.width || width == 1,
+            _ => self.input_width() == Some(width),
+        }
+    }
+
+    /// Returns `true` if a bus of the given width may be connected to input `input_index`.
+    pub fn accepts_port_width(&self, input_index: usize, width: usize) -> bool {
+        match self.gate_type {
+            GateType::FlipFlop(_) | GateType::Latch(_) | GateType::Memory(_) => {
+                self.input_port_width(input_index) == Some(width)
+            }
+            _ => self.accepts_input_width(width),
+        }
+    }
+
+    /// Computes the `Q` and `Q̅` outputs of a storage element from its inputs,
+    /// holding the current `Q` unless the element triggers.
+    ///
+    /// `previous_clock` is the clock level the flip-flop was last evaluated
+    /// with; latches ignore it. Other gates keep their current outputs.
+    pub fn evaluate_storage(&self, inputs: &[Bus], previous_clock: Logic) -> Vec<Bus> {
+        match self.gate_type {
+            GateType::FlipFlop(kind) => storage::next_outputs(kind, true, inputs, previous_clock, self.output()),
+            GateType::Latch(kind) => storage::next_outputs(kind, false, inputs, previous_clock, self.output()),
+            _ => self.outputs.clone(),
         }
     }
 
     /// Evaluate gate output based on given inputs
-    pub fn evaluate_with_inputs(&self, inputs: &[bool]) -> bool {
+    ///
+    /// This evaluates a single bit: buses are handled by [`Gate::evaluate_bus`],
+    /// which calls this once per bit. Unknown (`X`) and floating (`Z`) inputs
+    /// propagate as `X` unless a known input already decides the result, e.g. a
+    /// `Zero` on any input of an AND gate.
+    pub fn evaluate_with_inputs(&self, inputs: &[Logic]) -> Logic {
         match self.gate_type {
-            GateType::Input => {
+            GateType::Input
+            | GateType::Clock(_)
+            | GateType::FlipFlop(_)
+            | GateType::Latch(_)
+            | GateType::Memory(_) => {
                 // For inputs, output is externally set, so return stored output
-                self.output
+                self.output().bit(0)
             }
-            GateType::And => inputs.iter().all(|&x| x),
-            GateType::Or => inputs.iter().any(|&x| x),
+            GateType::And => Logic::all(inputs),
+            GateType::Or => Logic::any(inputs),
             GateType::Not => {
                 if inputs.len() != 1 {
-                    false
+                    Logic::X
                 } else {
                     !inputs[0]
                 }
             }
-            GateType::Xor => inputs.iter().filter(|&&x| x).count() % 2 == 1,
+            GateType::Xor => Logic::parity(inputs),
+            // Wiring components pass a single bit through unchanged
+            GateType::Splitter | GateType::Merger => inputs.first().copied().unwrap_or(Logic::Z),
+            GateType::Subcircuit(_) => Logic::X,
+        }
+    }
+
+    /// Evaluate the full output bus based on the given input buses
+    ///
+    /// Subcircuit instances and memories are evaluated by the containing
+    /// `Circuit` and storage elements by [`Gate::evaluate_storage`]; here they
+    /// keep their current first output.
+    pub fn evaluate_bus(&self, inputs: &[Bus]) -> Bus {
+        match self.gate_type {
+            GateType::Input
+            | GateType::Clock(_)
+            | GateType::FlipFlop(_)
+            | GateType::Latch(_)
+            | GateType::Memory(_)
+            | GateType::Subcircuit(_) => self.output().clone(),
+            GateType::Splitter => match inputs.first() {
+                Some(input) => input.slice(self.offset, self.width),
+                None => Bus::filled(self.width, Logic::Z),
+            },
+            GateType::Merger => Bus::concat(inputs),
+            _ => (0..self.width)
+                .map(|bit| {
+                    let lane: Vec<Logic> = inputs.iter().map(|input| input.bit(bit)).collect();
+                    self.evaluate_with_inputs(&lane)
+                })
+                .collect(),
+        }
+    }
+
+    /// Word-level counterpart of [`Gate::evaluate_with_inputs`]: evaluates one
+    /// bit for 64 independent input vectors at once.
+    pub fn evaluate_packed(&self, inputs: &[PackedLogic]) -> PackedLogic {
+        match self.gate_type {
+            GateType::Input
+            | GateType::Clock(_)
+            | GateType::FlipFlop(_)
+            | GateType::Latch(_)
+            | GateType::Memory(_) => {
+                PackedLogic::splat(self.output().bit(0))
+            }
+            GateType::And => PackedLogic::all(inputs),
+            GateType::Or => PackedLogic::any(inputs),
+            GateType::Not => {
+                if inputs.len() != 1 {
+                    PackedLogic::X
+                } else {
+                    !inputs[0]
+                }
+            }
+            GateType::Xor => PackedLogic::parity(inputs),
+            GateType::Splitter | GateType::Merger => inputs.first().copied().unwrap_or(PackedLogic::Z),
+            GateType::Subcircuit(_) => PackedLogic::X,
+        }
+    }
+
+    /// Word-level counterpart of [`Gate::evaluate_bus`], with one word per bit
+    /// of every input and of the output.
+    pub fn evaluate_packed_bus(&self, inputs: &[PackedBus]) -> PackedBus {
+        let bit = |bus: &PackedBus, i: usize| bus.get(i).copied().unwrap_or(PackedLogic::Z);
+        match self.gate_type {
+            GateType::Input
+            | GateType::Clock(_)
+            | GateType::FlipFlop(_)
+            | GateType::Latch(_)
+            | GateType::Memory(_)
+            | GateType::Subcircuit(_) => packed::splat_bus(self.output()),
+            GateType::Splitter => match inputs.first() {
+                Some(input) => (self.offset..self.offset + self.width).map(|i| bit(input, i)).collect(),
+                None => vec![PackedLogic::Z; self.width],
+            },
+            GateType::Merger => inputs.concat(),
+            _ => (0..self.width)
+                .map(|i| {
+                    let lane: Vec<PackedLogic> = inputs.iter().map(|input| bit(input, i)).collect();
+                    self.evaluate_packed(&lane)
+                })
+                .collect(),
         }
     }
 }
@@ -5,10 +5,46 @@
 //! and connections between gates.
 //!
 //! ## Modules
+//! - `logic`: Four-valued signal type (`0`, `1`, `X`, `Z`) carried by wires.
+//! - `bus`: Multi-bit values carried by bus wires.
+//! - `packed`: Bit-parallel signals for simulating 64 input vectors at once.
 //! - `gate`: Defines logic gate types and gate behavior.
+//! - `storage`: Next-state logic of the flip-flops and latches.
+//! - `memory`: Contents and ports of ROM and RAM components, and the image files they load.
 //! - `circuit`: Represents a circuit as a collection of gates and manages signal propagation.
 //! - `connection`: Manages connections between gates in the circuit.
+//! - `error`: Errors reported by the fallible circuit API.
+//! - `subcircuit`: User-defined components built from other circuits.
+//! - `library`: Standard combinational components such as multiplexers and adders.
+//! - `persistence`: Versioned JSON file format for saving and loading circuits.
+//! - `schedule`: Compiled, levelized evaluation order of a circuit's gates.
+//! - `event`: Event queue used by the event-driven simulation with propagation delays.
+//! - `expression`: Boolean expressions parsed into gates and derived back from them.
+//! - `bdd`: Reduced ordered binary decision diagrams of circuit outputs.
+//! - `equivalence`: Checks whether two circuits compute the same functions.
+//! - `cnf`: Tseitin encoding of circuits and miters as DIMACS CNF.
+//! - `minimize`: Quine-McCluskey minimization into a sum of products.
+//! - `truth_table`: Exhaustive truth tables of a circuit's outputs, with text exports.
+//! - `vectors`: Test vector files with expected outputs and a batch runner.
+pub mod logic;
+pub mod bus;
+pub mod packed;
 pub mod gate;
+pub mod storage;
+pub mod memory;
 pub mod circuit;
 pub mod connection;
+pub mod error;
+pub mod subcircuit;
+pub mod library;
+pub mod persistence;
+pub mod schedule;
+pub mod event;
+pub mod expression;
+pub mod minimize;
+pub mod bdd;
+pub mod equivalence;
+pub mod cnf;
+pub mod truth_table;
+pub mod vectors;
 pub mod ui;
@@ -0,0 +1,360 @@
+use crate::circuit::Circuit;
+use crate::connection::GateId;
+use crate::error::CircuitError;
+use crate::gate::GateType;
+use crate::subcircuit::Subcircuit;
+use serde::{Deserialize, Serialize};
+
+/// Largest number of select bits of a multiplexer, demultiplexer, decoder or
+/// priority encoder.
+pub const MAX_SELECT_WIDTH: usize = 6;
+/// Largest operand and data width of a library component.
+pub const MAX_WIDTH: usize = 64;
+
+/// A standard combinational component, built from primitive gates.
+///
+/// Each component is a [`Subcircuit`] whose inner circuit only contains
+/// `And`, `Or`, `Not` and `Xor` gates plus the splitters and mergers that take
+/// its buses apart, so an instance can be expanded into the gates it is made
+/// of with [`Circuit::expand`]. Selected lines are numbered from `0`, and
+/// select inputs and encoded outputs are binary numbers.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum Component {
+    /// Passes data input `D{S}` of `2^select_width` inputs to `Y`. Data inputs
+    /// and `Y` are `width` bits wide.
+    Multiplexer { select_width: usize, width: usize },
+    /// Passes `D` to output `Y{S}` of `2^select_width` outputs; the others are
+    /// `0`. `D` and the outputs are `width` bits wide.
+    Demultiplexer { select_width: usize, width: usize },
+    /// Sets output `Y{A}` of `2^select_width` single-bit outputs.
+    Decoder { select_width: usize },
+    /// Outputs on `Y` the highest index of the `2^select_width` single-bit
+    /// inputs `I0`, `I1`, … that is set, and on `V` whether any is.
+    PriorityEncoder { select_width: usize },
+    /// Adds `A`, `B` and the carry `CIN` into the sum `S` and carry `COUT`,
+    /// passing the carry from bit to bit.
+    RippleCarryAdder { width: usize },
+    /// Adds like [`Component::RippleCarryAdder`], but computes every carry
+    /// directly from the inputs in two levels of gates.
+    CarryLookaheadAdder { width: usize },
+    /// Compares the unsigned numbers `A` and `B`, setting one of `LT`, `EQ`
+    /// and `GT`.
+    Comparator { width: usize },
+}
+
+impl Component {
+    /// Every component with small default parameters, in declaration order.
+    pub const ALL: [Component; 7] = [
+        Component::Multiplexer { select_width: 1, width: 1 },
+        Component::Demultiplexer { select_width: 1, width: 1 },
+        Component::Decoder { select_width: 2 },
+        Component::PriorityEncoder { select_width: 2 },
+        Component::RippleCarryAdder { width: 4 },
+        Component::CarryLookaheadAdder { width: 4 },
+        Component::Comparator { width: 4 },
+    ];
+
+    /// Name of the kind of component, regardless of its parameters.
+    pub fn label(self) -> &'static str {
+        match self {
+            Component::Multiplexer { .. } => "Multiplexer",
+            Component::Demultiplexer { .. } => "Demultiplexer",
+            Component::Decoder { .. } => "Decoder",
+            Component::PriorityEncoder { .. } => "Priority encoder",
+            Component::RippleCarryAdder { .. } => "Ripple-carry adder",
+            Component::CarryLookaheadAdder { .. } => "Carry-lookahead adder",
+            Component::Comparator { .. } => "Comparator",
+        }
+    }
+
+    /// Short name of the component and its parameters, such as `MUX 4:1 [8]`,
+    /// used as the name of its subcircuit definition.
+    pub fn name(self) -> String {
+        let data = |width: usize| if width > 1 { format!(" [{}]", width) } else { String::new() };
+        match self {
+            Component::Multiplexer { select_width, width } => {
+                format!("MUX {}:1{}", 1usize << select_width, data(width))
+            }
+            Component::Demultiplexer { select_width, width } => {
+                format!("DEMUX 1:{}{}", 1usize << select_width, data(width))
+            }
+            Component::Decoder { select_width } => format!("DEC {}:{}", select_width, 1usize << select_width),
+            Component::PriorityEncoder { select_width } => {
+                format!("PENC {}:{}", 1usize << select_width, select_width)
+            }
+            Component::RippleCarryAdder { width } => format!("RCA {}", width),
+            Component::CarryLookaheadAdder { width } => format!("CLA {}", width),
+            Component::Comparator { width } => format!("CMP {}", width),
+        }
+    }
+
+    /// Returns `true` if select widths are between 1 and [`MAX_SELECT_WIDTH`]
+    /// and widths between 1 and [`MAX_WIDTH`].
+    pub fn is_valid(self) -> bool {
+        let select = |select_width: usize| (1..=MAX_SELECT_WIDTH).contains(&select_width);
+        let width = |width: usize| (1..=MAX_WIDTH).contains(&width);
+        match self {
+            Component::Multiplexer { select_width, width: w } | Component::Demultiplexer { select_width, width: w } => {
+                select(select_width) && width(w)
+            }
+            Component::Decoder { select_width } | Component::PriorityEncoder { select_width } => select(select_width),
+            Component::RippleCarryAdder { width: w }
+            | Component::CarryLookaheadAdder { width: w }
+            | Component::Comparator { width: w } => width(w),
+        }
+    }
+
+    /// Builds the subcircuit definition of the component, named after
+    /// [`Component::name`] and tagged with the component.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the parameters are not valid.
+    pub fn definition(self) -> Subcircuit {
+        if !self.is_valid() {
+            panic!("{}", CircuitError::InvalidComponent(self));
+        }
+        let mut b = Builder::default();
+        match self {
+            Component::Multiplexer { select_width, width } => {
+                let data: Vec<GateId> = (0..1 << select_width).map(|i| b.input(format!("D{}", i), width)).collect();
+                let select = b.input("S", select_width);
+                let lines = b.decode(select, select_width);
+                let mut terms = vec![];
+                for (&d, &line) in data.iter().zip(&lines) {
+                    let enable = b.widen(line, width);
+                    terms.push(b.gate(GateType::And, &[d, enable]));
+                }
+                let y = b.any(&terms);
+                b.output("Y", y);
+            }
+            Component::Demultiplexer { select_width, width } => {
+                let d = b.input("D", width);
+                let select = b.input("S", select_width);
+                for (i, line) in b.decode(select, select_width).into_iter().enumerate() {
+                    let enable = b.widen(line, width);
+                    let y = b.gate(GateType::And, &[d, enable]);
+                    b.output(format!("Y{}", i), y);
+                }
+            }
+            Component::Decoder { select_width } => {
+                let a = b.input("A", select_width);
+                for (i, line) in b.decode(a, select_width).into_iter().enumerate() {
+                    b.output(format!("Y{}", i), line);
+                }
+            }
+            Component::PriorityEncoder { select_width } => {
+                let inputs: Vec<GateId> = (0..1 << select_width).map(|i| b.input(format!("I{}", i), 1)).collect();
+                let inverted: Vec<GateId> = inputs[1..].iter().map(|&i| b.gate(GateType::Not, &[i])).collect();
+                // An input wins when no higher input is set
+                let winners: Vec<GateId> = (0..inputs.len())
+                    .map(|i| {
+                        let literals: Vec<GateId> = [inputs[i]].iter().chain(&inverted[i..]).copied().collect();
+                        b.all(&literals)
+                    })
+                    .collect();
+                let bits: Vec<GateId> = (0..select_width)
+                    .map(|bit| {
+                        let terms: Vec<GateId> = (0..winners.len())
+                            .filter(|i| i >> bit & 1 == 1)
+                            .map(|i| winners[i])
+                            .collect();
+                        b.any(&terms)
+                    })
+                    .collect();
+                let y = b.merge(&bits);
+                let v = b.any(&inputs);
+                b.output("Y", y);
+                b.output("V", v);
+            }
+            Component::RippleCarryAdder { width } => {
+                let (a_bits, b_bits, mut carry) = b.adder_inputs(width);
+                let mut sum = vec![];
+                for (&a, &b_bit) in a_bits.iter().zip(&b_bits) {
+                    let propagate = b.gate(GateType::Xor, &[a, b_bit]);
+                    sum.push(b.gate(GateType::Xor, &[propagate, carry]));
+                    let generate = b.gate(GateType::And, &[a, b_bit]);
+                    let carried = b.gate(GateType::And, &[propagate, carry]);
+                    carry = b.gate(GateType::Or, &[generate, carried]);
+                }
+                b.adder_outputs(&sum, carry);
+            }
+            Component::CarryLookaheadAdder { width } => {
+                let (a_bits, b_bits, carry_in) = b.adder_inputs(width);
+                let propagate: Vec<GateId> =
+                    (0..width).map(|i| b.gate(GateType::Xor, &[a_bits[i], b_bits[i]])).collect();
+                let generate: Vec<GateId> =
+                    (0..width).map(|i| b.gate(GateType::And, &[a_bits[i], b_bits[i]])).collect();
+                // The carry out of bit `i` is generated by it or a lower bit, or
+                // is the carry in, and propagated by every bit above that
+                let mut carries = vec![carry_in];
+                for i in 0..width {
+                    let mut terms = vec![generate[i]];
+                    for k in (0..i).rev() {
+                        let literals: Vec<GateId> =
+                            propagate[k + 1..=i].iter().chain([&generate[k]]).copied().collect();
+                        terms.push(b.all(&literals));
+                    }
+                    let literals: Vec<GateId> = propagate[..=i].iter().chain([&carry_in]).copied().collect();
+                    terms.push(b.all(&literals));
+                    carries.push(b.any(&terms));
+                }
+                let sum: Vec<GateId> = (0..width).map(|i| b.gate(GateType::Xor, &[propagate[i], carries[i]])).collect();
+                b.adder_outputs(&sum, carries[width]);
+            }
+            Component::Comparator { width } => {
+                let a = b.input("A", width);
+                let a_bits = b.bits(a, width);
+                let b_input = b.input("B", width);
+                let b_bits = b.bits(b_input, width);
+                let mut less = vec![];
+                let mut equal = vec![];
+                let mut greater = vec![];
+                for (&a, &b_bit) in a_bits.iter().zip(&b_bits) {
+                    let not_a = b.gate(GateType::Not, &[a]);
+                    let not_b = b.gate(GateType::Not, &[b_bit]);
+                    less.push(b.gate(GateType::And, &[not_a, b_bit]));
+                    greater.push(b.gate(GateType::And, &[a, not_b]));
+                    let differ = b.gate(GateType::Xor, &[a, b_bit]);
+                    equal.push(b.gate(GateType::Not, &[differ]));
+                }
+                // The highest differing bit decides
+                let decide = |b: &mut Builder, bits: &[GateId]| {
+                    let terms: Vec<GateId> = (0..width)
+                        .map(|i| {
+                            let literals: Vec<GateId> = [bits[i]].iter().chain(&equal[i + 1..]).copied().collect();
+                            b.all(&literals)
+                        })
+                        .collect();
+                    b.any(&terms)
+                };
+                let lt = decide(&mut b, &less);
+                let gt = decide(&mut b, &greater);
+                let eq = b.all(&equal);
+                b.output("LT", lt);
+                b.output("EQ", eq);
+                b.output("GT", gt);
+            }
+        }
+        let mut definition = b.finish(self.name());
+        definition.set_component(self);
+        definition
+    }
+}
+
+/// Builds the inner circuit of a component, wiring each gate as it is added.
+#[derive(Default)]
+struct Builder {
+    circuit: Circuit,
+    inputs: Vec<(String, GateId)>,
+    outputs: Vec<(String, GateId)>,
+}
+
+impl Builder {
+    /// Adds an input gate backing a named input port.
+    fn input(&mut self, name: impl Into<String>, width: usize) -> GateId {
+        let name = name.into();
+        let id = self.circuit.add_bus_gate(GateType::Input, 0, width);
+        self.circuit.set_name(id, name.clone());
+        self.inputs.push((name, id));
+        id
+    }
+
+    /// Exports the output of a gate as a named output port.
+    fn output(&mut self, name: impl Into<String>, gate: GateId) {
+        self.outputs.push((name.into(), gate));
+    }
+
+    /// Adds a gate as wide as its first input, driven by `inputs` in order.
+    fn gate(&mut self, gate_type: GateType, inputs: &[GateId]) -> GateId {
+        let width = self.circuit.gate(inputs[0]).output().width();
+        let id = self.circuit.add_bus_gate(gate_type, inputs.len(), width);
+        for (input_index, &from) in inputs.iter().enumerate() {
+            self.circuit.connect(from, id, input_index);
+        }
+        id
+    }
+
+    /// AND of single bits, without a gate for a single bit.
+    fn all(&mut self, bits: &[GateId]) -> GateId {
+        if bits.len() == 1 { bits[0] } else { self.gate(GateType::And, bits) }
+    }
+
+    /// OR of single bits, without a gate for a single bit.
+    fn any(&mut self, bits: &[GateId]) -> GateId {
+        if bits.len() == 1 { bits[0] } else { self.gate(GateType::Or, bits) }
+    }
+
+    /// Splits a bus into its bits, least significant first.
+    fn bits(&mut self, bus: GateId, width: usize) -> Vec<GateId> {
+        if width == 1 {
+            return vec![bus];
+        }
+        (0..width)
+            .map(|bit| {
+                let id = self.circuit.add_splitter(bit, 1);
+                self.circuit.connect(bus, id, 0);
+                id
+            })
+            .collect()
+    }
+
+    /// Joins single bits into a bus, least significant first.
+    fn merge(&mut self, bits: &[GateId]) -> GateId {
+        if bits.len() == 1 {
+            return bits[0];
+        }
+        let id = self.circuit.add_merger(bits.len(), 1);
+        for (input_index, &bit) in bits.iter().enumerate() {
+            self.circuit.connect(bit, id, input_index);
+        }
+        id
+    }
+
+    /// Repeats a single bit across a bus of `width` bits.
+    fn widen(&mut self, bit: GateId, width: usize) -> GateId {
+        self.merge(&vec![bit; width])
+    }
+
+    /// One line per value of a `select_width`-bit bus, set while the bus has
+    /// that value.
+    fn decode(&mut self, select: GateId, select_width: usize) -> Vec<GateId> {
+        let bits = self.bits(select, select_width);
+        let inverted: Vec<GateId> = bits.iter().map(|&bit| self.gate(GateType::Not, &[bit])).collect();
+        (0..1usize << select_width)
+            .map(|value| {
+                let literals: Vec<GateId> = (0..select_width)
+                    .map(|bit| if value >> bit & 1 == 1 { bits[bit] } else { inverted[bit] })
+                    .collect();
+                self.all(&literals)
+            })
+            .collect()
+    }
+
+    /// Adds the `A`, `B` and `CIN` ports of an adder, returning the bits of
+    /// both operands and the carry in.
+    fn adder_inputs(&mut self, width: usize) -> (Vec<GateId>, Vec<GateId>, GateId) {
+        let a = self.input("A", width);
+        let b = self.input("B", width);
+        let carry = self.input("CIN", 1);
+        (self.bits(a, width), self.bits(b, width), carry)
+    }
+
+    /// Adds the `S` and `COUT` ports of an adder.
+    fn adder_outputs(&mut self, sum: &[GateId], carry: GateId) {
+        let sum = self.merge(sum);
+        self.output("S", sum);
+        self.output("COUT", carry);
+    }
+
+    fn finish(self, name: String) -> Subcircuit {
+        let mut definition = Subcircuit::new(name, self.circuit);
+        for (name, gate) in self.inputs {
+            definition.add_input(name, gate);
+        }
+        for (name, gate) in self.outputs {
+            definition.add_output(name, gate);
+        }
+        definition
+    }
+}
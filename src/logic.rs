@@ -0,0 +1,142 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::ops::{BitAnd, BitOr, BitXor, Not};
+
+/// A four-valued logic signal.
+///
+/// Besides the two boolean levels, a signal can be unknown (`X`), e.g. a gate
+/// that has not been evaluated yet or a conflict, or floating (`Z`), e.g. an
+/// input that is not connected to anything. Gates treat `Z` on an input like `X`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum Logic {
+    Zero,
+    One,
+    #[default]
+    X,
+    Z,
+}
+
+impl Logic {
+    /// Returns `true` for `Zero` and `One`.
+    pub fn is_known(self) -> bool {
+        matches!(self, Logic::Zero | Logic::One)
+    }
+
+    /// Converts the signal to a boolean, or `None` if it is `X` or `Z`.
+    pub fn to_bool(self) -> Option<bool> {
+        match self {
+            Logic::Zero => Some(false),
+            Logic::One => Some(true),
+            Logic::X | Logic::Z => None,
+        }
+    }
+
+    /// Parses a single signal character: `0`, `1`, `X` or `Z` (case-insensitive).
+    pub fn from_char(c: char) -> Option<Logic> {
+        match c {
+            '0' => Some(Logic::Zero),
+            '1' => Some(Logic::One),
+            'x' | 'X' => Some(Logic::X),
+            'z' | 'Z' => Some(Logic::Z),
+            _ => None,
+        }
+    }
+
+    /// AND over any number of signals. A single `Zero` forces the result to `Zero`.
+    pub fn all(values: &[Logic]) -> Logic {
+        values.iter().fold(Logic::One, |acc, &v| acc & v)
+    }
+
+    /// OR over any number of signals. A single `One` forces the result to `One`.
+    pub fn any(values: &[Logic]) -> Logic {
+        values.iter().fold(Logic::Zero, |acc, &v| acc | v)
+    }
+
+    /// XOR (odd parity) over any number of signals. Any unknown input makes the result `X`.
+    pub fn parity(values: &[Logic]) -> Logic {
+        values.iter().fold(Logic::Zero, |acc, &v| acc ^ v)
+    }
+
+    /// Value of a wire driven by both signals at once.
+    ///
+    /// A floating driver (`Z`) gives way to the other one; two drivers pulling
+    /// in different directions produce `X`.
+    pub fn resolve(self, other: Logic) -> Logic {
+        match (self, other) {
+            (Logic::Z, value) | (value, Logic::Z) => value,
+            (a, b) if a == b => a,
+            _ => Logic::X,
+        }
+    }
+}
+
+impl From<bool> for Logic {
+    fn from(value: bool) -> Self {
+        if value { Logic::One } else { Logic::Zero }
+    }
+}
+
+impl PartialEq<bool> for Logic {
+    fn eq(&self, other: &bool) -> bool {
+        *self == Logic::from(*other)
+    }
+}
+
+impl fmt::Display for Logic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = match self {
+            Logic::Zero => "0",
+            Logic::One => "1",
+            Logic::X => "X",
+            Logic::Z => "Z",
+        };
+        f.write_str(symbol)
+    }
+}
+
+impl Not for Logic {
+    type Output = Logic;
+
+    fn not(self) -> Logic {
+        match self {
+            Logic::Zero => Logic::One,
+            Logic::One => Logic::Zero,
+            Logic::X | Logic::Z => Logic::X,
+        }
+    }
+}
+
+impl BitAnd for Logic {
+    type Output = Logic;
+
+    fn bitand(self, rhs: Logic) -> Logic {
+        match (self, rhs) {
+            (Logic::Zero, _) | (_, Logic::Zero) => Logic::Zero,
+            (Logic::One, Logic::One) => Logic::One,
+            _ => Logic::X,
+        }
+    }
+}
+
+impl BitOr for Logic {
+    type Output = Logic;
+
+    fn bitor(self, rhs: Logic) -> Logic {
+        match (self, rhs) {
+            (Logic::One, _) | (_, Logic::One) => Logic::One,
+            (Logic::Zero, Logic::Zero) => Logic::Zero,
+            _ => Logic::X,
+        }
+    }
+}
+
+impl BitXor for Logic {
+    type Output = Logic;
+
+    fn bitxor(self, rhs: Logic) -> Logic {
+        match (self.to_bool(), rhs.to_bool()) {
+            (Some(a), Some(b)) => Logic::from(a ^ b),
+            _ => Logic::X,
+        }
+    }
+}
@@ -1,17 +1,4 @@
-mod ui;
-mod circuit;
-mod gate;
-mod connection;
-
-use ui::CircuitEditor;
-use eframe::egui;
-
-
-impl eframe::App for CircuitEditor {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        self.draw(ctx);
-    }
-}
+use digital_logic_simulator::ui::CircuitEditor;
 
 
 fn main() -> eframe::Result<()> {
@@ -22,5 +9,3 @@ fn main() -> eframe::Result<()> {
         Box::new(|_cc| Box::new(CircuitEditor::new())),
     )
 }
-
-
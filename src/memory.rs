@@ -0,0 +1,451 @@
+use crate::bus::Bus;
+use crate::error::CircuitError;
+use crate::logic::Logic;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+/// Input selecting the word to read or write.
+pub const ADDRESS: usize = 0;
+/// Input that enables the memory; a deselected memory neither reads nor writes.
+pub const CHIP_SELECT: usize = 1;
+/// Input that drives the addressed word onto the output while high.
+pub const READ: usize = 2;
+/// Input holding the word a RAM writes.
+pub const DATA: usize = 3;
+/// Input that makes a RAM write on the rising edge of its clock.
+pub const WRITE: usize = 4;
+/// Clock input of a RAM.
+pub const CLOCK: usize = 5;
+
+/// Names of the inputs of a RAM, in port order; a ROM has the first three.
+const INPUT_NAMES: [&str; 6] = ["A", "CS", "RD", "DIN", "WR", "CLK"];
+/// Name of the single output of a memory.
+pub const OUTPUT_NAME: &str = "DOUT";
+
+/// Widest address a memory can have, giving 65536 words.
+pub const MAX_ADDRESS_WIDTH: usize = 16;
+/// Widest word a memory can store, so that images can hold words as integers.
+pub const MAX_DATA_WIDTH: usize = 64;
+
+/// Whether a memory can be written by the circuit.
+///
+/// Both kinds read asynchronously: while `CS` and `RD` are high, `DOUT` holds
+/// the word at address `A`, and otherwise it floats. A RAM additionally
+/// stores `DIN` at address `A` on the rising edge of `CLK` while `CS` and `WR`
+/// are high. The address is as wide as the memory's address, `DIN` and `DOUT`
+/// as wide as a word, and the control inputs are single bits. Unconnected `CS`
+/// and `RD` inputs are active and an unconnected `WR` is inactive.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum MemoryKind {
+    /// Read-only memory, whose contents are only changed by loading an image
+    /// or writing to it from outside the circuit.
+    #[default]
+    Rom,
+    /// Random-access memory.
+    Ram,
+}
+
+impl MemoryKind {
+    /// Every kind, in declaration order.
+    pub const ALL: [MemoryKind; 2] = [MemoryKind::Rom, MemoryKind::Ram];
+
+    /// Names of all inputs, in port order.
+    pub fn input_names(self) -> &'static [&'static str] {
+        match self {
+            MemoryKind::Rom => &INPUT_NAMES[..DATA],
+            MemoryKind::Ram => &INPUT_NAMES,
+        }
+    }
+
+    /// Number of inputs of a memory of this kind.
+    pub fn input_count(self) -> usize {
+        self.input_names().len()
+    }
+}
+
+/// Kind and address width of a memory; the width of its words is the width
+/// of its gate.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct MemorySpec {
+    pub kind: MemoryKind,
+    pub address_width: usize,
+}
+
+impl MemorySpec {
+    /// Number of words the memory holds.
+    pub fn word_count(self) -> usize {
+        1 << self.address_width
+    }
+
+    /// Returns `true` if a memory with words of `data_width` bits can be built.
+    pub fn is_valid(self, data_width: usize) -> bool {
+        (1..=MAX_ADDRESS_WIDTH).contains(&self.address_width) && (1..=MAX_DATA_WIDTH).contains(&data_width)
+    }
+}
+
+/// A ROM of 16 words.
+impl Default for MemorySpec {
+    fn default() -> Self {
+        Self { kind: MemoryKind::Rom, address_width: 4 }
+    }
+}
+
+/// The contents of a memory: a power of two of words of equal width.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Memory {
+    words: Vec<Bus>,
+}
+
+impl Memory {
+    /// Creates the contents of a memory with words of `data_width` bits, all zero.
+    pub fn new(spec: MemorySpec, data_width: usize) -> Self {
+        Self { words: vec![Bus::from_u64(0, data_width); spec.word_count()] }
+    }
+
+    /// Number of words.
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    /// Width of every word.
+    pub fn data_width(&self) -> usize {
+        self.words.first().map_or(0, Bus::width)
+    }
+
+    pub fn words(&self) -> &[Bus] {
+        &self.words
+    }
+
+    /// Returns the word at `address`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `address` is out of bounds.
+    pub fn word(&self, address: usize) -> &Bus {
+        &self.words[address]
+    }
+
+    /// Replaces the word at `address`; the value must be as wide as a word.
+    pub(crate) fn set_word(&mut self, address: usize, value: Bus) {
+        self.words[address] = value;
+    }
+
+    /// Reads the word selected by an address bus.
+    ///
+    /// If some address bits are unknown, bits that differ between the words
+    /// the address could select are `X`.
+    pub fn read(&self, address: &Bus) -> Bus {
+        let mut candidates = self.addresses(address).into_iter().map(|a| &self.words[a]);
+        let first = candidates.next().expect("every address matches at least one word").clone();
+        candidates.fold(first, |value, word| {
+            value
+                .bits()
+                .iter()
+                .zip(word.bits())
+                .map(|(&a, &b)| if a == b { a } else { Logic::X })
+                .collect()
+        })
+    }
+
+    /// Writes `value` to the word selected by an address bus if `trigger` is `One`.
+    ///
+    /// When it is unknown whether or where the write happens, bits of the
+    /// words it could change that differ from `value` become `X`.
+    fn write(&mut self, address: &Bus, value: &Bus, trigger: Logic) {
+        if trigger == Logic::Zero {
+            return;
+        }
+        let candidates = self.addresses(address);
+        if trigger == Logic::One && candidates.len() == 1 {
+            self.words[candidates[0]] = value.clone();
+            return;
+        }
+        for address in candidates {
+            let word = &mut self.words[address];
+            *word = word
+                .bits()
+                .iter()
+                .zip(value.bits())
+                .map(|(&old, &new)| if old == new { old } else { Logic::X })
+                .collect();
+        }
+    }
+
+    /// Every address whose bits agree with the known bits of `address`.
+    fn addresses(&self, address: &Bus) -> Vec<usize> {
+        let (mut mask, mut known) = (0, 0);
+        for (i, bit) in address.bits().iter().enumerate().take(self.len().trailing_zeros() as usize) {
+            if let Some(value) = bit.to_bool() {
+                mask |= 1 << i;
+                known |= (value as usize) << i;
+            }
+        }
+        (0..self.len()).filter(|a| a & mask == known).collect()
+    }
+
+    /// Replaces the contents with an image, setting the words it does not
+    /// cover to zero. Bits of image words beyond the word width are ignored.
+    ///
+    /// Returns the number of words the image sets. The contents are left
+    /// unchanged if the image is invalid.
+    pub fn load(&mut self, format: ImageFormat, bytes: &[u8]) -> Result<usize, ImageError> {
+        let data_width = self.data_width();
+        let words = match format {
+            ImageFormat::IntelHex => words_from_bytes(parse_intel_hex(text(bytes)?)?, data_width, self.len())?,
+            ImageFormat::Binary => words_from_bytes(bytes.iter().copied().enumerate(), data_width, self.len())?,
+            ImageFormat::LogisimRaw => parse_logisim_raw(text(bytes)?, self.len())?,
+        };
+        self.words.fill(Bus::from_u64(0, data_width));
+        for (&address, &value) in &words {
+            self.words[address] = Bus::from_u64(value, data_width);
+        }
+        Ok(words.len())
+    }
+}
+
+/// Computes the `DOUT` output of a memory from its inputs.
+pub fn read_port(memory: &Memory, inputs: &[Bus]) -> Bus {
+    let selected = control_level(inputs[CHIP_SELECT].bit(0), Logic::One);
+    let enabled = selected & control_level(inputs[READ].bit(0), Logic::One);
+    match enabled {
+        Logic::One => memory.read(&inputs[ADDRESS]),
+        Logic::Zero => Bus::filled(memory.data_width(), Logic::Z),
+        _ => Bus::filled(memory.data_width(), Logic::X),
+    }
+}
+
+/// Performs the write of a RAM whose clock was `previous_clock` when it was
+/// last evaluated, given its current inputs.
+pub fn write_port(memory: &mut Memory, inputs: &[Bus], previous_clock: Logic) {
+    let trigger = !previous_clock
+        & inputs[CLOCK].bit(0)
+        & control_level(inputs[CHIP_SELECT].bit(0), Logic::One)
+        & control_level(inputs[WRITE].bit(0), Logic::Zero);
+    memory.write(&inputs[ADDRESS], &inputs[DATA], trigger);
+}
+
+/// The level of a control input, reading a floating input as `unconnected`.
+fn control_level(value: Logic, unconnected: Logic) -> Logic {
+    if value == Logic::Z { unconnected } else { value }
+}
+
+/// File formats memory images can be loaded from.
+///
+/// - Intel HEX files hold bytes at byte addresses in `:`-prefixed records,
+///   with extended segment and linear address records for large images.
+/// - Raw binary files hold every byte from address zero on.
+/// - Logisim `v2.0 raw` files start with the line `v2.0 raw` followed by one
+///   hexadecimal value per word; `n*value` repeats a value `n` times and `#`
+///   starts a comment.
+///
+/// In the byte-oriented formats each word takes as many bytes as it needs,
+/// least significant byte first.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub enum ImageFormat {
+    #[default]
+    IntelHex,
+    Binary,
+    LogisimRaw,
+}
+
+impl ImageFormat {
+    /// Every format, in declaration order.
+    pub const ALL: [ImageFormat; 3] = [ImageFormat::IntelHex, ImageFormat::Binary, ImageFormat::LogisimRaw];
+
+    /// Name of the format as shown to users.
+    pub fn name(self) -> &'static str {
+        match self {
+            ImageFormat::IntelHex => "Intel HEX",
+            ImageFormat::Binary => "Raw binary",
+            ImageFormat::LogisimRaw => "v2.0 raw",
+        }
+    }
+
+    /// Guesses the format of an image from its contents and file name:
+    /// `v2.0 raw` files by their header, Intel HEX files by a `.hex`, `.ihx`
+    /// or `.ihex` extension, and anything else as raw binary.
+    pub fn detect(path: impl AsRef<Path>, bytes: &[u8]) -> Self {
+        let extension = path.as_ref().extension().and_then(|e| e.to_str()).unwrap_or_default();
+        if bytes.starts_with(b"v2.0 raw") {
+            ImageFormat::LogisimRaw
+        } else if ["hex", "ihx", "ihex"].iter().any(|e| extension.eq_ignore_ascii_case(e)) {
+            ImageFormat::IntelHex
+        } else {
+            ImageFormat::Binary
+        }
+    }
+}
+
+/// Errors in a memory image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImageError {
+    /// A text image is not valid UTF-8.
+    NotText,
+    /// A line of an Intel HEX file is not a well-formed record.
+    InvalidRecord { line: usize },
+    /// The checksum of an Intel HEX record does not match its contents.
+    ChecksumMismatch { line: usize },
+    /// An Intel HEX record has a type other than `00` to `05`.
+    UnsupportedRecord { line: usize, record_type: u8 },
+    /// A Logisim image does not start with `v2.0 raw`.
+    MissingHeader,
+    /// A token of a Logisim image is neither a hexadecimal value nor a run.
+    InvalidValue { line: usize, token: String },
+    /// The image holds a word at an address past the end of the memory.
+    OutOfRange { address: usize, word_count: usize },
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageError::NotText => f.write_str("image is not a text file"),
+            ImageError::InvalidRecord { line } => write!(f, "line {}: not an Intel HEX record", line),
+            ImageError::ChecksumMismatch { line } => write!(f, "line {}: checksum mismatch", line),
+            ImageError::UnsupportedRecord { line, record_type } => {
+                write!(f, "line {}: unsupported record type {:02X}", line, record_type)
+            }
+            ImageError::MissingHeader => f.write_str("expected 'v2.0 raw' on the first line"),
+            ImageError::InvalidValue { line, token } => write!(f, "line {}: invalid value '{}'", line, token),
+            ImageError::OutOfRange { address, word_count } => {
+                write!(f, "word {} is past the end of a memory of {} words", address, word_count)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ImageError {}
+
+/// Errors from loading an image into a memory of a circuit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoadError {
+    /// The gate is missing or not a memory.
+    Circuit(CircuitError),
+    /// The image is malformed or does not fit the memory.
+    Image(ImageError),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Circuit(err) => err.fmt(f),
+            LoadError::Image(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+impl From<CircuitError> for LoadError {
+    fn from(err: CircuitError) -> Self {
+        LoadError::Circuit(err)
+    }
+}
+
+impl From<ImageError> for LoadError {
+    fn from(err: ImageError) -> Self {
+        LoadError::Image(err)
+    }
+}
+
+fn text(bytes: &[u8]) -> Result<&str, ImageError> {
+    std::str::from_utf8(bytes).map_err(|_| ImageError::NotText)
+}
+
+/// Returns the bytes stored by an Intel HEX file with their byte addresses.
+fn parse_intel_hex(text: &str) -> Result<Vec<(usize, u8)>, ImageError> {
+    let mut bytes = vec![];
+    let mut base = 0;
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let record: Vec<u8> = line
+            .strip_prefix(':')
+            .filter(|digits| digits.len() % 2 == 0 && digits.is_ascii())
+            .and_then(|digits| {
+                (0..digits.len())
+                    .step_by(2)
+                    .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).ok())
+                    .collect()
+            })
+            .ok_or(ImageError::InvalidRecord { line: line_number })?;
+        if record.len() < 5 || record.len() != 5 + record[0] as usize {
+            return Err(ImageError::InvalidRecord { line: line_number });
+        }
+        if record.iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte)) != 0 {
+            return Err(ImageError::ChecksumMismatch { line: line_number });
+        }
+        let offset = u16::from_be_bytes([record[1], record[2]]) as usize;
+        let data = &record[4..record.len() - 1];
+        let value = || match data {
+            [high, low] => Ok(u16::from_be_bytes([*high, *low]) as usize),
+            _ => Err(ImageError::InvalidRecord { line: line_number }),
+        };
+        match record[3] {
+            0x00 => bytes.extend(data.iter().enumerate().map(|(i, &byte)| (base + offset + i, byte))),
+            0x01 => break,
+            0x02 => base = value()? << 4,
+            0x04 => base = value()? << 16,
+            // Start addresses only matter to processors
+            0x03 | 0x05 => {}
+            record_type => return Err(ImageError::UnsupportedRecord { line: line_number, record_type }),
+        }
+    }
+    Ok(bytes)
+}
+
+/// Assembles words from bytes at byte addresses, least significant byte first.
+fn words_from_bytes(
+    bytes: impl IntoIterator<Item = (usize, u8)>,
+    data_width: usize,
+    word_count: usize,
+) -> Result<BTreeMap<usize, u64>, ImageError> {
+    let bytes_per_word = data_width.div_ceil(8);
+    let mut words = BTreeMap::new();
+    for (byte_address, byte) in bytes {
+        let address = byte_address / bytes_per_word;
+        if address >= word_count {
+            return Err(ImageError::OutOfRange { address, word_count });
+        }
+        let shift = 8 * (byte_address % bytes_per_word);
+        *words.entry(address).or_insert(0) |= (byte as u64) << shift;
+    }
+    Ok(words)
+}
+
+/// Returns the words of a Logisim `v2.0 raw` file by address.
+fn parse_logisim_raw(text: &str, word_count: usize) -> Result<BTreeMap<usize, u64>, ImageError> {
+    let mut lines = text.lines().enumerate();
+    if lines.next().map(|(_, line)| line.trim()) != Some("v2.0 raw") {
+        return Err(ImageError::MissingHeader);
+    }
+    let mut words = BTreeMap::new();
+    let mut address: usize = 0;
+    for (index, line) in lines {
+        let line = line.split('#').next().unwrap_or_default();
+        for token in line.split_whitespace() {
+            let invalid = || ImageError::InvalidValue { line: index + 1, token: token.to_string() };
+            let (count, value) = match token.split_once('*') {
+                Some((count, value)) => (count.parse().map_err(|_| invalid())?, value),
+                None => (1, token),
+            };
+            let value = u64::from_str_radix(value, 16).map_err(|_| invalid())?;
+            // Report the first address of the run that does not fit
+            let end = address
+                .checked_add(count)
+                .filter(|&end| end <= word_count)
+                .ok_or(ImageError::OutOfRange { address: address.max(word_count), word_count })?;
+            words.extend((address..end).map(|a| (a, value)));
+            address = end;
+        }
+    }
+    Ok(words)
+}
@@ -0,0 +1,307 @@
+use crate::circuit::Circuit;
+use crate::connection::GateId;
+use crate::expression::Expr;
+use crate::logic::Logic;
+use crate::truth_table::TruthTable;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// Number of branches the exact cover search explores before settling for the
+/// best cover found so far.
+const SEARCH_LIMIT: usize = 100_000;
+
+/// A product term: the minterms whose bits in `care` equal those of `value`.
+///
+/// Bit `p` stands for bit `p` of a truth table row number, so with `n`
+/// variables it is the literal of variable `n - 1 - p`. Bits outside `care`
+/// are zero in `value`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Implicant {
+    pub care: u64,
+    pub value: u64,
+}
+
+impl Implicant {
+    /// Returns `true` if the term is true for the given row number.
+    pub fn covers(self, minterm: u64) -> bool {
+        minterm & self.care == self.value
+    }
+
+    /// Number of literals in the term.
+    pub fn literal_count(self) -> usize {
+        self.care.count_ones() as usize
+    }
+}
+
+/// A boolean function written as an OR of AND terms over named variables.
+///
+/// An empty list of terms is the constant `0`; a term without literals is the
+/// constant `1`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SumOfProducts {
+    pub variables: Vec<String>,
+    pub terms: Vec<Implicant>,
+}
+
+impl SumOfProducts {
+    /// Finds a minimal sum of products that is true for every row number in
+    /// `ones`, false for every row outside `ones` and `dont_cares`, and either
+    /// for the rows in `dont_cares`.
+    ///
+    /// The result uses as few terms as possible, and as few literals as
+    /// possible among covers with that many terms. Prime implicants are found
+    /// with the Quine-McCluskey method and the cover is chosen by an exact
+    /// search; for functions where that search runs too long the best cover
+    /// found so far is returned.
+    pub fn minimize(variables: Vec<String>, ones: &[u64], dont_cares: &[u64]) -> Self {
+        let primes = prime_implicants(variables.len(), ones.iter().chain(dont_cares).copied());
+        let mut terms = select_cover(&primes, ones);
+        let n = variables.len();
+        terms.sort_by_key(|&term| literal_pattern(term, n));
+        Self { variables, terms }
+    }
+
+    /// Minimizes bit `bit` of output column `output` of a truth table.
+    ///
+    /// Rows where that bit is `X` or `Z` are don't-cares. Each bit of an input
+    /// column is a variable; bus inputs give one variable per bit, named
+    /// `<label>_<bit>` and ordered from the most significant bit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the column or bit does not exist.
+    pub fn from_truth_table(table: &TruthTable, output: usize, bit: usize) -> Self {
+        let widths = table.rows.first().map_or(vec![1; table.inputs.len()], |row| {
+            row.inputs.iter().map(|value| value.width()).collect()
+        });
+        let mut variables = vec![];
+        for (label, &width) in table.input_labels.iter().zip(&widths) {
+            if width == 1 {
+                variables.push(label.clone());
+            } else {
+                variables.extend((0..width).rev().map(|k| format!("{}_{}", label, k)));
+            }
+        }
+
+        let mut ones = vec![];
+        let mut dont_cares = vec![];
+        for (minterm, row) in table.rows.iter().enumerate() {
+            match row.outputs[output].bit(bit) {
+                Logic::One => ones.push(minterm as u64),
+                Logic::Zero => {}
+                Logic::X | Logic::Z => dont_cares.push(minterm as u64),
+            }
+        }
+        Self::minimize(variables, &ones, &dont_cares)
+    }
+
+    /// Total number of literals over all terms.
+    pub fn literal_count(&self) -> usize {
+        self.terms.iter().map(|term| term.literal_count()).sum()
+    }
+
+    /// Returns the function as an expression.
+    ///
+    /// Constant functions are written as `v & !v` or `v | !v` with the first
+    /// variable, since circuits have no constant gates. Returns `None` for a
+    /// constant function without variables.
+    pub fn to_expr(&self) -> Option<Expr> {
+        let n = self.variables.len();
+        let literal = |j: usize, value: u64| {
+            let var = Expr::Var(self.variables[j].clone());
+            if (value >> (n - 1 - j)) & 1 == 1 { var } else { Expr::Not(Box::new(var)) }
+        };
+        let constant = |one: bool| {
+            let first = self.variables.first()?;
+            let var = Expr::Var(first.clone());
+            let operands = vec![var.clone(), Expr::Not(Box::new(var))];
+            Some(if one { Expr::Or(operands) } else { Expr::And(operands) })
+        };
+
+        let mut terms = vec![];
+        for term in &self.terms {
+            let mut literals: Vec<Expr> = (0..n)
+                .filter(|&j| (term.care >> (n - 1 - j)) & 1 == 1)
+                .map(|j| literal(j, term.value))
+                .collect();
+            terms.push(match literals.len() {
+                0 => return constant(true),
+                1 => literals.pop().expect("one literal"),
+                _ => Expr::And(literals),
+            });
+        }
+        match terms.len() {
+            0 => constant(false),
+            1 => terms.pop(),
+            _ => Some(Expr::Or(terms)),
+        }
+    }
+
+    /// Builds a circuit computing the function and returns it with its output
+    /// gate, or `None` for a constant function without variables.
+    pub fn synthesize(&self) -> Option<(Circuit, GateId)> {
+        self.to_expr().map(|expr| expr.to_circuit())
+    }
+}
+
+impl fmt::Display for SumOfProducts {
+    /// Writes the terms joined by `|`, or `0` or `1` for constant functions.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.terms.is_empty() {
+            return f.write_str("0");
+        }
+        if self.terms.iter().any(|term| term.care == 0) {
+            return f.write_str("1");
+        }
+        match self.to_expr() {
+            Some(expr) => write!(f, "{}", expr),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Sort key listing, for each variable from the first, whether the term has
+/// it negated (0), positive (1) or not at all (2).
+fn literal_pattern(term: Implicant, n: usize) -> Vec<u8> {
+    (0..n)
+        .map(|j| {
+            let bit = 1 << (n - 1 - j);
+            if term.care & bit == 0 {
+                2
+            } else {
+                u8::from(term.value & bit != 0)
+            }
+        })
+        .collect()
+}
+
+/// Merges minterms into ever larger implicants until none can be merged,
+/// returning the implicants that were never merged.
+fn prime_implicants(n: usize, minterms: impl Iterator<Item = u64>) -> Vec<Implicant> {
+    let all = if n == 64 { !0 } else { (1u64 << n) - 1 };
+    let mut current: HashSet<Implicant> = minterms.map(|value| Implicant { care: all, value }).collect();
+    let mut primes = vec![];
+    while !current.is_empty() {
+        let mut next = HashSet::new();
+        let mut merged = HashSet::new();
+        for &term in &current {
+            // Pair each term with the one differing only in a cared-for zero bit
+            let mut bits = term.care & !term.value;
+            while bits != 0 {
+                let bit = bits & bits.wrapping_neg();
+                bits &= bits - 1;
+                let partner = Implicant { care: term.care, value: term.value | bit };
+                if current.contains(&partner) {
+                    merged.insert(term);
+                    merged.insert(partner);
+                    next.insert(Implicant { care: term.care & !bit, value: term.value });
+                }
+            }
+        }
+        primes.extend(current.into_iter().filter(|term| !merged.contains(term)));
+        current = next;
+    }
+    primes.sort_by_key(|term| (term.care, term.value));
+    primes
+}
+
+/// Picks primes covering every minterm in `ones` at the lowest cost.
+fn select_cover(primes: &[Implicant], ones: &[u64]) -> Vec<Implicant> {
+    let ones: Vec<u64> = {
+        let mut ones = ones.to_vec();
+        ones.sort_unstable();
+        ones.dedup();
+        ones
+    };
+    let covering: Vec<Vec<usize>> = ones
+        .iter()
+        .map(|&m| (0..primes.len()).filter(|&p| primes[p].covers(m)).collect())
+        .collect();
+
+    // Essential primes are the only cover of some minterm
+    let mut chosen: Vec<usize> = covering.iter().filter(|c| c.len() == 1).map(|c| c[0]).collect();
+    chosen.sort_unstable();
+    chosen.dedup();
+    let remaining: Vec<usize> = (0..ones.len())
+        .filter(|&m| !chosen.iter().any(|&p| primes[p].covers(ones[m])))
+        .collect();
+
+    let mut search = CoverSearch {
+        primes,
+        covering: &covering,
+        best: None,
+        budget: SEARCH_LIMIT,
+    };
+    let greedy = search.greedy(&remaining);
+    search.best = Some((search.cost(&greedy), greedy));
+    search.branch(&remaining, &mut vec![]);
+
+    let (_, rest) = search.best.expect("greedy cover exists");
+    chosen.extend(rest);
+    chosen.into_iter().map(|p| primes[p]).collect()
+}
+
+/// Branch-and-bound search for the cheapest set of primes covering the
+/// minterms left after the essential primes are taken.
+struct CoverSearch<'a> {
+    primes: &'a [Implicant],
+    covering: &'a [Vec<usize>],
+    best: Option<((usize, usize), Vec<usize>)>,
+    budget: usize,
+}
+
+impl CoverSearch<'_> {
+    /// Number of terms and literals of a cover.
+    fn cost(&self, cover: &[usize]) -> (usize, usize) {
+        (cover.len(), cover.iter().map(|&p| self.primes[p].literal_count()).sum())
+    }
+
+    /// Minterms of `uncovered` that `prime` does not cover.
+    fn without(&self, uncovered: &[usize], prime: usize) -> Vec<usize> {
+        uncovered.iter().copied().filter(|m| !self.covering[*m].contains(&prime)).collect()
+    }
+
+    /// Repeatedly takes the prime covering the most uncovered minterms.
+    fn greedy(&self, uncovered: &[usize]) -> Vec<usize> {
+        let mut uncovered = uncovered.to_vec();
+        let mut cover = vec![];
+        while !uncovered.is_empty() {
+            let mut counts: HashMap<usize, usize> = HashMap::new();
+            for &m in &uncovered {
+                for &p in &self.covering[m] {
+                    *counts.entry(p).or_default() += 1;
+                }
+            }
+            // Prefer primes with fewer literals, then lower indices, among equal counts
+            let prime = counts
+                .into_iter()
+                .max_by_key(|&(p, count)| (count, Reverse(self.primes[p].literal_count()), Reverse(p)))
+                .map(|(p, _)| p)
+                .expect("every minterm has a prime");
+            uncovered = self.without(&uncovered, prime);
+            cover.push(prime);
+        }
+        cover
+    }
+
+    /// Covers the minterm with the fewest candidate primes by each of them in turn.
+    fn branch(&mut self, uncovered: &[usize], cover: &mut Vec<usize>) {
+        let best_cost = self.best.as_ref().map(|(cost, _)| *cost);
+        if best_cost.is_some_and(|best| self.cost(cover) >= best) || self.budget == 0 {
+            return;
+        }
+        self.budget -= 1;
+        let Some(&minterm) = uncovered.iter().min_by_key(|&&m| self.covering[m].len()) else {
+            let cost = self.cost(cover);
+            self.best = Some((cost, cover.clone()));
+            return;
+        };
+        for &prime in &self.covering[minterm] {
+            cover.push(prime);
+            let rest = self.without(uncovered, prime);
+            self.branch(&rest, cover);
+            cover.pop();
+        }
+    }
+}
@@ -0,0 +1,208 @@
+use crate::bus::Bus;
+use crate::logic::Logic;
+use std::ops::{BitAnd, BitOr, BitXor, Not};
+
+/// Number of input vectors simulated at once by a [`PackedLogic`] word.
+pub const LANES: usize = 64;
+
+/// 64 independent [`Logic`] signals, one per lane, packed into two words.
+///
+/// Bit `i` of `known` tells whether lane `i` is `Zero` or `One`, in which case
+/// bit `i` of `value` holds the level. Unknown lanes are `Z` if their `value`
+/// bit is set and `X` otherwise. The bitwise operators work on all lanes at
+/// once and follow the same rules as the operators on `Logic`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PackedLogic {
+    pub known: u64,
+    pub value: u64,
+}
+
+/// A multi-bit signal in packed form: one word per bit, least significant first.
+pub type PackedBus = Vec<PackedLogic>;
+
+impl PackedLogic {
+    /// Every lane `X`.
+    pub const X: PackedLogic = PackedLogic { known: 0, value: 0 };
+    /// Every lane `Z`.
+    pub const Z: PackedLogic = PackedLogic { known: 0, value: !0 };
+
+    /// Known lanes, lane `i` being `One` if bit `i` of `bits` is set.
+    pub fn from_bits(bits: u64) -> Self {
+        Self { known: !0, value: bits }
+    }
+
+    /// The same signal on every lane.
+    pub fn splat(value: Logic) -> Self {
+        match value {
+            Logic::Zero => Self::from_bits(0),
+            Logic::One => Self::from_bits(!0),
+            Logic::X => Self::X,
+            Logic::Z => Self::Z,
+        }
+    }
+
+    /// Packs up to [`LANES`] signals; missing lanes are `X`.
+    pub fn from_lanes(lanes: &[Logic]) -> Self {
+        let mut word = Self::X;
+        for (i, &value) in lanes.iter().take(LANES).enumerate() {
+            word.set_lane(i, value);
+        }
+        word
+    }
+
+    /// Lane `i` holds bit `bit` of the number `first + i`.
+    ///
+    /// Giving input `k` the word `counting(k, first)` makes the lanes cover
+    /// the consecutive input combinations `first..first + 64`, which is how
+    /// exhaustive simulations enumerate their vectors.
+    pub fn counting(bit: usize, first: u64) -> Self {
+        let bits = (0..LANES as u64).fold(0, |acc, i| acc | ((first.wrapping_add(i) >> bit) & 1) << i);
+        Self::from_bits(bits)
+    }
+
+    /// Returns the signal on lane `i`.
+    pub fn lane(self, i: usize) -> Logic {
+        let known = (self.known >> i) & 1 == 1;
+        let value = (self.value >> i) & 1 == 1;
+        match (known, value) {
+            (true, false) => Logic::Zero,
+            (true, true) => Logic::One,
+            (false, false) => Logic::X,
+            (false, true) => Logic::Z,
+        }
+    }
+
+    /// Sets the signal on lane `i`.
+    pub fn set_lane(&mut self, i: usize, value: Logic) {
+        let mask = 1u64 << i;
+        let (known, level) = match value {
+            Logic::Zero => (true, false),
+            Logic::One => (true, true),
+            Logic::X => (false, false),
+            Logic::Z => (false, true),
+        };
+        self.known = if known { self.known | mask } else { self.known & !mask };
+        self.value = if level { self.value | mask } else { self.value & !mask };
+    }
+
+    /// Lanes that are `One`.
+    pub fn ones(self) -> u64 {
+        self.known & self.value
+    }
+
+    /// Lanes that are `Zero`.
+    pub fn zeros(self) -> u64 {
+        self.known & !self.value
+    }
+
+    /// AND over any number of words, lane by lane.
+    pub fn all(words: &[PackedLogic]) -> PackedLogic {
+        words.iter().fold(Self::splat(Logic::One), |acc, &w| acc & w)
+    }
+
+    /// OR over any number of words, lane by lane.
+    pub fn any(words: &[PackedLogic]) -> PackedLogic {
+        words.iter().fold(Self::splat(Logic::Zero), |acc, &w| acc | w)
+    }
+
+    /// XOR over any number of words, lane by lane.
+    pub fn parity(words: &[PackedLogic]) -> PackedLogic {
+        words.iter().fold(Self::splat(Logic::Zero), |acc, &w| acc ^ w)
+    }
+
+    /// Lane-wise [`Logic::resolve`] of two words driving the same wire.
+    pub fn resolve(self, other: PackedLogic) -> PackedLogic {
+        let self_z = !self.known & self.value;
+        let other_z = !other.known & other.value;
+        let agree = self.known & other.known & !(self.value ^ other.value);
+        let known = (self.known & other_z) | (other.known & self_z) | agree;
+        let both_z = self_z & other_z;
+        PackedLogic {
+            known,
+            value: ((self.value & self.known) | (other.value & other.known)) & known | both_z,
+        }
+    }
+}
+
+impl Not for PackedLogic {
+    type Output = PackedLogic;
+
+    fn not(self) -> PackedLogic {
+        PackedLogic {
+            known: self.known,
+            value: !self.value & self.known,
+        }
+    }
+}
+
+impl BitAnd for PackedLogic {
+    type Output = PackedLogic;
+
+    /// A `Zero` on either side decides the lane even if the other side is unknown.
+    fn bitand(self, rhs: PackedLogic) -> PackedLogic {
+        let known = (self.known & rhs.known) | self.zeros() | rhs.zeros();
+        PackedLogic {
+            known,
+            value: self.ones() & rhs.ones(),
+        }
+    }
+}
+
+impl BitOr for PackedLogic {
+    type Output = PackedLogic;
+
+    /// A `One` on either side decides the lane even if the other side is unknown.
+    fn bitor(self, rhs: PackedLogic) -> PackedLogic {
+        let known = (self.known & rhs.known) | self.ones() | rhs.ones();
+        PackedLogic {
+            known,
+            value: (self.ones() | rhs.ones()) & known,
+        }
+    }
+}
+
+impl BitXor for PackedLogic {
+    type Output = PackedLogic;
+
+    fn bitxor(self, rhs: PackedLogic) -> PackedLogic {
+        let known = self.known & rhs.known;
+        PackedLogic {
+            known,
+            value: (self.value ^ rhs.value) & known,
+        }
+    }
+}
+
+/// Packs a bus holding the same value on every lane.
+pub fn splat_bus(bus: &Bus) -> PackedBus {
+    bus.bits().iter().map(|&bit| PackedLogic::splat(bit)).collect()
+}
+
+/// Extracts the value of lane `i` of a packed bus.
+pub fn lane_bus(bus: &[PackedLogic], i: usize) -> Bus {
+    bus.iter().map(|word| word.lane(i)).collect()
+}
+
+/// Evaluates a function of unpacked buses on every lane of `inputs` and packs
+/// its outputs, which must have the same number and widths on every lane.
+///
+/// This is how components without a bit-parallel implementation take part in
+/// packed simulation. `evaluate` is given the lane index and its inputs.
+pub fn map_lanes(inputs: &[PackedBus], evaluate: impl Fn(usize, &[Bus]) -> Vec<Bus>) -> Vec<PackedBus> {
+    let lanes: Vec<Vec<Bus>> = (0..LANES)
+        .map(|lane| {
+            let lane_inputs: Vec<Bus> = inputs.iter().map(|input| lane_bus(input, lane)).collect();
+            evaluate(lane, &lane_inputs)
+        })
+        .collect();
+    (0..lanes[0].len())
+        .map(|output| {
+            (0..lanes[0][output].width())
+                .map(|bit| {
+                    let bits: Vec<Logic> = lanes.iter().map(|outputs| outputs[output].bit(bit)).collect();
+                    PackedLogic::from_lanes(&bits)
+                })
+                .collect()
+        })
+        .collect()
+}
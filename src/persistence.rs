@@ -0,0 +1,172 @@
+use crate::circuit::Circuit;
+use crate::connection::GateId;
+use crate::error::CircuitError;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Version written into every saved document.
+///
+/// Bump this whenever the document layout changes and add a step to
+/// [`migrate`] that upgrades documents of the previous version.
+pub const FORMAT_VERSION: u32 = 2;
+
+/// Position of a gate on the editor canvas, stored alongside the circuit.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GatePosition {
+    pub gate: GateId,
+    pub x: f32,
+    pub y: f32,
+}
+
+/// Errors that can occur while saving or loading a circuit document.
+#[derive(Debug)]
+pub enum FileError {
+    /// The file could not be read or written.
+    Io(io::Error),
+    /// The file is not a valid circuit document.
+    Format(serde_json::Error),
+    /// The document has a version this simulator cannot read.
+    UnsupportedVersion(u32),
+    /// The document is well-formed but describes an inconsistent circuit.
+    Invalid(CircuitError),
+}
+
+impl fmt::Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileError::Io(err) => write!(f, "I/O error: {}", err),
+            FileError::Format(err) => write!(f, "invalid circuit document: {}", err),
+            FileError::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported document version {} (expected at most {})",
+                version, FORMAT_VERSION
+            ),
+            FileError::Invalid(err) => write!(f, "invalid circuit: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for FileError {}
+
+impl From<io::Error> for FileError {
+    fn from(err: io::Error) -> Self {
+        FileError::Io(err)
+    }
+}
+
+impl From<CircuitError> for FileError {
+    fn from(err: CircuitError) -> Self {
+        FileError::Invalid(err)
+    }
+}
+
+impl From<serde_json::Error> for FileError {
+    fn from(err: serde_json::Error) -> Self {
+        FileError::Format(err)
+    }
+}
+
+/// The versioned document written to disk.
+#[derive(Serialize)]
+struct DocumentRef<'a> {
+    version: u32,
+    circuit: &'a Circuit,
+    layout: &'a [GatePosition],
+}
+
+#[derive(Deserialize)]
+struct Document {
+    circuit: Circuit,
+    #[serde(default)]
+    layout: Vec<GatePosition>,
+}
+
+/// Serializes a circuit and the positions of its gates into a JSON document.
+pub fn write_document(circuit: &Circuit, layout: &[GatePosition]) -> Result<String, FileError> {
+    let document = DocumentRef {
+        version: FORMAT_VERSION,
+        circuit,
+        layout,
+    };
+    Ok(serde_json::to_string_pretty(&document)?)
+}
+
+/// Parses a JSON document, upgrading it to the current format version first.
+///
+/// The circuit is validated before it is returned, so gates and connections
+/// edited by hand cannot make the simulation panic later.
+pub fn read_document(json: &str) -> Result<(Circuit, Vec<GatePosition>), FileError> {
+    let mut value: Value = serde_json::from_str(json)?;
+    migrate(&mut value)?;
+    let document: Document = serde_json::from_value(value)?;
+    document.circuit.validate()?;
+    Ok((document.circuit, document.layout))
+}
+
+/// Upgrades from older format versions, oldest first.
+///
+/// `MIGRATIONS[i]` rewrites a document of version `i + 1` into version `i + 2`,
+/// so each format change appends one step here and bumps [`FORMAT_VERSION`].
+const MIGRATIONS: &[fn(&mut Value)] = &[|document| {
+    if let Some(circuit) = document.get_mut("circuit") {
+        gates_by_id(circuit);
+    }
+}];
+
+/// Version 2: gates are stored in an object keyed by their stable id instead
+/// of an array indexed by position, and the next free id is recorded.
+fn gates_by_id(circuit: &mut Value) {
+    let Some(circuit) = circuit.as_object_mut() else {
+        return;
+    };
+    if let Some(Value::Array(gates)) = circuit.remove("gates") {
+        circuit.insert("next_gate_id".into(), gates.len().into());
+        let by_id = gates.into_iter().enumerate().map(|(id, gate)| (id.to_string(), gate)).collect();
+        circuit.insert("gates".into(), Value::Object(by_id));
+    }
+    if let Some(Value::Array(subcircuits)) = circuit.get_mut("subcircuits") {
+        for definition in subcircuits {
+            if let Some(inner) = definition.get_mut("circuit") {
+                gates_by_id(inner);
+            }
+        }
+    }
+    if let Some(Value::Object(instances)) = circuit.get_mut("instances") {
+        for instance in instances.values_mut() {
+            gates_by_id(instance);
+        }
+    }
+}
+
+/// Upgrades a parsed document in place to [`FORMAT_VERSION`].
+pub fn migrate(document: &mut Value) -> Result<(), FileError> {
+    let version = document
+        .get("version")
+        .and_then(Value::as_u64)
+        .ok_or_else(|| FileError::Format(serde::de::Error::missing_field("version")))?;
+    let version = u32::try_from(version).unwrap_or(u32::MAX);
+    if version == 0 || version > FORMAT_VERSION {
+        return Err(FileError::UnsupportedVersion(version));
+    }
+
+    for step in &MIGRATIONS[version as usize - 1..] {
+        step(document);
+    }
+    document["version"] = FORMAT_VERSION.into();
+    Ok(())
+}
+
+/// Writes a document to `path`.
+pub fn save(path: impl AsRef<Path>, circuit: &Circuit, layout: &[GatePosition]) -> Result<(), FileError> {
+    fs::write(path, write_document(circuit, layout)?)?;
+    Ok(())
+}
+
+/// Reads a document from `path`.
+pub fn load(path: impl AsRef<Path>) -> Result<(Circuit, Vec<GatePosition>), FileError> {
+    read_document(&fs::read_to_string(path)?)
+}
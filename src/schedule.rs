@@ -0,0 +1,193 @@
+use crate::circuit::Circuit;
+use crate::connection::{Connection, GateId};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// A feedback loop that prevents the gates of a circuit from being sorted.
+///
+/// `gates` lists the gates of one loop in the direction signals flow: each gate
+/// drives the next, and the last drives the first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cycle {
+    pub gates: Vec<GateId>,
+}
+
+impl fmt::Display for Cycle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let gates: Vec<String> = self.gates.iter().map(|g| g.to_string()).collect();
+        write!(f, "feedback loop through gates {}", gates.join(" -> "))
+    }
+}
+
+impl std::error::Error for Cycle {}
+
+/// Evaluation order and adjacency lists compiled from the netlist of a circuit.
+///
+/// The circuit builds its schedule on first use and discards it whenever
+/// gates or connections are added or removed, so evaluating an unchanged
+/// netlist never scans the connection list.
+///
+/// Acyclic circuits are evaluated level by level: level 0 holds the gates
+/// without connected inputs and every other gate sits one level above its
+/// deepest driver. Circuits with feedback are evaluated in depth-first order
+/// instead, where the gates closing a loop read the previous output of the
+/// gate they feed back to.
+#[derive(Debug, Clone)]
+pub struct Schedule {
+    fanin: HashMap<GateId, Vec<Connection>>,
+    fanout: HashMap<GateId, Vec<GateId>>,
+    order: Vec<GateId>,
+    rank: HashMap<GateId, usize>,
+    levels: Result<Vec<Vec<GateId>>, Cycle>,
+}
+
+impl Schedule {
+    /// Compiles the schedule of a circuit.
+    pub fn build(circuit: &Circuit) -> Self {
+        let gate_ids: Vec<GateId> = circuit.gate_ids().collect();
+        let mut fanin: HashMap<GateId, Vec<Connection>> = HashMap::new();
+        let mut fanout: HashMap<GateId, Vec<GateId>> = HashMap::new();
+        for conn in circuit.port_connections() {
+            fanin.entry(conn.to).or_default().push(conn.clone());
+            fanout.entry(conn.from).or_default().push(conn.to);
+        }
+        for targets in fanout.values_mut() {
+            targets.sort_unstable();
+            targets.dedup();
+        }
+
+        let mut schedule = Self {
+            fanin,
+            fanout,
+            order: vec![],
+            rank: HashMap::new(),
+            levels: Ok(vec![]),
+        };
+        schedule.levels = schedule.levelize(&gate_ids);
+        schedule.order = match &schedule.levels {
+            Ok(levels) => levels.concat(),
+            Err(_) => schedule.depth_first_order(&gate_ids),
+        };
+        schedule.rank = schedule.order.iter().enumerate().map(|(i, &id)| (id, i)).collect();
+        schedule
+    }
+
+    /// Returns the order in which gates are evaluated.
+    pub fn order(&self) -> &[GateId] {
+        &self.order
+    }
+
+    /// Returns the position of a gate in the evaluation order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the gate is not part of the circuit the schedule was built for.
+    pub fn rank(&self, gate_id: GateId) -> usize {
+        self.rank[&gate_id]
+    }
+
+    /// Returns the gates grouped by level, or a feedback loop if the circuit has one.
+    pub fn levels(&self) -> Result<&[Vec<GateId>], &Cycle> {
+        self.levels.as_deref()
+    }
+
+    /// Returns the connections driving the inputs of a gate.
+    pub fn fanin(&self, gate_id: GateId) -> &[Connection] {
+        self.fanin.get(&gate_id).map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns the gates reading an output of `gate_id`, in increasing order.
+    pub fn fanout(&self, gate_id: GateId) -> &[GateId] {
+        self.fanout.get(&gate_id).map_or(&[], Vec::as_slice)
+    }
+
+    /// Sorts the gates into levels with Kahn's algorithm.
+    fn levelize(&self, gate_ids: &[GateId]) -> Result<Vec<Vec<GateId>>, Cycle> {
+        let mut pending: HashMap<GateId, usize> = gate_ids.iter().map(|&id| (id, self.fanin(id).len())).collect();
+        let mut level: Vec<GateId> = gate_ids.iter().copied().filter(|id| pending[id] == 0).collect();
+        let mut levels = vec![];
+        let mut sorted = 0;
+
+        while !level.is_empty() {
+            let mut next = vec![];
+            for &gate_id in &level {
+                for conn in self.fanout_connections(gate_id) {
+                    let count = pending.get_mut(&conn.to).expect("connected gate exists");
+                    *count -= 1;
+                    if *count == 0 {
+                        next.push(conn.to);
+                    }
+                }
+            }
+            next.sort_unstable();
+            sorted += level.len();
+            levels.push(level);
+            level = next;
+        }
+
+        if sorted == gate_ids.len() {
+            Ok(levels)
+        } else {
+            let blocked: HashSet<GateId> = pending.into_iter().filter(|&(_, n)| n > 0).map(|(id, _)| id).collect();
+            Err(self.find_cycle(&blocked))
+        }
+    }
+
+    /// Connections leaving `gate_id`, one per driven input.
+    fn fanout_connections(&self, gate_id: GateId) -> impl Iterator<Item = &Connection> {
+        self.fanout(gate_id)
+            .iter()
+            .flat_map(move |&to| self.fanin(to).iter().filter(move |c| c.from == gate_id))
+    }
+
+    /// Walks backwards from a gate that could not be sorted until a gate repeats.
+    ///
+    /// Every unsorted gate has an unsorted driver, so the walk always ends in a loop.
+    fn find_cycle(&self, blocked: &HashSet<GateId>) -> Cycle {
+        let mut current = *blocked.iter().min().expect("a cycle leaves gates unsorted");
+        let mut path = vec![];
+        let mut seen = HashMap::new();
+        while !seen.contains_key(&current) {
+            seen.insert(current, path.len());
+            path.push(current);
+            current = self
+                .fanin(current)
+                .iter()
+                .map(|c| c.from)
+                .filter(|from| blocked.contains(from))
+                .min()
+                .expect("an unsorted gate has an unsorted driver");
+        }
+        let mut gates = path.split_off(seen[&current]);
+        gates.reverse();
+        // Start the loop at its lowest gate id so the report is stable
+        let first = gates.iter().enumerate().min_by_key(|&(_, id)| id).map_or(0, |(i, _)| i);
+        gates.rotate_left(first);
+        Cycle { gates }
+    }
+
+    /// Orders gates so that each comes after its drivers, except where a
+    /// driver is part of a loop that is still being visited.
+    fn depth_first_order(&self, gate_ids: &[GateId]) -> Vec<GateId> {
+        let mut order = Vec::with_capacity(gate_ids.len());
+        let mut visited = HashSet::new();
+        for &root in gate_ids {
+            if !visited.insert(root) {
+                continue;
+            }
+            let mut stack = vec![(root, 0)];
+            while let Some((gate_id, next_input)) = stack.pop() {
+                match self.fanin(gate_id).get(next_input) {
+                    Some(conn) => {
+                        stack.push((gate_id, next_input + 1));
+                        if visited.insert(conn.from) {
+                            stack.push((conn.from, 0));
+                        }
+                    }
+                    None => order.push(gate_id),
+                }
+            }
+        }
+        order
+    }
+}
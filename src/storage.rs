@@ -0,0 +1,145 @@
+use crate::bus::Bus;
+use crate::logic::Logic;
+use serde::{Deserialize, Serialize};
+
+/// Output port of a storage element holding the stored value.
+pub const Q: usize = 0;
+/// Output port of a storage element holding the complement of the stored value.
+pub const Q_BAR: usize = 1;
+/// Names of the outputs of every storage element, in port order.
+pub const OUTPUT_NAMES: [&str; 2] = ["Q", "Q̅"];
+
+/// Names of the inputs every storage element has after its data inputs.
+const CONTROL_INPUTS: [&str; 4] = ["CLK", "EN", "PRE", "CLR"];
+
+/// How the data inputs of a flip-flop or latch determine its next state.
+///
+/// Every storage element has the data inputs of its kind followed by a clock
+/// `CLK`, an enable `EN` and the asynchronous preset `PRE` and clear `CLR`.
+/// Data inputs and both outputs are as wide as the gate; the control inputs
+/// are single bits. An unconnected `EN` enables the element and unconnected
+/// `PRE` and `CLR` inputs are inactive.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum StorageKind {
+    /// Stores `D`.
+    #[default]
+    D,
+    /// Sets on `J`, resets on `K` and toggles on both.
+    JK,
+    /// Toggles on `T`.
+    T,
+    /// Sets on `S` and resets on `R`; both at once give `X`.
+    SR,
+}
+
+impl StorageKind {
+    /// Every kind, in declaration order.
+    pub const ALL: [StorageKind; 4] = [StorageKind::D, StorageKind::JK, StorageKind::T, StorageKind::SR];
+
+    /// Names of the data inputs.
+    pub fn data_inputs(self) -> &'static [&'static str] {
+        match self {
+            StorageKind::D => &["D"],
+            StorageKind::JK => &["J", "K"],
+            StorageKind::T => &["T"],
+            StorageKind::SR => &["S", "R"],
+        }
+    }
+
+    /// Names of all inputs, in port order.
+    pub fn input_names(self) -> Vec<&'static str> {
+        self.data_inputs().iter().chain(&CONTROL_INPUTS).copied().collect()
+    }
+
+    /// Number of inputs of a storage element of this kind.
+    pub fn input_count(self) -> usize {
+        self.data_inputs().len() + CONTROL_INPUTS.len()
+    }
+
+    /// Index of the clock input.
+    pub fn clock_index(self) -> usize {
+        self.data_inputs().len()
+    }
+
+    /// Index of the enable input.
+    pub fn enable_index(self) -> usize {
+        self.clock_index() + 1
+    }
+
+    /// Index of the asynchronous preset input.
+    pub fn preset_index(self) -> usize {
+        self.clock_index() + 2
+    }
+
+    /// Index of the asynchronous clear input.
+    pub fn clear_index(self) -> usize {
+        self.clock_index() + 3
+    }
+
+    /// The state after a triggering clock, given one bit of each data input
+    /// and of the current state.
+    fn next(self, data: &[Logic], q: Logic) -> Logic {
+        match self {
+            StorageKind::D => data[0],
+            StorageKind::T => q ^ data[0],
+            StorageKind::JK => match (data[0], data[1]) {
+                (Logic::One, Logic::Zero) => Logic::One,
+                (Logic::Zero, Logic::One) => Logic::Zero,
+                (j, k) => (j & !q) | (!k & q),
+            },
+            StorageKind::SR if data[0] == Logic::One && data[1] == Logic::One => Logic::X,
+            StorageKind::SR => data[0] | (!data[1] & q),
+        }
+    }
+}
+
+/// Computes one bit of the next state of a storage element.
+///
+/// `inputs` holds that bit of every data input followed by the control inputs.
+/// A flip-flop (`edge_triggered`) takes its next state when the clock rises
+/// from `previous_clock`; a latch follows it for as long as the clock is high.
+/// Either only while enabled. When it is unknown whether the element triggers,
+/// bits that would change become `X`.
+pub fn next_state(kind: StorageKind, edge_triggered: bool, inputs: &[Logic], previous_clock: Logic, q: Logic) -> Logic {
+    let (data, control) = inputs.split_at(kind.data_inputs().len());
+    let [clock, enable, preset, clear] = [control[0], control[1], control[2], control[3]];
+    let enable = if enable == Logic::Z { Logic::One } else { enable };
+    let inactive = |value: Logic| if value == Logic::Z { Logic::Zero } else { value };
+
+    match (inactive(preset), inactive(clear)) {
+        (Logic::Zero, Logic::Zero) => {}
+        (Logic::One, Logic::Zero) => return Logic::One,
+        (Logic::Zero, Logic::One) => return Logic::Zero,
+        _ => return Logic::X,
+    }
+
+    let trigger = if edge_triggered { !previous_clock & clock & enable } else { clock & enable };
+    let next = kind.next(data, q);
+    match trigger {
+        Logic::One => next,
+        Logic::Zero => q,
+        _ if next == q => q,
+        _ => Logic::X,
+    }
+}
+
+/// Computes the `Q` and `Q̅` outputs of a storage element from its inputs and
+/// its current `Q`.
+pub fn next_outputs(
+    kind: StorageKind,
+    edge_triggered: bool,
+    inputs: &[Bus],
+    previous_clock: Logic,
+    q: &Bus,
+) -> Vec<Bus> {
+    let control: Vec<Logic> = inputs[kind.clock_index()..].iter().map(|input| input.bit(0)).collect();
+    let state: Bus = (0..q.width())
+        .map(|bit| {
+            let mut lane: Vec<Logic> = inputs[..kind.clock_index()].iter().map(|input| input.bit(bit)).collect();
+            lane.extend(&control);
+            next_state(kind, edge_triggered, &lane, previous_clock, q.bit(bit))
+        })
+        .collect();
+    let complement = state.bits().iter().map(|&bit| !bit).collect();
+    vec![state, complement]
+}
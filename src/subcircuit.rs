@@ -0,0 +1,129 @@
+use crate::circuit::Circuit;
+use crate::connection::GateId;
+use crate::error::CircuitError;
+use crate::gate::GateType;
+use crate::library::Component;
+use serde::{Deserialize, Serialize};
+
+/// Alias for identifying a subcircuit definition registered with a circuit.
+pub type SubcircuitId = usize;
+
+/// A named port of a subcircuit, backed by a gate of the inner circuit.
+///
+/// Input ports are `GateType::Input` gates whose value is driven from outside;
+/// output ports export the first output of any gate.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Port {
+    pub name: String,
+    pub gate: GateId,
+}
+
+/// A reusable component defined by wrapping a `Circuit`.
+///
+/// Once registered with `Circuit::define_subcircuit`, it can be instantiated any
+/// number of times. Each instance has one input per input port and one output
+/// per output port, in the order the ports were added.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Subcircuit {
+    name: String,
+    circuit: Circuit,
+    inputs: Vec<Port>,
+    outputs: Vec<Port>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    component: Option<Component>,
+}
+
+impl Subcircuit {
+    /// Creates a subcircuit definition without any ports.
+    pub fn new(name: impl Into<String>, circuit: Circuit) -> Self {
+        Self {
+            name: name.into(),
+            circuit,
+            inputs: vec![],
+            outputs: vec![],
+            component: None,
+        }
+    }
+
+    /// Exposes an input gate of the inner circuit as a named input port.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `gate` does not exist or is not an input gate.
+    pub fn add_input(&mut self, name: impl Into<String>, gate: GateId) {
+        self.try_add_input(name, gate).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like [`Subcircuit::add_input`], but reports an unknown gate or one that
+    /// is not an input gate instead of panicking.
+    pub fn try_add_input(&mut self, name: impl Into<String>, gate: GateId) -> Result<(), CircuitError> {
+        if self.circuit.try_gate(gate)?.gate_type != GateType::Input {
+            return Err(CircuitError::NotAnInput(gate));
+        }
+        self.inputs.push(Port { name: name.into(), gate });
+        Ok(())
+    }
+
+    /// Exposes the output of a gate of the inner circuit as a named output port.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `gate` does not exist.
+    pub fn add_output(&mut self, name: impl Into<String>, gate: GateId) {
+        self.try_add_output(name, gate).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like [`Subcircuit::add_output`], but reports an unknown gate instead of panicking.
+    pub fn try_add_output(&mut self, name: impl Into<String>, gate: GateId) -> Result<(), CircuitError> {
+        self.circuit.try_gate(gate)?;
+        self.outputs.push(Port { name: name.into(), gate });
+        Ok(())
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the library component this definition was built from, if any.
+    pub fn component(&self) -> Option<Component> {
+        self.component
+    }
+
+    /// Marks the definition as the one of a library component.
+    pub(crate) fn set_component(&mut self, component: Component) {
+        self.component = Some(component);
+    }
+
+    /// Returns the circuit wrapped by this definition.
+    pub fn circuit(&self) -> &Circuit {
+        &self.circuit
+    }
+
+    pub fn inputs(&self) -> &[Port] {
+        &self.inputs
+    }
+
+    pub fn outputs(&self) -> &[Port] {
+        &self.outputs
+    }
+
+    /// Returns the input index of an instance that corresponds to the named port.
+    pub fn input_index(&self, name: &str) -> Option<usize> {
+        self.inputs.iter().position(|p| p.name == name)
+    }
+
+    /// Returns the output index of an instance that corresponds to the named port.
+    pub fn output_index(&self, name: &str) -> Option<usize> {
+        self.outputs.iter().position(|p| p.name == name)
+    }
+
+    /// Returns the bus width of each input port.
+    pub fn input_widths(&self) -> Vec<usize> {
+        self.inputs.iter().map(|p| self.circuit.gate(p.gate).width).collect()
+    }
+
+    /// Returns the bus width of each output port.
+    pub fn output_widths(&self) -> Vec<usize> {
+        self.outputs.iter().map(|p| self.circuit.gate(p.gate).output().width()).collect()
+    }
+}
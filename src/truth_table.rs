@@ -0,0 +1,197 @@
+use crate::bus::Bus;
+use crate::circuit::{Circuit, Oscillation, DEFAULT_MAX_PASSES};
+use crate::connection::GateId;
+use crate::error::CircuitError;
+use crate::gate::GateType;
+use crate::packed::{self, PackedBus, PackedLogic, LANES};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Largest number of input bits a truth table is generated for (about a million rows).
+pub const MAX_INPUT_BITS: usize = 20;
+
+/// Errors that can occur while generating a truth table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TruthTableError {
+    /// The inputs have more bits than [`MAX_INPUT_BITS`].
+    TooManyInputs { bits: usize },
+    /// An output gate does not exist.
+    Circuit(CircuitError),
+    /// The circuit did not settle for some input combination.
+    Oscillation(Oscillation),
+}
+
+impl fmt::Display for TruthTableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TruthTableError::TooManyInputs { bits } => write!(
+                f,
+                "{} input bits give too many rows (at most {} bits are supported)",
+                bits, MAX_INPUT_BITS
+            ),
+            TruthTableError::Circuit(err) => err.fmt(f),
+            TruthTableError::Oscillation(oscillation) => oscillation.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for TruthTableError {}
+
+impl From<CircuitError> for TruthTableError {
+    fn from(err: CircuitError) -> Self {
+        TruthTableError::Circuit(err)
+    }
+}
+
+impl From<Oscillation> for TruthTableError {
+    fn from(oscillation: Oscillation) -> Self {
+        TruthTableError::Oscillation(oscillation)
+    }
+}
+
+/// One line of a truth table: a combination of input values and the outputs it produces.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Row {
+    pub inputs: Vec<Bus>,
+    pub outputs: Vec<Bus>,
+}
+
+/// The outputs of a circuit for every combination of its inputs.
+///
+/// Rows are ordered by counting up in binary, with the first input as the most
+/// significant part. Columns are labelled with the names of their gates, or
+/// `in<id>` and `out<id>` for unnamed gates, and can be relabelled before
+/// exporting.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TruthTable {
+    pub inputs: Vec<GateId>,
+    pub outputs: Vec<GateId>,
+    pub input_labels: Vec<String>,
+    pub output_labels: Vec<String>,
+    pub rows: Vec<Row>,
+}
+
+impl TruthTable {
+    /// Evaluates `outputs` for every combination of the values of `inputs`.
+    ///
+    /// Inputs not listed keep their current value. The circuit is simulated 64
+    /// rows at a time and is left unchanged.
+    pub fn generate(circuit: &mut Circuit, inputs: &[GateId], outputs: &[GateId]) -> Result<Self, TruthTableError> {
+        let mut widths = vec![];
+        for &gate_id in inputs {
+            let gate = circuit.try_gate(gate_id)?;
+            if gate.gate_type != GateType::Input {
+                return Err(CircuitError::NotAnInput(gate_id).into());
+            }
+            widths.push(gate.width);
+        }
+        for &gate_id in outputs {
+            circuit.try_get_output(gate_id)?;
+        }
+        let bits: usize = widths.iter().sum();
+        if bits > MAX_INPUT_BITS {
+            return Err(TruthTableError::TooManyInputs { bits });
+        }
+
+        // Bit `k` of input `j` is bit `shifts[j] + k` of the row number
+        let shifts: Vec<usize> = (0..widths.len()).map(|j| widths[j + 1..].iter().sum()).collect();
+        let row_count = 1u64 << bits;
+        let mut rows = Vec::with_capacity(row_count as usize);
+        for first in (0..row_count).step_by(LANES) {
+            let packed_inputs: HashMap<GateId, PackedBus> = inputs
+                .iter()
+                .zip(&widths)
+                .zip(&shifts)
+                .map(|((&gate_id, &width), &shift)| {
+                    let words = (0..width).map(|k| PackedLogic::counting(shift + k, first)).collect();
+                    (gate_id, words)
+                })
+                .collect();
+            let values = circuit.simulate_packed(&packed_inputs, DEFAULT_MAX_PASSES)?;
+
+            let lanes = (row_count - first).min(LANES as u64) as usize;
+            for lane in 0..lanes {
+                rows.push(Row {
+                    inputs: inputs.iter().map(|id| packed::lane_bus(&packed_inputs[id], lane)).collect(),
+                    outputs: outputs.iter().map(|id| packed::lane_bus(&values[id][0], lane)).collect(),
+                });
+            }
+        }
+
+        let label = |gate_id: GateId, prefix: &str| match &circuit.gate(gate_id).name {
+            Some(name) => name.clone(),
+            None => format!("{}{}", prefix, gate_id),
+        };
+        Ok(Self {
+            inputs: inputs.to_vec(),
+            outputs: outputs.to_vec(),
+            input_labels: inputs.iter().map(|&id| label(id, "in")).collect(),
+            output_labels: outputs.iter().map(|&id| label(id, "out")).collect(),
+            rows,
+        })
+    }
+
+    /// Returns the column labels, inputs first.
+    pub fn header(&self) -> Vec<&str> {
+        self.input_labels.iter().chain(&self.output_labels).map(String::as_str).collect()
+    }
+
+    /// Returns the cells of every row as text, inputs first. Buses are written
+    /// as bit strings, most significant bit first.
+    pub fn cells(&self) -> Vec<Vec<String>> {
+        self.rows
+            .iter()
+            .map(|row| row.inputs.iter().chain(&row.outputs).map(Bus::to_bit_string).collect())
+            .collect()
+    }
+
+    /// Formats the table as comma-separated values with a header line.
+    pub fn to_csv(&self) -> String {
+        let mut csv = self.header().join(",") + "\n";
+        for row in self.cells() {
+            csv += &(row.join(",") + "\n");
+        }
+        csv
+    }
+
+    /// Formats the table as a Markdown table.
+    pub fn to_markdown(&self) -> String {
+        let header = self.header();
+        let mut markdown = format!("| {} |\n", header.join(" | "));
+        markdown += &format!("|{}\n", "---|".repeat(header.len()));
+        for row in self.cells() {
+            markdown += &format!("| {} |\n", row.join(" | "));
+        }
+        markdown
+    }
+
+    /// Formats the table as aligned plain text, with inputs and outputs
+    /// separated by a vertical bar.
+    pub fn to_text(&self) -> String {
+        let header = self.header();
+        let cells = self.cells();
+        let widths: Vec<usize> = (0..header.len())
+            .map(|c| cells.iter().map(|row| row[c].len()).chain([header[c].len()]).max().unwrap_or(0))
+            .collect();
+        let split = self.inputs.len();
+        let line = |row: Vec<&str>| {
+            let pad = |range: std::ops::Range<usize>| {
+                range.map(|c| format!("{:<w$}", row[c], w = widths[c])).collect::<Vec<_>>().join(" ")
+            };
+            format!("{} | {}", pad(0..split), pad(split..row.len())).trim().to_string() + "\n"
+        };
+
+        let mut text = line(header.clone());
+        text += &format!("{}\n", "-".repeat(text.len() - 1));
+        for row in &cells {
+            text += &line(row.iter().map(String::as_str).collect());
+        }
+        text
+    }
+}
+
+impl fmt::Display for TruthTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_text())
+    }
+}
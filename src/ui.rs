@@ -1,8 +1,20 @@
-use eframe::egui::{self, CentralPanel, SidePanel, Pos2, Rect, Sense, Color32, Stroke};
+use eframe::egui::{self, CentralPanel, SidePanel, TopBottomPanel, Pos2, Rect, Sense, Color32, Stroke};
 use egui::vec2;
 use strum::IntoEnumIterator;
-use crate::circuit::Circuit;
-use crate::gate::GateType;
+use std::collections::HashMap;
+use crate::bdd::{Manager, VariableOrder};
+use crate::bus::Bus;
+use crate::circuit::{Circuit, Contention, Oscillation, DEFAULT_MAX_PASSES};
+use crate::connection::Connection;
+use crate::error::CircuitError;
+use crate::expression::{Expr, ParseError};
+use crate::gate::{ClockTiming, GateType};
+use crate::library::{Component, MAX_SELECT_WIDTH, MAX_WIDTH};
+use crate::logic::Logic;
+use crate::memory::{ImageFormat, MemoryKind, MemorySpec, MAX_ADDRESS_WIDTH};
+use crate::persistence::{self, GatePosition};
+use crate::storage::StorageKind;
+use crate::truth_table::{TruthTable, TruthTableError};
 
 pub type GateId = usize;
 
@@ -11,14 +23,194 @@ pub struct GateWidget {
     pub id: usize,
     pub gate_type: GateType,
     pub position: Pos2,        // Top-left corner of gate rectangle
-    pub input_state: Option<bool>,
+    pub input_state: Option<Bus>,
+}
+
+/// Something on the canvas that the Delete key removes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Selection {
+    Gate(GateId),
+    Wire(Connection),
+}
+
+/// Distance from `point` to the line segment between `a` and `b`.
+fn distance_to_segment(point: Pos2, a: Pos2, b: Pos2) -> f32 {
+    let ab = b - a;
+    let t = if ab.length_sq() > 0.0 {
+        ((point - a).dot(ab) / ab.length_sq()).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    point.distance(a + ab * t)
+}
+
+/// Position of pin `index` of `count` pins spread evenly down the side of a
+/// gate that starts at `top`.
+fn pin_position(top: Pos2, index: usize, count: usize, height: f32) -> Pos2 {
+    top + vec2(0.0, height / (count as f32 + 1.0) * (index as f32 + 1.0))
+}
+
+/// Colour used to draw pins and wires carrying the given signal.
+fn signal_color(value: Logic) -> Color32 {
+    match value {
+        Logic::One => Color32::GREEN,
+        Logic::Zero => Color32::RED,
+        Logic::X => Color32::GOLD,
+        Logic::Z => Color32::GRAY,
+    }
+}
+
+/// Colour used to draw pins and wires carrying the given bus.
+///
+/// Single-bit wires use the colour of their signal; wider buses are blue once
+/// every bit is known.
+fn bus_color(value: &Bus) -> Color32 {
+    if value.width() == 1 {
+        signal_color(value.bit(0))
+    } else if value.is_known() {
+        Color32::LIGHT_BLUE
+    } else if value.bits().iter().all(|&b| b == Logic::Z) {
+        Color32::GRAY
+    } else {
+        Color32::GOLD
+    }
+}
+
+/// Name of a gate type as shown in the palette, where the kind of storage
+/// element is chosen separately.
+fn palette_label(gate_type: GateType) -> String {
+    match gate_type {
+        GateType::FlipFlop(_) => "Flip-flop".to_string(),
+        GateType::Latch(_) => "Latch".to_string(),
+        GateType::Memory(_) => "Memory".to_string(),
+        _ => gate_type_label(gate_type),
+    }
+}
+
+/// Name of a gate type as shown on gates.
+fn gate_type_label(gate_type: GateType) -> String {
+    match gate_type {
+        GateType::Clock(timing) => format!("Clock {}/{}", timing.high, timing.period),
+        GateType::FlipFlop(kind) => format!("{:?} flip-flop", kind),
+        GateType::Latch(kind) => format!("{:?} latch", kind),
+        GateType::Memory(spec) => format!("{} {}", memory_kind_label(spec.kind), spec.word_count()),
+        _ => format!("{:?}", gate_type),
+    }
+}
+
+fn memory_kind_label(kind: MemoryKind) -> &'static str {
+    match kind {
+        MemoryKind::Rom => "ROM",
+        MemoryKind::Ram => "RAM",
+    }
+}
+
+/// Edits the parameters of a library component.
+fn component_editor(ui: &mut egui::Ui, component: &mut Component) {
+    let (select_width, width) = match component {
+        Component::Multiplexer { select_width, width } | Component::Demultiplexer { select_width, width } => {
+            (Some(select_width), Some(width))
+        }
+        Component::Decoder { select_width } | Component::PriorityEncoder { select_width } => (Some(select_width), None),
+        Component::RippleCarryAdder { width }
+        | Component::CarryLookaheadAdder { width }
+        | Component::Comparator { width } => (None, Some(width)),
+    };
+    if let Some(select_width) = select_width {
+        ui.horizontal(|ui| {
+            ui.label("Select bits");
+            ui.add(egui::DragValue::new(select_width).clamp_range(1..=MAX_SELECT_WIDTH));
+        });
+    }
+    if let Some(width) = width {
+        ui.horizontal(|ui| {
+            ui.label("Data bits");
+            ui.add(egui::DragValue::new(width).clamp_range(1..=MAX_WIDTH));
+        });
+    }
+}
+
+/// Value an input gate takes when clicked: single bits toggle, buses count up.
+fn next_input_value(value: &Bus) -> Bus {
+    if value.width() == 1 {
+        Bus::from(value.bit(0) != Logic::One)
+    } else {
+        let next = value.to_u64().map_or(0, |v| v.wrapping_add(1));
+        Bus::from_u64(next, value.width())
+    }
+}
+
+/// Edits the period and high time of a clock, returning `true` if either changed.
+fn clock_timing_editor(ui: &mut egui::Ui, timing: &mut ClockTiming) -> bool {
+    let mut changed = false;
+    ui.horizontal(|ui| {
+        ui.label("Period");
+        changed |= ui.add(egui::DragValue::new(&mut timing.period).clamp_range(2..=1000)).changed();
+        ui.label("High");
+        changed |= ui
+            .add(egui::DragValue::new(&mut timing.high).clamp_range(1..=timing.period - 1))
+            .changed();
+    });
+    timing.high = timing.high.min(timing.period - 1);
+    changed
 }
 
 pub struct CircuitEditor {
     pub circuit: Circuit,
     pub gate_widgets: Vec<GateWidget>,
     pub selected_gate: Option<GateType>,
-    pub connect_from: Option<GateId>,
+    /// The library component placed by clicking the canvas, instead of a gate.
+    pub selected_component: Option<Component>,
+    /// The gate and output port a new wire starts from.
+    pub connect_from: Option<(GateId, usize)>,
+    pub oscillation: Option<Oscillation>,
+    pub contention: Vec<Contention>,
+    pub connect_error: Option<CircuitError>,
+    pub selection: Option<Selection>,
+    pub new_gate_width: usize,
+    pub new_gate_offset: usize,
+    pub file_path: String,
+    pub file_status: Option<String>,
+    pub truth_table: Option<Result<TruthTable, TruthTableError>>,
+    truth_table_lines: Vec<String>,
+    analysis_revision: Option<u64>,
+    pub constant_outputs: Vec<(GateId, Bus)>,
+    pub expression_text: String,
+    pub expression_error: Option<ParseError>,
+    pub new_clock_timing: ClockTiming,
+    pub new_storage_kind: StorageKind,
+    pub running: bool,
+    pub ticks_per_second: f32,
+    tick_progress: f32,
+    pub new_memory_spec: MemorySpec,
+    /// The memory shown in the memory inspector window, if it is open.
+    pub inspected_memory: Option<GateId>,
+    pub poke_address: usize,
+    pub poke_value: String,
+    pub image_path: String,
+    pub image_format: ImageFormat,
+    pub memory_status: Option<String>,
+}
+
+impl eframe::App for CircuitEditor {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        if self.running {
+            self.tick_progress += ctx.input(|i| i.stable_dt) * self.ticks_per_second;
+            let ticks = self.tick_progress.floor();
+            self.tick_progress -= ticks;
+            if ticks >= 1.0 {
+                self.tick(ticks as u64);
+            }
+            ctx.request_repaint();
+        }
+        self.draw(ctx);
+    }
+}
+
+impl Default for CircuitEditor {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl CircuitEditor {
@@ -27,46 +219,522 @@ impl CircuitEditor {
             circuit: Circuit::new(),
             gate_widgets: vec![],
             selected_gate: None,
+            selected_component: None,
             connect_from: None,
+            oscillation: None,
+            contention: vec![],
+            connect_error: None,
+            selection: None,
+            new_gate_width: 1,
+            new_gate_offset: 0,
+            file_path: String::from("circuit.json"),
+            file_status: None,
+            truth_table: None,
+            truth_table_lines: vec![],
+            analysis_revision: None,
+            constant_outputs: vec![],
+            expression_text: String::new(),
+            expression_error: None,
+            new_clock_timing: ClockTiming::default(),
+            new_storage_kind: StorageKind::default(),
+            running: false,
+            ticks_per_second: 2.0,
+            tick_progress: 0.0,
+            new_memory_spec: MemorySpec::default(),
+            inspected_memory: None,
+            poke_address: 0,
+            poke_value: String::new(),
+            image_path: String::from("memory.hex"),
+            image_format: ImageFormat::default(),
+            memory_status: None,
+        }
+    }
+
+    /// Discards the current circuit and starts an empty one.
+    pub fn new_circuit(&mut self) {
+        *self = Self {
+            file_path: std::mem::take(&mut self.file_path),
+            ..Self::new()
+        };
+    }
+
+    /// Saves the circuit and the position of every gate to `file_path`.
+    pub fn save(&mut self) {
+        let layout: Vec<GatePosition> = self
+            .gate_widgets
+            .iter()
+            .map(|w| GatePosition { gate: w.id, x: w.position.x, y: w.position.y })
+            .collect();
+        self.file_status = Some(match persistence::save(&self.file_path, &self.circuit, &layout) {
+            Ok(()) => format!("Saved {}", self.file_path),
+            Err(err) => format!("Could not save {}: {}", self.file_path, err),
+        });
+    }
+
+    /// Replaces the current circuit with the one stored at `file_path`.
+    pub fn open(&mut self) {
+        match persistence::load(&self.file_path) {
+            Ok((circuit, layout)) => {
+                self.load_circuit(circuit, &layout);
+                self.file_status = Some(format!("Opened {}", self.file_path));
+            }
+            Err(err) => {
+                self.file_status = Some(format!("Could not open {}: {}", self.file_path, err));
+            }
+        }
+    }
+
+    /// Rebuilds the gate widgets for a loaded circuit.
+    ///
+    /// Gates missing from the layout are placed on a grid below the others.
+    pub fn load_circuit(&mut self, circuit: Circuit, layout: &[GatePosition]) {
+        self.circuit = circuit;
+        self.connect_from = None;
+        self.connect_error = None;
+        self.selection = None;
+        self.inspected_memory = None;
+        self.analysis_revision = None;
+        self.gate_widgets.clear();
+        let mut unplaced = 0;
+        let gate_ids: Vec<GateId> = self.circuit.gate_ids().collect();
+        for id in gate_ids {
+            let position = match layout.iter().find(|p| p.gate == id) {
+                Some(p) => Pos2::new(p.x, p.y),
+                None => {
+                    let slot = unplaced as f32;
+                    unplaced += 1;
+                    Pos2::new(20.0 + (slot % 8.0) * 100.0, 400.0 + (slot / 8.0).floor() * 70.0)
+                }
+            };
+            self.push_widget(id, position);
+        }
+        // Loaded outputs may be stale, so start from a full evaluation
+        self.oscillation = self.circuit.settle(DEFAULT_MAX_PASSES).err();
+        self.contention = self.circuit.contention();
+    }
+
+    /// Propagates the latest edits through the circuit until it settles,
+    /// remembering whether it oscillates and which inputs are driven to
+    /// conflicting values.
+    ///
+    /// Only gates downstream of the changes are re-evaluated, which keeps
+    /// clicks responsive in large circuits.
+    pub fn evaluate(&mut self) {
+        self.oscillation = self.circuit.propagate(DEFAULT_MAX_PASSES).err();
+        self.contention = self.circuit.contention();
+    }
+
+    /// Advances the clocks by `count` ticks and updates the warnings.
+    ///
+    /// Stops a running clock if the circuit oscillates.
+    pub fn tick(&mut self, count: u64) {
+        self.oscillation = self.circuit.tick(count).err();
+        self.contention = self.circuit.contention();
+        if self.oscillation.is_some() {
+            self.running = false;
         }
     }
 
+    /// Adds a widget for a gate that is already part of the circuit.
+    fn push_widget(&mut self, id: GateId, position: Pos2) {
+        let gate = self.circuit.gate(id);
+        let input_state = (gate.gate_type == GateType::Input).then(|| gate.output().clone());
+        self.gate_widgets.push(GateWidget {
+            id,
+            gate_type: gate.gate_type,
+            position,
+            input_state,
+        });
+    }
+
+    /// Parses `expression_text` and adds the gates computing it below the
+    /// existing ones.
+    ///
+    /// Input gates already named after a variable are reused; new ones start
+    /// at `0`. Gates are laid out in columns by their distance from the inputs.
+    pub fn insert_expression(&mut self) {
+        let expr: Expr = match self.expression_text.parse() {
+            Ok(expr) => expr,
+            Err(err) => {
+                self.expression_error = Some(err);
+                return;
+            }
+        };
+        self.expression_error = None;
+        expr.add_to(&mut self.circuit);
+
+        let top = self.gate_widgets.iter().map(|w| w.position.y + 80.0).fold(20.0, f32::max);
+        self.place_new_gates(Pos2::new(20.0, top));
+        self.evaluate();
+    }
+
+    /// Replaces a subcircuit instance by the gates it is made of, laid out in
+    /// its place.
+    pub fn expand_gate(&mut self, id: GateId) {
+        let Some(index) = self.gate_widgets.iter().position(|w| w.id == id) else {
+            return;
+        };
+        if let Err(err) = self.circuit.try_expand(id) {
+            self.connect_error = Some(err);
+            return;
+        }
+        let widget = self.gate_widgets.remove(index);
+        self.selection = None;
+        if self.connect_from.is_some_and(|(from, _)| from == id) {
+            self.connect_from = None;
+        }
+        self.place_new_gates(widget.position);
+        self.evaluate();
+    }
+
+    /// Adds widgets for the gates of the circuit that have none, in columns by
+    /// their distance from the inputs, starting at `origin`.
+    ///
+    /// New input gates start at `0`.
+    fn place_new_gates(&mut self, origin: Pos2) {
+        let new_ids: Vec<GateId> = self
+            .circuit
+            .gate_ids()
+            .filter(|&id| !self.gate_widgets.iter().any(|w| w.id == id))
+            .collect();
+        // New gates are created after their drivers, so one pass finds every depth
+        let mut depths: HashMap<GateId, usize> = HashMap::new();
+        let mut column_sizes: Vec<usize> = vec![];
+        for id in new_ids {
+            let gate = self.circuit.gate(id);
+            let depth = if gate.gate_type == GateType::Input {
+                self.circuit.set_primary_input_value(id, false);
+                0
+            } else {
+                (0..gate.input_count)
+                    .filter_map(|i| self.circuit.driver(id, i))
+                    .map(|conn| depths.get(&conn.from).map_or(1, |d| d + 1))
+                    .max()
+                    .unwrap_or(1)
+            };
+            depths.insert(id, depth);
+            if column_sizes.len() <= depth {
+                column_sizes.resize(depth + 1, 0);
+            }
+            let row = column_sizes[depth];
+            column_sizes[depth] += 1;
+            self.push_widget(id, origin + vec2(depth as f32 * 110.0, row as f32 * 70.0));
+        }
+    }
+
+    /// Regenerates the truth table and the constant output warnings if the
+    /// netlist changed since they were last built or a memory was edited.
+    ///
+    /// Both cover every gate whose output is not connected to anything, as a
+    /// function of every input gate.
+    pub fn refresh_analysis(&mut self) {
+        let revision = self.circuit.revision();
+        if self.analysis_revision == Some(revision) {
+            return;
+        }
+        self.analysis_revision = Some(revision);
+        let gate_ids: Vec<GateId> = self.circuit.gate_ids().collect();
+        let schedule = self.circuit.schedule();
+        let sinks: Vec<GateId> = gate_ids.into_iter().filter(|&id| schedule.fanout(id).is_empty()).collect();
+        let outputs: Vec<GateId> = sinks
+            .into_iter()
+            .filter(|&id| self.circuit.gate(id).gate_type != GateType::Input)
+            .collect();
+        let table = self.circuit.truth_table(&outputs);
+        self.truth_table_lines = match &table {
+            Ok(table) => table.to_text().lines().map(String::from).collect(),
+            Err(_) => vec![],
+        };
+        self.truth_table = Some(table);
+
+        // Circuits a BDD cannot describe, such as latches, get no warnings
+        self.constant_outputs = match self.circuit.bdds(&outputs, VariableOrder::DepthFirst) {
+            Ok(bdds) => outputs
+                .iter()
+                .zip(&bdds.outputs)
+                .filter(|(_, bits)| bits.iter().all(|&bit| bdds.manager.is_constant(bit)))
+                .map(|(&id, bits)| (id, bits.iter().map(|&bit| Logic::from(bit == Manager::ONE)).collect()))
+                .collect(),
+            Err(_) => vec![],
+        };
+    }
+
     pub fn add_gate(&mut self, gate_type: GateType, position: Pos2) {
-        let id = self.circuit.add_gate(
-            gate_type,
-            match gate_type {
-                GateType::Not => 1,
-                GateType::Input => 0,
-                _ => 2,
+        let width = self.new_gate_width;
+        let id = match gate_type {
+            GateType::Splitter => self.circuit.add_splitter(self.new_gate_offset, width),
+            GateType::Merger => self.circuit.add_merger(width, 1),
+            GateType::Clock(_) => match self.circuit.try_add_gate(GateType::Clock(self.new_clock_timing), 0) {
+                Ok(id) => id,
+                Err(err) => {
+                    self.connect_error = Some(err);
+                    return;
+                }
             },
-        );
+            GateType::FlipFlop(_) => {
+                let kind = self.new_storage_kind;
+                self.circuit.add_bus_gate(GateType::FlipFlop(kind), kind.input_count(), width)
+            }
+            GateType::Latch(_) => {
+                let kind = self.new_storage_kind;
+                self.circuit.add_bus_gate(GateType::Latch(kind), kind.input_count(), width)
+            }
+            GateType::Memory(_) => {
+                let spec = self.new_memory_spec;
+                match self.circuit.try_add_bus_gate(GateType::Memory(spec), spec.kind.input_count(), width) {
+                    Ok(id) => id,
+                    Err(err) => {
+                        self.connect_error = Some(err);
+                        return;
+                    }
+                }
+            }
+            _ => self.circuit.add_bus_gate(
+                gate_type,
+                match gate_type {
+                    GateType::Not => 1,
+                    GateType::Input => 0,
+                    _ => 2,
+                },
+                width,
+            ),
+        };
 
-        let input_state = if gate_type == GateType::Input { Some(false) } else { None };
+        let input_state = if gate_type == GateType::Input {
+            let value = Bus::from_u64(0, width);
+            self.circuit.set_primary_input_value(id, value.clone());
+            Some(value)
+        } else {
+            None
+        };
 
         self.gate_widgets.push(GateWidget {
             id,
-            gate_type,
+            gate_type: self.circuit.gate(id).gate_type,
             position,
             input_state,
         });
     }
 
+    /// Adds an instance of a library component at `position`.
+    pub fn add_component(&mut self, component: Component, position: Pos2) {
+        match self.circuit.try_add_component(component) {
+            Ok(id) => self.push_widget(id, position),
+            Err(err) => self.connect_error = Some(err),
+        }
+    }
+
+    /// Stores `poke_value`, a hexadecimal number, at `poke_address` of the
+    /// inspected memory.
+    pub fn poke_memory(&mut self) {
+        let Some(id) = self.inspected_memory else {
+            return;
+        };
+        let digits = self.poke_value.trim().trim_start_matches("0x");
+        let Ok(value) = u64::from_str_radix(digits, 16) else {
+            self.memory_status = Some(format!("'{}' is not a hexadecimal number", self.poke_value));
+            return;
+        };
+        let width = self.circuit.memory(id).data_width();
+        // Bus::from_u64 would silently drop the bits that do not fit
+        if value.checked_shr(width as u32).is_some_and(|high| high != 0) {
+            self.memory_status = Some(format!("{:X} does not fit in a {}-bit word", value, width));
+            return;
+        }
+        self.memory_status = match self.circuit.try_write_memory(id, self.poke_address, Bus::from_u64(value, width)) {
+            Ok(()) => None,
+            Err(err) => Some(err.to_string()),
+        };
+        // ROM contents feed the truth table without changing the netlist revision
+        self.analysis_revision = None;
+        self.evaluate();
+    }
+
+    /// Replaces the contents of the inspected memory with the image at `image_path`.
+    pub fn load_memory_image(&mut self) {
+        let Some(id) = self.inspected_memory else {
+            return;
+        };
+        self.memory_status = Some(match std::fs::read(&self.image_path) {
+            Ok(bytes) => match self.circuit.try_load_memory(id, self.image_format, &bytes) {
+                Ok(words) => format!("Loaded {} words from {}", words, self.image_path),
+                Err(err) => format!("Could not load {}: {}", self.image_path, err),
+            },
+            Err(err) => format!("Could not read {}: {}", self.image_path, err),
+        });
+        self.analysis_revision = None;
+        self.evaluate();
+    }
+
+    /// Shows the contents of the inspected memory, eight words per row, with
+    /// controls to load an image and to change single words.
+    fn draw_memory_inspector(&mut self, ctx: &egui::Context) {
+        let Some(id) = self.inspected_memory else {
+            return;
+        };
+        let Ok(memory) = self.circuit.try_memory(id) else {
+            self.inspected_memory = None;
+            return;
+        };
+        let word_count = memory.len();
+        let rows: Vec<String> = memory
+            .words()
+            .chunks(8)
+            .enumerate()
+            .map(|(row, words)| {
+                let words: Vec<String> = words.iter().map(Bus::to_hex).collect();
+                format!("{:04X}: {}", row * 8, words.join(" "))
+            })
+            .collect();
+
+        let mut open = true;
+        let (mut poke, mut load) = (false, false);
+        egui::Window::new(format!("Memory {}", id)).open(&mut open).show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Image");
+                ui.text_edit_singleline(&mut self.image_path);
+                egui::ComboBox::from_id_source("image_format")
+                    .selected_text(self.image_format.name())
+                    .show_ui(ui, |ui| {
+                        for format in ImageFormat::ALL {
+                            ui.selectable_value(&mut self.image_format, format, format.name());
+                        }
+                    });
+                load = ui.button("Load").clicked();
+            });
+            ui.horizontal(|ui| {
+                ui.label("Address");
+                ui.add(
+                    egui::DragValue::new(&mut self.poke_address)
+                        .clamp_range(0..=word_count - 1)
+                        .hexadecimal(4, false, true),
+                );
+                ui.label("Value");
+                ui.text_edit_singleline(&mut self.poke_value);
+                poke = ui.button("Write").clicked();
+            });
+            if let Some(status) = &self.memory_status {
+                ui.label(status);
+            }
+            ui.separator();
+            let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+            egui::ScrollArea::vertical().show_rows(ui, row_height, rows.len(), |ui, range| {
+                for line in &rows[range] {
+                    ui.monospace(line);
+                }
+            });
+        });
+
+        if load {
+            self.load_memory_image();
+        }
+        if poke {
+            self.poke_memory();
+        }
+        if !open {
+            self.inspected_memory = None;
+        }
+    }
+
+    /// Removes the selected gate or wire from the circuit.
+    ///
+    /// Removing a gate also removes its wires; all other gates keep their ids.
+    pub fn delete_selection(&mut self) {
+        match self.selection.take() {
+            Some(Selection::Gate(id)) => {
+                if self.circuit.try_remove_gate(id).is_ok() {
+                    self.gate_widgets.retain(|w| w.id != id);
+                }
+                if self.connect_from.is_some_and(|(from, _)| from == id) {
+                    self.connect_from = None;
+                }
+            }
+            Some(Selection::Wire(conn)) => {
+                let _ = self
+                    .circuit
+                    .try_disconnect_port(conn.from, conn.output_index, conn.to, conn.input_index);
+            }
+            None => return,
+        }
+        self.evaluate();
+    }
+
+    /// Size of the rectangle of a gate, tall enough to keep its pins apart.
+    fn gate_size(&self, id: GateId) -> egui::Vec2 {
+        let gate = self.circuit.gate(id);
+        let pin_count = gate.input_count.max(gate.output_count());
+        vec2(80.0, f32::max(50.0, (pin_count + 1) as f32 * 16.0))
+    }
+
+    fn gate_rect(&self, gate: &GateWidget) -> Rect {
+        Rect::from_min_size(gate.position, self.gate_size(gate.id))
+    }
+
+    /// Screen positions of the output and input pin joined by a wire.
+    fn wire_endpoints(&self, conn: &Connection) -> Option<(Pos2, Pos2)> {
+        let from = self.gate_widgets.iter().find(|g| g.id == conn.from)?;
+        let to = self.gate_widgets.iter().find(|g| g.id == conn.to)?;
+        let output_count = self.circuit.gate(from.id).output_count();
+        let input_count = self.circuit.gate(to.id).input_count;
+        let from_size = self.gate_size(from.id);
+        let outputs_top = from.position + vec2(from_size.x, 0.0);
+        let from_pos = pin_position(outputs_top, conn.output_index, output_count, from_size.y);
+        let to_pos = pin_position(to.position, conn.input_index, input_count, self.gate_size(to.id).y);
+        Some((from_pos, to_pos))
+    }
+
     fn is_position_free(&self, pos: Pos2) -> bool {
         let new_rect = Rect::from_min_size(pos, vec2(80.0, 50.0));
-        !self.gate_widgets.iter().any(|gate| {
-            let gate_rect = Rect::from_min_size(gate.position, vec2(80.0, 50.0));
-            gate_rect.intersects(new_rect)
-        })
+        !self.gate_widgets.iter().any(|gate| self.gate_rect(gate).intersects(new_rect))
     }
 
     pub fn draw(&mut self, ctx: &egui::Context) {
+        TopBottomPanel::top("menu_bar").show(ctx, |ui| {
+            egui::menu::bar(ui, |ui| {
+                ui.menu_button("File", |ui| {
+                    if ui.button("New").clicked() {
+                        self.new_circuit();
+                        ui.close_menu();
+                    }
+                    if ui.button("Open").clicked() {
+                        self.open();
+                        ui.close_menu();
+                    }
+                    if ui.button("Save").clicked() {
+                        self.save();
+                        ui.close_menu();
+                    }
+                });
+                ui.label("Path");
+                ui.text_edit_singleline(&mut self.file_path);
+                if let Some(status) = &self.file_status {
+                    ui.label(status);
+                }
+
+                ui.separator();
+                if ui.button(if self.running { "Pause" } else { "Run" }).clicked() {
+                    self.running = !self.running;
+                    self.tick_progress = 0.0;
+                }
+                if ui.add_enabled(!self.running, egui::Button::new("Step")).clicked() {
+                    self.tick(1);
+                }
+                ui.add(
+                    egui::Slider::new(&mut self.ticks_per_second, 0.5..=100.0)
+                        .logarithmic(true)
+                        .text("ticks/s"),
+                );
+                ui.label(format!("Tick {}", self.circuit.ticks()));
+            });
+        });
+
         // Sidebar for gate selection
         SidePanel::left("gate_selection_panel").show(ctx, |ui| {
             ui.heading("Select Gate Type");
 
             for gate_type in GateType::iter() {
                 let selected = self.selected_gate == Some(gate_type);
-                let button = egui::Button::new(format!("{:?}", gate_type))
+                let button = egui::Button::new(palette_label(gate_type))
                     .fill(if selected { Color32::DARK_GREEN } else { Color32::DARK_GRAY })
                     .stroke(if selected {
                         Stroke::new(2.0, Color32::YELLOW)
@@ -76,16 +744,173 @@ impl CircuitEditor {
 
                 if ui.add(button).clicked() {
                     self.selected_gate = if selected { None } else { Some(gate_type) };
+                    self.selected_component = None;
+                }
+            }
+
+            ui.label("Library");
+            for component in Component::ALL {
+                let selected = self.selected_component.is_some_and(|c| c.label() == component.label());
+                let button = egui::Button::new(component.label())
+                    .fill(if selected { Color32::DARK_GREEN } else { Color32::DARK_GRAY })
+                    .stroke(if selected {
+                        Stroke::new(2.0, Color32::YELLOW)
+                    } else {
+                        Stroke::NONE
+                    });
+
+                if ui.add(button).clicked() {
+                    self.selected_component = if selected { None } else { Some(component) };
+                    self.selected_gate = None;
                 }
             }
 
             if let Some(gate) = self.selected_gate {
-                ui.label(format!("Selected: {:?}", gate));
+                ui.label(format!("Selected: {}", palette_label(gate)));
+            } else if let Some(component) = self.selected_component {
+                ui.label(format!("Selected: {}", component.name()));
             } else {
                 ui.label("No gate selected");
             }
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label("Bus width");
+                ui.add(egui::DragValue::new(&mut self.new_gate_width).clamp_range(1..=64));
+            });
+            if self.selected_gate == Some(GateType::Splitter) {
+                ui.horizontal(|ui| {
+                    ui.label("First bit");
+                    ui.add(egui::DragValue::new(&mut self.new_gate_offset).clamp_range(0..=63));
+                });
+            }
+            if matches!(self.selected_gate, Some(GateType::Clock(_))) {
+                clock_timing_editor(ui, &mut self.new_clock_timing);
+            }
+            if matches!(self.selected_gate, Some(GateType::FlipFlop(_) | GateType::Latch(_))) {
+                egui::ComboBox::from_label("Kind")
+                    .selected_text(format!("{:?}", self.new_storage_kind))
+                    .show_ui(ui, |ui| {
+                        for kind in StorageKind::ALL {
+                            ui.selectable_value(&mut self.new_storage_kind, kind, format!("{:?}", kind));
+                        }
+                    });
+            }
+            if let Some(component) = &mut self.selected_component {
+                component_editor(ui, component);
+            }
+            if matches!(self.selected_gate, Some(GateType::Memory(_))) {
+                egui::ComboBox::from_label("Memory kind")
+                    .selected_text(memory_kind_label(self.new_memory_spec.kind))
+                    .show_ui(ui, |ui| {
+                        for kind in MemoryKind::ALL {
+                            ui.selectable_value(&mut self.new_memory_spec.kind, kind, memory_kind_label(kind));
+                        }
+                    });
+                ui.horizontal(|ui| {
+                    ui.label("Address bits");
+                    ui.add(
+                        egui::DragValue::new(&mut self.new_memory_spec.address_width)
+                            .clamp_range(1..=MAX_ADDRESS_WIDTH),
+                    );
+                });
+            }
+            // The timing of a selected clock can be changed in place
+            if let Some(Selection::Gate(id)) = self.selection
+                && let Some(GateType::Clock(mut timing)) = self.circuit.try_gate(id).ok().map(|g| g.gate_type)
+            {
+                ui.label(format!("Clock gate {}", id));
+                if clock_timing_editor(ui, &mut timing) {
+                    self.connect_error = self.circuit.try_set_clock_timing(id, timing).err();
+                    if let Some(widget) = self.gate_widgets.iter_mut().find(|w| w.id == id) {
+                        widget.gate_type = self.circuit.gate(id).gate_type;
+                    }
+                    self.evaluate();
+                }
+            }
+            if let Some(Selection::Gate(id)) = self.selection
+                && self.circuit.try_memory(id).is_ok()
+                && ui.button("Inspect memory").clicked()
+            {
+                self.inspected_memory = Some(id);
+                self.poke_address = 0;
+                self.memory_status = None;
+            }
+            if let Some(Selection::Gate(id)) = self.selection
+                && matches!(self.circuit.try_gate(id).map(|g| g.gate_type), Ok(GateType::Subcircuit(_)))
+                && ui.button("Expand into gates").clicked()
+            {
+                self.expand_gate(id);
+            }
+
+            ui.separator();
+            ui.label("Expression");
+            ui.text_edit_singleline(&mut self.expression_text);
+            if ui.button("Insert from expression").clicked() {
+                self.insert_expression();
+            }
+            if let Some(err) = &self.expression_error {
+                ui.colored_label(Color32::RED, err.to_string());
+            }
+
+            if let Some(oscillation) = &self.oscillation {
+                ui.colored_label(Color32::RED, oscillation.to_string());
+            }
+            for contention in &self.contention {
+                ui.colored_label(Color32::RED, contention.to_string());
+            }
+            if let Some(err) = &self.connect_error {
+                ui.colored_label(Color32::RED, err.to_string());
+            }
+            for (gate_id, value) in &self.constant_outputs {
+                ui.colored_label(Color32::KHAKI, format!("Output of gate {} is always {}", gate_id, value));
+            }
+        });
+
+        self.refresh_analysis();
+        SidePanel::right("truth_table_panel").show(ctx, |ui| {
+            ui.heading("Truth Table");
+            match &self.truth_table {
+                Some(Ok(table)) => {
+                    ui.horizontal(|ui| {
+                        let copied = if ui.button("Copy CSV").clicked() {
+                            Some(table.to_csv())
+                        } else if ui.button("Copy Markdown").clicked() {
+                            Some(table.to_markdown())
+                        } else if ui.button("Copy text").clicked() {
+                            Some(table.to_text())
+                        } else {
+                            None
+                        };
+                        if let Some(text) = copied {
+                            ui.output_mut(|o| o.copied_text = text);
+                        }
+                    });
+                    let (header, rows) = self.truth_table_lines.split_at(2.min(self.truth_table_lines.len()));
+                    for line in header {
+                        ui.monospace(line);
+                    }
+                    let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+                    egui::ScrollArea::vertical().show_rows(ui, row_height, rows.len(), |ui, range| {
+                        for line in &rows[range] {
+                            ui.monospace(line);
+                        }
+                    });
+                }
+                Some(Err(err)) => {
+                    ui.colored_label(Color32::RED, err.to_string());
+                }
+                None => {}
+            }
         });
 
+        self.draw_memory_inspector(ctx);
+
+        // Keys typed into a text field belong to it, not to the canvas
+        if !ctx.wants_keyboard_input() && ctx.input(|i| i.key_pressed(egui::Key::Delete)) {
+            self.delete_selection();
+        }
+
         CentralPanel::default().show(ctx, |ui| {
             let canvas_size = ui.available_size();
             let response = ui.allocate_rect(
@@ -103,7 +928,6 @@ impl CircuitEditor {
                 }
             }
 
-            let gate_size = vec2(80.0, 50.0);
             let pin_radius = 6.0;
 
             // First, allocate all clickable input/output pin rectangles (to avoid borrow conflicts)
@@ -111,25 +935,22 @@ impl CircuitEditor {
             let mut output_pin_clicks = Vec::new();
 
             for gate in &self.gate_widgets {
-                let input_count = match gate.gate_type {
-                    GateType::Not => 1,
-                    GateType::Input => 0,
-                    _ => 2,
-                };
-                let input_spacing = gate_size.y / (input_count as f32 + 1.0);
-
+                let gate_size = self.gate_size(gate.id);
+                let input_count = self.circuit.gate(gate.id).input_count;
                 for i in 0..input_count {
-                    let y = gate.position.y + input_spacing * (i as f32 + 1.0);
-                    let input_pos = Pos2::new(gate.position.x, y);
+                    let input_pos = pin_position(gate.position, i, input_count, gate_size.y);
                     let input_rect = Rect::from_center_size(input_pos, vec2(pin_radius * 2.0, pin_radius * 2.0));
                     let response = ui.allocate_rect(input_rect, Sense::click());
                     input_pin_clicks.push((gate.id, i, response));
                 }
 
-                let output_pos = Pos2::new(gate.position.x + gate_size.x, gate.position.y + gate_size.y / 2.0);
-                let output_rect = Rect::from_center_size(output_pos, vec2(pin_radius * 2.0, pin_radius * 2.0));
-                let response = ui.allocate_rect(output_rect, Sense::click());
-                output_pin_clicks.push((gate.id, response));
+                let output_count = self.circuit.gate(gate.id).output_count();
+                for i in 0..output_count {
+                    let output_pos = pin_position(gate.position + vec2(gate_size.x, 0.0), i, output_count, gate_size.y);
+                    let output_rect = Rect::from_center_size(output_pos, vec2(pin_radius * 2.0, pin_radius * 2.0));
+                    let response = ui.allocate_rect(output_rect, Sense::click());
+                    output_pin_clicks.push((gate.id, i, response));
+                }
             }
 
             // Now get the painter and do all drawing
@@ -137,104 +958,120 @@ impl CircuitEditor {
 
             // Draw all gates
             for gate in &self.gate_widgets {
+                let gate_size = self.gate_size(gate.id);
                 let rect = Rect::from_min_size(gate.position, gate_size);
-                let is_input = gate.gate_type == GateType::Input;
-
-                painter.rect_filled(
-                    rect,
-                    5.0,
-                    if is_input { Color32::LIGHT_GREEN } else { Color32::LIGHT_BLUE },
-                );
+                let fill = match gate.gate_type {
+                    GateType::Input => Color32::LIGHT_GREEN,
+                    GateType::Clock(_) => Color32::LIGHT_YELLOW,
+                    GateType::Memory(_) => Color32::from_rgb(230, 210, 250),
+                    GateType::Subcircuit(_) => Color32::from_rgb(250, 225, 190),
+                    _ => Color32::LIGHT_BLUE,
+                };
+                painter.rect_filled(rect, 5.0, fill);
 
-                painter.rect_stroke(rect, 5.0, Stroke::new(1.0, Color32::BLACK));
+                if self.selection == Some(Selection::Gate(gate.id)) {
+                    painter.rect_stroke(rect, 5.0, Stroke::new(3.0, Color32::YELLOW));
+                } else {
+                    painter.rect_stroke(rect, 5.0, Stroke::new(1.0, Color32::BLACK));
+                }
 
+                let width = self.circuit.gate(gate.id).width;
+                // Subcircuits are labelled with the name of their definition,
+                // which describes their widths
+                let mut label = match gate.gate_type {
+                    GateType::Subcircuit(id) => self.circuit.subcircuit(id).name().to_string(),
+                    gate_type => gate_type_label(gate_type),
+                };
+                if let Some(name) = &self.circuit.gate(gate.id).name {
+                    label = format!("{} {}", label, name);
+                }
+                if width > 1 && !matches!(gate.gate_type, GateType::Subcircuit(_)) {
+                    label = format!("{} [{}]", label, width);
+                }
                 painter.text(
                     gate.position + vec2(10.0, 10.0),
                     egui::Align2::LEFT_TOP,
-                    format!("{:?}", gate.gate_type),
+                    label,
                     egui::TextStyle::Body.resolve(ui.style()),
                     Color32::BLACK,
                 );
 
                 // Draw input pins with color based on signal
-                let input_count = match gate.gate_type {
-                    GateType::Not => 1,
-                    GateType::Input => 0,
-                    _ => 2,
-                };
-                let input_spacing = gate_size.y / (input_count as f32 + 1.0);
-
-                for i in 0..input_count {
-                    let y = gate.position.y + input_spacing * (i as f32 + 1.0);
-                    let input_pos = Pos2::new(gate.position.x, y);
-                    // Get input signal from circuit connections
-                    let connections = self.circuit.connections();
-                    let conn = connections.iter().find(|&&(_, to_id, input_idx)| {
-                        to_id == gate.id && input_idx == i
-                    });
-                    let input_signal = if let Some((from_id, _, _)) = conn {
-                        self.circuit.get_output(*from_id)
-                    } else {
-                        false
+                let input_names = self.circuit.input_names(gate.id);
+                for (i, input_label) in input_names.iter().enumerate() {
+                    let input_pos = pin_position(gate.position, i, input_names.len(), gate_size.y);
+                    // Get input signal from the output driving it
+                    let input_signal = match self.circuit.driver(gate.id, i) {
+                        Some(conn) => self.circuit.get_port_output(conn.from, conn.output_index),
+                        None => Bus::filled(1, Logic::Z),
                     };
 
-                    let color = if input_signal { Color32::GREEN } else { Color32::RED };
+                    let color = bus_color(&input_signal);
                     painter.circle_filled(input_pos, pin_radius, color);
-
                     painter.text(
                         input_pos - vec2(10.0, 0.0),
                         egui::Align2::RIGHT_CENTER,
-                        format!("In{}", i),
+                        input_label,
                         egui::TextStyle::Small.resolve(ui.style()),
                         Color32::BLACK,
                     );
                 }
 
-                // Draw output pin
-                let output_pos = Pos2::new(gate.position.x + gate_size.x, gate.position.y + gate_size.y / 2.0);
-                let output_signal = self.circuit.get_output(gate.id);
-                let output_color = if output_signal { Color32::GREEN } else { Color32::RED };
-                painter.circle_filled(output_pos, pin_radius, output_color);
-                painter.text(
-                    output_pos + vec2(10.0, 0.0),
-                    egui::Align2::LEFT_CENTER,
-                    "Out",
-                    egui::TextStyle::Small.resolve(ui.style()),
-                    Color32::BLACK,
-                );
+                // Draw output pins, highlighting the one a new wire starts from
+                let output_names = self.circuit.output_names(gate.id);
+                for (i, output_label) in output_names.iter().enumerate() {
+                    let output_pos =
+                        pin_position(gate.position + vec2(gate_size.x, 0.0), i, output_names.len(), gate_size.y);
+                    let output_signal = self.circuit.get_port_output(gate.id, i);
+                    painter.circle_filled(output_pos, pin_radius, bus_color(&output_signal));
+                    if self.connect_from == Some((gate.id, i)) {
+                        painter.circle_stroke(output_pos, pin_radius + 2.0, Stroke::new(2.0, Color32::YELLOW));
+                    }
+                    painter.text(
+                        output_pos + vec2(10.0, 0.0),
+                        egui::Align2::LEFT_CENTER,
+                        output_label,
+                        egui::TextStyle::Small.resolve(ui.style()),
+                        Color32::BLACK,
+                    );
+                }
             }
 
             // Draw connection lines
-            for (from_id, to_id, input_index) in self.circuit.connections() {
-                let from_gate = self.gate_widgets.iter().find(|g| g.id == from_id);
-                let to_gate = self.gate_widgets.iter().find(|g| g.id == to_id);
-
-                if let (Some(from), Some(to)) = (from_gate, to_gate) {
-                    let from_pos = from.position + vec2(gate_size.x, gate_size.y / 2.0);
-                    let input_count = match to.gate_type {
-                        GateType::Not => 1,
-                        GateType::Input => 0,
-                        _ => 2,
-                    };
-                    let input_spacing = gate_size.y / (input_count as f32 + 1.0);
-                    let to_pos = to.position + vec2(0.0, input_spacing * (input_index as f32 + 1.0));
+            for conn in self.circuit.port_connections() {
+                if let Some((from_pos, to_pos)) = self.wire_endpoints(conn) {
+                    let output_value = self.circuit.get_port_output(conn.from, conn.output_index);
+                    let color = bus_color(&output_value);
 
-                    let output_value = self.circuit.get_output(from_id);
-                    let color = if output_value { Color32::GREEN } else { Color32::RED };
+                    if self.selection.as_ref() == Some(&Selection::Wire(conn.clone())) {
+                        painter.line_segment([from_pos, to_pos], Stroke::new(9.0, Color32::YELLOW));
+                    }
 
-                    painter.line_segment([from_pos, to_pos], Stroke::new(2.0, color));
+                    if output_value.width() > 1 {
+                        // Buses are drawn thicker and labelled with their value
+                        painter.line_segment([from_pos, to_pos], Stroke::new(5.0, color));
+                        painter.text(
+                            from_pos + (to_pos - from_pos) / 2.0,
+                            egui::Align2::CENTER_BOTTOM,
+                            output_value.to_string(),
+                            egui::TextStyle::Small.resolve(ui.style()),
+                            Color32::BLACK,
+                        );
+                    } else {
+                        painter.line_segment([from_pos, to_pos], Stroke::new(2.0, color));
+                    }
                 }
             }
 
             // Handle toggle button clicks and draw toggles
             for (gate_id, toggle_response, toggle_rect) in toggle_responses {
                 if toggle_response.clicked() {
-                    let current = self.circuit.get_output(gate_id);
-                    self.circuit.set_primary_input_value(gate_id, !current);
-                    self.circuit.evaluate();
+                    let new_state = next_input_value(&self.circuit.get_output(gate_id));
+                    self.circuit.set_primary_input_value(gate_id, new_state.clone());
+                    self.evaluate();
 
                     if let Some(gate_widget) = self.gate_widgets.iter_mut().find(|g| g.id == gate_id) {
-                        gate_widget.input_state = Some(!current);
+                        gate_widget.input_state = Some(new_state);
                     }
                 }
 
@@ -242,7 +1079,16 @@ impl CircuitEditor {
                 painter.text(
                     toggle_rect.center(),
                     egui::Align2::CENTER_CENTER,
-                    if self.circuit.get_output(gate_id) { "TRUE" } else { "FALSE" },
+                    {
+                        let value = self.circuit.get_output(gate_id);
+                        if value == Logic::One {
+                            "TRUE".to_string()
+                        } else if value == Logic::Zero {
+                            "FALSE".to_string()
+                        } else {
+                            value.to_string()
+                        }
+                    },
                     egui::TextStyle::Body.resolve(ui.style()),
                     Color32::WHITE,
                 );
@@ -250,62 +1096,79 @@ impl CircuitEditor {
 
             // Handle clicks on input pins to create connections
             for (to_id, input_idx, response) in input_pin_clicks {
-                if response.clicked() {
-                    if let Some(from_id) = self.connect_from {
-                        self.circuit.connect(from_id, to_id, input_idx);
-                        self.connect_from = None;
-                        self.circuit.evaluate();
-                    }
+                if response.clicked()
+                    && let Some((from_id, output_idx)) = self.connect_from
+                {
+                    self.connect_error = self.circuit.try_replace_driver(from_id, output_idx, to_id, input_idx).err();
+                    self.connect_from = None;
+                    self.evaluate();
                 }
             }
 
             // Handle clicks on output pins to start the connection
-            for (from_id, response) in output_pin_clicks {
+            for (from_id, output_idx, response) in output_pin_clicks {
                 if response.clicked() {
-                    self.connect_from = Some(from_id);
+                    self.connect_from = Some((from_id, output_idx));
                 }
             }
 
             // Toggle input gates with click on gate rectangle as fallback
             let pointer_pos = ui.ctx().input(|i| i.pointer.interact_pos());
 
-            if let Some(pos) = pointer_pos {
-                if ui.input(|i| i.pointer.any_click()) {
-                    for gate in &mut self.gate_widgets {
-                        let rect = Rect::from_min_size(gate.position, gate_size);
-                        if rect.contains(pos) && gate.gate_type == GateType::Input {
-                            gate.input_state = Some(!gate.input_state.unwrap_or(false));
-                            self.circuit.set_primary_input_value(gate.id, gate.input_state.unwrap());
-                            self.circuit.evaluate();
-                        }
+            if let Some(pos) = pointer_pos
+                && ui.input(|i| i.pointer.any_click())
+            {
+                let mut toggled = false;
+                for index in 0..self.gate_widgets.len() {
+                    let gate = &self.gate_widgets[index];
+                    if self.gate_rect(gate).contains(pos) && gate.gate_type == GateType::Input {
+                        let new_state = next_input_value(&self.circuit.get_output(gate.id));
+                        self.circuit.set_primary_input_value(gate.id, new_state.clone());
+                        self.gate_widgets[index].input_state = Some(new_state);
+                        toggled = true;
                     }
                 }
+                if toggled {
+                    self.evaluate();
+                }
             }
 
             // Handle gate placement or connection on empty canvas
-            if response.clicked() {
-                if let Some(click_pos) = response.interact_pointer_pos() {
-                    let adjusted_pos = click_pos - vec2(gate_size.x / 2.0, gate_size.y / 2.0);
+            if response.clicked()
+                && let Some(click_pos) = response.interact_pointer_pos()
+            {
+                let adjusted_pos = click_pos - vec2(40.0, 25.0);
 
-                    let clicked_gate = self.gate_widgets.iter().find(|g| {
-                        let rect = Rect::from_min_size(g.position, gate_size);
-                        rect.contains(click_pos)
-                    });
+                let clicked_gate = self.gate_widgets.iter().find(|g| self.gate_rect(g).contains(click_pos));
 
-                    if let Some(gate) = clicked_gate {
-                        if self.connect_from.is_some() {
-                            // clicking a gate's body after selecting a from gate connects to input 0
-                            self.circuit.connect(self.connect_from.unwrap(), gate.id, 0);
-                            self.connect_from = None;
-                            self.circuit.evaluate();
-                        } else {
-                            self.connect_from = Some(gate.id);
-                        }
-                    } else if let Some(gate_type) = self.selected_gate {
-                        if self.is_position_free(adjusted_pos) {
-                            self.add_gate(gate_type, adjusted_pos);
-                            self.circuit.evaluate();
-                        }
+                if let Some(gate) = clicked_gate {
+                    if let Some((from_id, output_idx)) = self.connect_from {
+                        // clicking a gate's body after selecting a from gate connects to input 0
+                        self.connect_error = self.circuit.try_replace_driver(from_id, output_idx, gate.id, 0).err();
+                        self.connect_from = None;
+                        self.evaluate();
+                    } else {
+                        // clicking a gate's body starts a wire from its first output
+                        self.connect_from = Some((gate.id, 0));
+                        self.selection = Some(Selection::Gate(gate.id));
+                    }
+                } else if let Some(conn) = self.circuit.port_connections().iter().find(|conn| {
+                    self.wire_endpoints(conn)
+                        .is_some_and(|(a, b)| distance_to_segment(click_pos, a, b) < 5.0)
+                }) {
+                    self.selection = Some(Selection::Wire(conn.clone()));
+                } else {
+                    self.selection = None;
+                    if let Some(gate_type) = self.selected_gate
+                        && self.is_position_free(adjusted_pos)
+                    {
+                        self.add_gate(gate_type, adjusted_pos);
+                        self.evaluate();
+                    } else if let Some(component) = self.selected_component
+                        && self.is_position_free(adjusted_pos)
+                    {
+                        self.add_component(component, adjusted_pos);
+                        self.evaluate();
                     }
                 }
             }
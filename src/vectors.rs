@@ -0,0 +1,298 @@
+use crate::bus::{Bus, ParseBusError};
+use crate::circuit::{Circuit, Oscillation, DEFAULT_MAX_PASSES};
+use crate::connection::GateId;
+use crate::gate::GateType;
+use crate::logic::Logic;
+use std::fmt;
+use std::str::FromStr;
+
+/// Errors in a test vector file, or in how its vectors refer to a circuit.
+///
+/// Each error carries the line of the file it was found on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VectorError {
+    /// A token is not of the form `name=value`.
+    Malformed { line: usize, token: String },
+    /// A value contains a character that is not a signal value.
+    InvalidValue { line: usize, name: String, error: ParseBusError },
+    /// No gate has the name.
+    UnknownGate { line: usize, name: String },
+    /// A value is assigned to a gate that is not an input.
+    NotAnInput { line: usize, name: String },
+    /// A value does not have as many bits as its gate.
+    WidthMismatch { line: usize, name: String, width: usize, found: usize },
+}
+
+impl fmt::Display for VectorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VectorError::Malformed { line, token } => {
+                write!(f, "line {}: expected name=value, found '{}'", line, token)
+            }
+            VectorError::InvalidValue { line, name, error } => write!(f, "line {}: {}: {}", line, name, error),
+            VectorError::UnknownGate { line, name } => write!(f, "line {}: no gate named '{}'", line, name),
+            VectorError::NotAnInput { line, name } => write!(f, "line {}: {} is not an input", line, name),
+            VectorError::WidthMismatch { line, name, width, found } => {
+                write!(f, "line {}: {} is {} bits wide, found {} bits", line, name, width, found)
+            }
+        }
+    }
+}
+
+impl std::error::Error for VectorError {}
+
+/// An expected value in which some bits may be don't-cares.
+///
+/// Written like a [`Bus`] bit string, most significant bit first, with `-`
+/// for a bit that may have any value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    /// The expected value of each bit, least significant first, or `None` for
+    /// a don't-care.
+    pub bits: Vec<Option<Logic>>,
+}
+
+impl Pattern {
+    /// Number of bits.
+    pub fn width(&self) -> usize {
+        self.bits.len()
+    }
+
+    /// Returns `true` if the value has the same width and every bit that is
+    /// not a don't-care is equal.
+    pub fn matches(&self, value: &Bus) -> bool {
+        value.width() == self.width()
+            && self.bits.iter().zip(value.bits()).all(|(expected, actual)| expected.is_none_or(|bit| bit == *actual))
+    }
+}
+
+impl From<Bus> for Pattern {
+    fn from(value: Bus) -> Self {
+        Self { bits: value.bits().iter().copied().map(Some).collect() }
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = ParseBusError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.chars()
+            .rev()
+            .map(|c| match c {
+                '-' => Ok(None),
+                _ => Logic::from_char(c).map(Some).ok_or(ParseBusError { invalid: c }),
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(|bits| Self { bits })
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for bit in self.bits.iter().rev() {
+            match bit {
+                Some(bit) => write!(f, "{}", bit)?,
+                None => f.write_str("-")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// One line of a test vector file: values for some inputs and the outputs
+/// expected once the circuit has settled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestVector {
+    /// Line of the file, counting from 1.
+    pub line: usize,
+    pub inputs: Vec<(String, Bus)>,
+    pub expected: Vec<(String, Pattern)>,
+}
+
+/// Parses a test vector file.
+///
+/// Each line assigns inputs and may list expected outputs after `->`, as
+/// whitespace-separated `name=value` pairs. Values are bit strings, most
+/// significant bit first, and expected values may use `-` for don't-care
+/// bits. Everything after `#` is a comment, and blank lines are skipped:
+///
+/// ```text
+/// # half adder
+/// a=0 b=1 -> sum=1 carry=0
+/// a=1 b=1 -> sum=0 carry=-
+/// ```
+///
+/// Gates are named by [`Circuit::set_name`]; unnamed gates can be referred to
+/// as `in<id>` for inputs and `out<id>` otherwise. Inputs that a line does
+/// not assign keep their value from the previous line.
+pub fn parse(text: &str) -> Result<Vec<TestVector>, VectorError> {
+    let mut vectors = vec![];
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (assignments, expectations) = line.split_once("->").unwrap_or((line, ""));
+        vectors.push(TestVector {
+            line: line_number,
+            inputs: parse_pairs(line_number, assignments)?,
+            expected: parse_pairs(line_number, expectations)?,
+        });
+    }
+    Ok(vectors)
+}
+
+fn parse_pairs<T: FromStr<Err = ParseBusError>>(line: usize, text: &str) -> Result<Vec<(String, T)>, VectorError> {
+    text.split_whitespace()
+        .map(|token| {
+            let (name, value) = token
+                .split_once('=')
+                .ok_or_else(|| VectorError::Malformed { line, token: token.to_string() })?;
+            let value = value
+                .parse()
+                .map_err(|error| VectorError::InvalidValue { line, name: name.to_string(), error })?;
+            Ok((name.to_string(), value))
+        })
+        .collect()
+}
+
+/// An expected output that differs from the simulated one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    /// Position of the vector among all vectors, counting from 1.
+    pub row: usize,
+    /// Line of the file the vector is on.
+    pub line: usize,
+    pub name: String,
+    pub expected: Pattern,
+    pub actual: Bus,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "row {} (line {}): {} is {}, expected {}",
+            self.row,
+            self.line,
+            self.name,
+            self.actual.to_bit_string(),
+            self.expected
+        )
+    }
+}
+
+/// The simulated values for one vector.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RowResult {
+    pub row: usize,
+    pub line: usize,
+    /// The inputs assigned by the vector followed by every gate whose output
+    /// is not connected to anything, labelled as in the vector file.
+    pub values: Vec<(String, Bus)>,
+    /// Set if the circuit did not settle for this vector.
+    pub oscillation: Option<Oscillation>,
+}
+
+/// The results of running a list of test vectors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    pub rows: Vec<RowResult>,
+    pub mismatches: Vec<Mismatch>,
+}
+
+impl Report {
+    /// Returns `true` if every vector settled and produced its expected outputs.
+    pub fn passed(&self) -> bool {
+        self.mismatches.is_empty() && self.rows.iter().all(|row| row.oscillation.is_none())
+    }
+}
+
+/// Applies each vector to the circuit in turn, settles it and compares the
+/// outputs with the expected values.
+///
+/// Every name is checked before anything is simulated. Inputs keep the values
+/// of the last vector afterwards.
+pub fn run(circuit: &mut Circuit, vectors: &[TestVector]) -> Result<Report, VectorError> {
+    let mut resolved = vec![];
+    for vector in vectors {
+        let mut inputs = vec![];
+        for (name, value) in &vector.inputs {
+            let gate_id = resolve(circuit, vector.line, name, value.width())?;
+            if circuit.gate(gate_id).gate_type != GateType::Input {
+                return Err(VectorError::NotAnInput { line: vector.line, name: name.clone() });
+            }
+            inputs.push((gate_id, value.clone()));
+        }
+        let mut expected = vec![];
+        for (name, pattern) in &vector.expected {
+            expected.push(resolve(circuit, vector.line, name, pattern.width())?);
+        }
+        resolved.push((inputs, expected));
+    }
+
+    let gate_ids: Vec<GateId> = circuit.gate_ids().collect();
+    let schedule = circuit.schedule();
+    let sinks: Vec<GateId> = gate_ids.into_iter().filter(|&id| schedule.fanout(id).is_empty()).collect();
+    let outputs: Vec<GateId> = sinks
+        .into_iter()
+        .filter(|&id| circuit.gate(id).gate_type != GateType::Input)
+        .collect();
+
+    let mut report = Report { rows: vec![], mismatches: vec![] };
+    for (index, (vector, (inputs, expected))) in vectors.iter().zip(resolved).enumerate() {
+        let row = index + 1;
+        for (gate_id, value) in &inputs {
+            circuit.set_primary_input_value(*gate_id, value.clone());
+        }
+        let oscillation = circuit.settle(DEFAULT_MAX_PASSES).err();
+
+        for ((name, pattern), gate_id) in vector.expected.iter().zip(expected) {
+            let actual = circuit.get_output(gate_id);
+            if !pattern.matches(&actual) {
+                report.mismatches.push(Mismatch {
+                    row,
+                    line: vector.line,
+                    name: name.clone(),
+                    expected: pattern.clone(),
+                    actual,
+                });
+            }
+        }
+        let values = inputs
+            .iter()
+            .map(|(gate_id, _)| *gate_id)
+            .chain(outputs.iter().copied())
+            .map(|gate_id| (label(circuit, gate_id), circuit.get_output(gate_id)))
+            .collect();
+        report.rows.push(RowResult { row, line: vector.line, values, oscillation });
+    }
+    Ok(report)
+}
+
+/// Finds the gate a vector refers to and checks the width of its value.
+fn resolve(circuit: &Circuit, line: usize, name: &str, width: usize) -> Result<GateId, VectorError> {
+    let gate_id = circuit
+        .find_gate(name)
+        .or_else(|| {
+            let id = name.strip_prefix("in").or_else(|| name.strip_prefix("out"))?.parse().ok()?;
+            (circuit.contains_gate(id) && label(circuit, id) == name).then_some(id)
+        })
+        .ok_or_else(|| VectorError::UnknownGate { line, name: name.to_string() })?;
+    let gate_width = circuit.gate(gate_id).width;
+    if width != gate_width {
+        return Err(VectorError::WidthMismatch { line, name: name.to_string(), width: gate_width, found: width });
+    }
+    Ok(gate_id)
+}
+
+/// The name of a gate, or `in<id>` for unnamed inputs and `out<id>` for other unnamed gates.
+fn label(circuit: &Circuit, gate_id: GateId) -> String {
+    let gate = circuit.gate(gate_id);
+    match &gate.name {
+        Some(name) => name.clone(),
+        None if gate.gate_type == GateType::Input => format!("in{}", gate_id),
+        None => format!("out{}", gate_id),
+    }
+}
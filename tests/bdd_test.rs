@@ -0,0 +1,165 @@
+use digital_logic_simulator::bdd::{BddError, Manager, VariableOrder};
+use digital_logic_simulator::bus::Bus;
+use digital_logic_simulator::circuit::Circuit;
+use digital_logic_simulator::equivalence::{Equivalence, OutputMismatch};
+use digital_logic_simulator::expression::Expr;
+use digital_logic_simulator::gate::GateType;
+use digital_logic_simulator::subcircuit::Subcircuit;
+
+fn manager(count: usize) -> Manager {
+    Manager::new((0..count).map(|i| format!("v{}", i)).collect())
+}
+
+#[test]
+fn test_equal_functions_share_a_node() {
+    let mut m = manager(2);
+    let (a, b) = (m.var(0), m.var(1));
+    let and = m.and(a, b);
+    let not_a = m.not(a);
+    let not_b = m.not(b);
+    let or = m.or(not_a, not_b);
+    assert_eq!(m.not(and), or);
+
+    let a_xor_b = m.xor(a, b);
+    let twice = m.xor(a_xor_b, b);
+    assert_eq!(twice, a);
+    let contradiction = m.and(a, not_a);
+    assert_eq!(contradiction, Manager::ZERO);
+    assert!(m.is_constant(contradiction));
+}
+
+#[test]
+fn test_counting_and_enumeration() {
+    let mut m = manager(4);
+    let (a, c) = (m.var(0), m.var(2));
+    let f = m.or(a, c);
+
+    assert_eq!(m.sat_count(f), 12);
+    assert_eq!(m.sat_count(Manager::ONE), 16);
+    assert_eq!(m.sat_count(Manager::ZERO), 0);
+    assert_eq!(m.node_count(f), 2);
+    assert_eq!(m.any_sat(f), Some(vec![false, false, true, false]));
+    assert_eq!(m.any_sat(Manager::ZERO), None);
+
+    let mut cubes = m.cubes(f);
+    cubes.sort();
+    assert_eq!(cubes, vec![vec![Some(false), None, Some(true), None], vec![Some(true), None, None, None]]);
+    assert!(m.evaluate(f, &[false, true, true, false]));
+    assert!(!m.evaluate(f, &[false, true, false, true]));
+}
+
+#[test]
+fn test_gates_computing_the_same_function() {
+    let mut circuit = Circuit::new();
+    let factored = "a & (b | c)".parse::<Expr>().unwrap().add_to(&mut circuit);
+    let expanded = "a & b | c & a".parse::<Expr>().unwrap().add_to(&mut circuit);
+    let different = "a & b | c".parse::<Expr>().unwrap().add_to(&mut circuit);
+    let bdds = circuit.bdds(&[factored, expanded, different], VariableOrder::Declaration).unwrap();
+
+    assert_eq!(bdds.manager.variables(), ["a", "b", "c"]);
+    assert_eq!(bdds.inputs, vec![(0, 0), (1, 0), (2, 0)]);
+    assert_eq!(bdds.outputs[0], bdds.outputs[1]);
+    assert_ne!(bdds.outputs[0], bdds.outputs[2]);
+    assert_eq!(bdds.manager.sat_count(bdds.outputs[0][0]), 3);
+}
+
+/// Builds `x == y` for two `width`-bit inputs, returning the circuit and its output.
+fn bus_comparator(width: usize) -> (Circuit, usize) {
+    let mut circuit = Circuit::new();
+    let x = circuit.add_bus_gate(GateType::Input, 0, width);
+    let y = circuit.add_bus_gate(GateType::Input, 0, width);
+    let xor = circuit.add_bus_gate(GateType::Xor, 2, width);
+    let not = circuit.add_bus_gate(GateType::Not, 1, width);
+    let equal = circuit.add_gate(GateType::And, width);
+    circuit.connect(x, xor, 0);
+    circuit.connect(y, xor, 1);
+    circuit.connect(xor, not, 0);
+    for bit in 0..width {
+        let splitter = circuit.add_splitter(bit, 1);
+        circuit.connect(not, splitter, 0);
+        circuit.connect(splitter, equal, bit);
+    }
+    (circuit, equal)
+}
+
+#[test]
+fn test_variable_orders() {
+    let (circuit, equal) = bus_comparator(8);
+    let size = |order| {
+        let bdds = circuit.bdds(&[equal], order).unwrap();
+        assert_eq!(bdds.manager.sat_count(bdds.outputs[0][0]), 256);
+        bdds.manager.node_count(bdds.outputs[0][0])
+    };
+
+    assert_eq!(size(VariableOrder::Interleaved), 3 * 8);
+    assert!(size(VariableOrder::Declaration) > 500);
+
+    let bdds = circuit.bdds(&[equal], VariableOrder::Interleaved).unwrap();
+    assert_eq!(bdds.manager.variables()[..4], ["in0_7", "in1_7", "in0_6", "in1_6"]);
+
+    // Depth-first reaches the bits of the first comparison first
+    let mut chain = Circuit::new();
+    let output = "a0 & b0 | a1 & b1".parse::<Expr>().unwrap().add_to(&mut chain);
+    let unused = chain.add_gate(GateType::Input, 0);
+    chain.set_name(unused, "unused");
+    let bdds = chain.bdds(&[output], VariableOrder::DepthFirst).unwrap();
+    assert_eq!(bdds.manager.variables(), ["a0", "b0", "a1", "b1", "unused"]);
+}
+
+#[test]
+fn test_unsupported_circuits() {
+    let mut circuit = Circuit::new();
+    let a = circuit.add_gate(GateType::Input, 0);
+    let and = circuit.add_gate(GateType::And, 2);
+    circuit.connect(a, and, 0);
+    assert_eq!(
+        circuit.bdds(&[and], VariableOrder::Declaration).unwrap_err(),
+        BddError::Floating { gate: and, input_index: 1 }
+    );
+    assert_eq!(circuit.bdds(&[7], VariableOrder::Declaration).unwrap_err(), BddError::InvalidGate(7));
+
+    circuit.connect(and, and, 1);
+    assert_eq!(circuit.bdds(&[and], VariableOrder::Declaration).unwrap_err(), BddError::Feedback(and));
+
+    let mut inner = Circuit::new();
+    let port = inner.add_gate(GateType::Input, 0);
+    let mut definition = Subcircuit::new("buffer", inner);
+    definition.add_input("in", port);
+    definition.add_output("out", port);
+    let id = circuit.define_subcircuit(definition);
+    let instance = circuit.add_subcircuit(id);
+    circuit.connect(a, instance, 0);
+    assert_eq!(
+        circuit.bdds(&[instance], VariableOrder::Declaration).unwrap_err(),
+        BddError::Unsupported(instance)
+    );
+    // Gates outside the fan-in of the outputs do not matter
+    assert!(circuit.bdds(&[a], VariableOrder::Declaration).is_ok());
+}
+
+#[test]
+fn test_equivalence_beyond_exhaustive_simulation() {
+    // Two 16-bit inputs are too many to simulate every combination
+    let named = |swap: bool| {
+        let (mut circuit, equal) = bus_comparator(16);
+        circuit.set_name(if swap { 1 } else { 0 }, "x");
+        circuit.set_name(if swap { 0 } else { 1 }, "y");
+        circuit.set_name(equal, "equal");
+        circuit
+    };
+    assert_eq!(named(false).check_equivalence(&mut named(true)), Ok(Equivalence::Equivalent));
+
+    let mut broken = named(false);
+    broken.replace_driver(0, 0, 2, 1);
+    let Ok(Equivalence::Different(counterexample)) = named(false).check_equivalence(&mut broken) else {
+        panic!("comparing x with itself differs from comparing x with y");
+    };
+    assert_eq!(
+        counterexample.inputs,
+        vec![("x".to_string(), Bus::from_u64(0, 16)), ("y".to_string(), Bus::from_u64(1, 16))]
+    );
+    assert_eq!(
+        counterexample.outputs,
+        vec![OutputMismatch { name: "equal".to_string(), left: Bus::from(false), right: Bus::from(true) }]
+    );
+}
@@ -0,0 +1,58 @@
+use digital_logic_simulator::bus::Bus;
+use digital_logic_simulator::logic::Logic;
+
+#[test]
+fn test_integer_round_trip() {
+    let bus = Bus::from_u64(0xA5, 8);
+    assert_eq!(bus.width(), 8);
+    assert_eq!(bus.bit(0), Logic::One);
+    assert_eq!(bus.bit(1), Logic::Zero);
+    assert_eq!(bus.to_u64(), Some(0xA5));
+}
+
+#[test]
+fn test_unknown_bits_have_no_integer_value() {
+    let mut bus = Bus::from_u64(3, 4);
+    bus.set_bit(2, Logic::X);
+    assert!(!bus.is_known());
+    assert_eq!(bus.to_u64(), None);
+}
+
+#[test]
+fn test_hex_formatting() {
+    assert_eq!(Bus::from_u64(0x1F, 8).to_hex(), "1F");
+    assert_eq!(Bus::from_u64(0x5, 3).to_hex(), "5");
+    assert_eq!(Bus::filled(8, Logic::Z).to_hex(), "ZZ");
+
+    let mut bus = Bus::from_u64(0x12, 8);
+    bus.set_bit(6, Logic::X);
+    assert_eq!(bus.to_hex(), "X2");
+
+    assert_eq!(Bus::from_u64(0xBEEF, 16).to_string(), "0xBEEF");
+    assert_eq!(Bus::from(true).to_string(), "1");
+}
+
+#[test]
+fn test_slice_and_concat() {
+    let bus = Bus::from_u64(0b1101_0110, 8);
+    assert_eq!(bus.slice(4, 4), Bus::from_u64(0b1101, 4));
+    assert_eq!(bus.slice(6, 4).bit(3), Logic::Z);
+
+    let merged = Bus::concat(&[Bus::from_u64(0b10, 2), Bus::from_u64(0b1, 1)]);
+    assert_eq!(merged, Bus::from_u64(0b110, 3));
+}
+
+#[test]
+fn test_single_bit_bus_compares_with_scalars() {
+    assert_eq!(Bus::from(Logic::One), Logic::One);
+    assert_eq!(Bus::from(false), false);
+    assert!(Bus::from_u64(1, 2) != Logic::One);
+}
+
+#[test]
+fn test_resolve_buses() {
+    let a: Bus = "10ZZ".parse().unwrap();
+    let b: Bus = "1Z01".parse().unwrap();
+    assert_eq!(a.resolve(&b), "1001".parse::<Bus>().unwrap());
+    assert_eq!(a.resolve(&"0ZZZ".parse().unwrap()), "X0ZZ".parse::<Bus>().unwrap());
+}
@@ -1,5 +1,8 @@
+use digital_logic_simulator::bus::Bus;
 use digital_logic_simulator::gate::GateType;
-use digital_logic_simulator::circuit::Circuit;
+use digital_logic_simulator::logic::Logic;
+use digital_logic_simulator::circuit::{Circuit, DEFAULT_MAX_PASSES};
+use digital_logic_simulator::error::CircuitError;
 
 #[test]
 fn test_add_gate_and_get_output() {
@@ -94,3 +97,377 @@ fn test_circuit_with_nested_gates() {
     assert_eq!(circuit.get_output(and1), true);
     assert_eq!(circuit.get_output(not1), false);
 }
+
+/// Builds an SR latch from two cross-coupled NOR gates (OR followed by NOT).
+///
+/// Returns `(set, reset, q, q_bar)`.
+fn build_sr_latch(circuit: &mut Circuit) -> (usize, usize, usize, usize) {
+    let set = circuit.add_gate(GateType::Input, 0);
+    let reset = circuit.add_gate(GateType::Input, 0);
+    let or_q = circuit.add_gate(GateType::Or, 2);
+    let q = circuit.add_gate(GateType::Not, 1);
+    let or_q_bar = circuit.add_gate(GateType::Or, 2);
+    let q_bar = circuit.add_gate(GateType::Not, 1);
+
+    circuit.connect(reset, or_q, 0);
+    circuit.connect(q_bar, or_q, 1);
+    circuit.connect(or_q, q, 0);
+    circuit.connect(set, or_q_bar, 0);
+    circuit.connect(q, or_q_bar, 1);
+    circuit.connect(or_q_bar, q_bar, 0);
+
+    (set, reset, q, q_bar)
+}
+
+#[test]
+fn test_sr_latch_settles_and_holds_state() {
+    let mut circuit = Circuit::new();
+    let (set, reset, q, q_bar) = build_sr_latch(&mut circuit);
+
+    circuit.set_primary_input_value(set, true);
+    circuit.set_primary_input_value(reset, false);
+    assert!(circuit.settle(DEFAULT_MAX_PASSES).is_ok());
+    assert_eq!(circuit.get_output(q), true);
+    assert_eq!(circuit.get_output(q_bar), false);
+
+    // Releasing set keeps the latch in its previous state
+    circuit.set_primary_input_value(set, false);
+    assert!(circuit.settle(DEFAULT_MAX_PASSES).is_ok());
+    assert_eq!(circuit.get_output(q), true);
+    assert_eq!(circuit.get_output(q_bar), false);
+
+    circuit.set_primary_input_value(reset, true);
+    assert!(circuit.settle(DEFAULT_MAX_PASSES).is_ok());
+    assert_eq!(circuit.get_output(q), false);
+    assert_eq!(circuit.get_output(q_bar), true);
+
+    circuit.set_primary_input_value(reset, false);
+    assert!(circuit.settle(DEFAULT_MAX_PASSES).is_ok());
+    assert_eq!(circuit.get_output(q), false);
+    assert_eq!(circuit.get_output(q_bar), true);
+}
+
+#[test]
+fn test_ring_oscillator_reports_oscillation() {
+    let mut circuit = Circuit::new();
+
+    // An AND gate feeding an inverter back into itself oscillates once enabled
+    let enable = circuit.add_gate(GateType::Input, 0);
+    let and1 = circuit.add_gate(GateType::And, 2);
+    let not1 = circuit.add_gate(GateType::Not, 1);
+
+    circuit.connect(enable, and1, 0);
+    circuit.connect(not1, and1, 1);
+    circuit.connect(and1, not1, 0);
+
+    circuit.set_primary_input_value(enable, false);
+    assert!(circuit.settle(DEFAULT_MAX_PASSES).is_ok());
+    assert_eq!(circuit.get_output(not1), true);
+
+    circuit.set_primary_input_value(enable, true);
+    let oscillation = circuit.settle(10).unwrap_err();
+    assert_eq!(oscillation.passes, 10);
+    assert!(!oscillation.unstable.is_empty());
+}
+
+#[test]
+fn test_settle_on_acyclic_circuit() {
+    let mut circuit = Circuit::new();
+
+    let a = circuit.add_gate(GateType::Input, 0);
+    let not1 = circuit.add_gate(GateType::Not, 1);
+    circuit.connect(a, not1, 0);
+    circuit.set_primary_input_value(a, false);
+
+    assert_eq!(circuit.settle(DEFAULT_MAX_PASSES), Ok(2));
+    assert_eq!(circuit.get_output(not1), true);
+    assert_eq!(circuit.settle(DEFAULT_MAX_PASSES), Ok(1));
+}
+
+#[test]
+fn test_unconnected_input_is_visible() {
+    let mut circuit = Circuit::new();
+
+    let a = circuit.add_gate(GateType::Input, 0);
+    let and_gate = circuit.add_gate(GateType::And, 2);
+    let or_gate = circuit.add_gate(GateType::Or, 2);
+    circuit.connect(a, and_gate, 0);
+    circuit.connect(a, or_gate, 0);
+
+    circuit.set_primary_input_value(a, true);
+    circuit.evaluate();
+    assert_eq!(circuit.get_output(and_gate), Logic::X);
+    assert_eq!(circuit.get_output(or_gate), Logic::One);
+
+    circuit.set_primary_input_value(a, false);
+    circuit.evaluate();
+    assert_eq!(circuit.get_output(and_gate), Logic::Zero);
+    assert_eq!(circuit.get_output(or_gate), Logic::X);
+}
+
+#[test]
+fn test_unset_input_reads_unknown() {
+    let mut circuit = Circuit::new();
+
+    let a = circuit.add_gate(GateType::Input, 0);
+    let not_gate = circuit.add_gate(GateType::Not, 1);
+    circuit.connect(a, not_gate, 0);
+
+    circuit.evaluate();
+    assert_eq!(circuit.get_output(a), Logic::X);
+    assert_eq!(circuit.get_output(not_gate), Logic::X);
+
+    circuit.set_primary_input_value(a, Logic::One);
+    circuit.evaluate();
+    assert_eq!(circuit.get_output(not_gate), Logic::Zero);
+}
+
+#[test]
+fn test_bus_datapath() {
+    let mut circuit = Circuit::new();
+
+    let a = circuit.add_bus_gate(GateType::Input, 0, 8);
+    let b = circuit.add_bus_gate(GateType::Input, 0, 8);
+    let xor_gate = circuit.add_bus_gate(GateType::Xor, 2, 8);
+    let high = circuit.add_splitter(4, 4);
+    let low = circuit.add_splitter(0, 4);
+    let swapped = circuit.add_merger(2, 4);
+
+    circuit.connect(a, xor_gate, 0);
+    circuit.connect(b, xor_gate, 1);
+    circuit.connect(xor_gate, high, 0);
+    circuit.connect(xor_gate, low, 0);
+    circuit.connect(high, swapped, 0);
+    circuit.connect(low, swapped, 1);
+
+    circuit.set_primary_input_value(a, Bus::from_u64(0xF0, 8));
+    circuit.set_primary_input_value(b, Bus::from_u64(0x3C, 8));
+    circuit.evaluate();
+
+    assert_eq!(circuit.get_output(xor_gate).to_u64(), Some(0xCC));
+    assert_eq!(circuit.get_output(high).to_u64(), Some(0xC));
+    assert_eq!(circuit.get_output(swapped).to_u64(), Some(0xCC));
+
+    circuit.set_primary_input_value(b, Bus::from_u64(0x0F, 8));
+    circuit.evaluate();
+    assert_eq!(circuit.get_output(xor_gate).to_hex(), "FF");
+}
+
+#[test]
+fn test_single_bit_splitter_selects_one_bit() {
+    let mut circuit = Circuit::new();
+
+    let a = circuit.add_bus_gate(GateType::Input, 0, 4);
+    let bit2 = circuit.add_splitter(2, 1);
+    let not_gate = circuit.add_gate(GateType::Not, 1);
+    circuit.connect(a, bit2, 0);
+    circuit.connect(bit2, not_gate, 0);
+
+    circuit.set_primary_input_value(a, Bus::from_u64(0b0100, 4));
+    circuit.evaluate();
+    assert_eq!(circuit.get_output(not_gate), false);
+}
+
+#[test]
+#[should_panic(expected = "Cannot connect 8-bit output of gate 0 to input 0 of gate 1")]
+fn test_connect_rejects_width_mismatch() {
+    let mut circuit = Circuit::new();
+    let a = circuit.add_bus_gate(GateType::Input, 0, 8);
+    let not_gate = circuit.add_gate(GateType::Not, 1);
+    circuit.connect(a, not_gate, 0);
+}
+
+#[test]
+fn test_remove_gate_keeps_other_ids() {
+    let mut circuit = Circuit::new();
+    let a = circuit.add_gate(GateType::Input, 0);
+    let b = circuit.add_gate(GateType::Input, 0);
+    let not = circuit.add_gate(GateType::Not, 1);
+    let and = circuit.add_gate(GateType::And, 2);
+    circuit.connect(a, not, 0);
+    circuit.connect(not, and, 0);
+    circuit.connect(b, and, 1);
+
+    let removed = circuit.remove_gate(not);
+    assert_eq!(removed.gate_type, GateType::Not);
+    assert_eq!(circuit.gate_count(), 3);
+    assert!(!circuit.contains_gate(not));
+    assert_eq!(circuit.gate_ids().collect::<Vec<_>>(), vec![a, b, and]);
+    assert_eq!(circuit.connections(), vec![(b, and, 1)]);
+
+    // Ids of removed gates are not handed out again
+    let or = circuit.add_gate(GateType::Or, 2);
+    assert_eq!(or, 4);
+
+    circuit.set_primary_input_value(b, true);
+    circuit.settle(10).unwrap();
+    assert_eq!(circuit.get_output(and), Logic::X);
+    assert_eq!(circuit.try_get_output(not), Err(CircuitError::InvalidGate(not)));
+}
+
+#[test]
+fn test_disconnect_leaves_input_floating() {
+    let mut circuit = Circuit::new();
+    let a = circuit.add_gate(GateType::Input, 0);
+    let not = circuit.add_gate(GateType::Not, 1);
+    circuit.connect(a, not, 0);
+    circuit.set_primary_input_value(a, false);
+    circuit.settle(10).unwrap();
+    assert_eq!(circuit.get_output(not), Logic::One);
+
+    circuit.disconnect(a, not, 0);
+    assert!(circuit.connections().is_empty());
+    circuit.settle(10).unwrap();
+    assert_eq!(circuit.get_output(not), Logic::X);
+
+    assert_eq!(
+        circuit.try_disconnect(a, not, 0),
+        Err(CircuitError::NotConnected { from: a, output_index: 0, to: not, input_index: 0 })
+    );
+}
+
+#[test]
+fn test_second_driver_is_rejected() {
+    let mut circuit = Circuit::new();
+    let a = circuit.add_gate(GateType::Input, 0);
+    let b = circuit.add_gate(GateType::Input, 0);
+    let not = circuit.add_gate(GateType::Not, 1);
+    circuit.connect(a, not, 0);
+
+    assert_eq!(
+        circuit.try_connect(b, not, 0),
+        Err(CircuitError::InputAlreadyDriven { to: not, input_index: 0, driver: a })
+    );
+    assert_eq!(circuit.connections(), vec![(a, not, 0)]);
+}
+
+#[test]
+fn test_replace_driver() {
+    let mut circuit = Circuit::new();
+    let a = circuit.add_gate(GateType::Input, 0);
+    let b = circuit.add_gate(GateType::Input, 0);
+    let not = circuit.add_gate(GateType::Not, 1);
+    circuit.set_primary_input_value(a, true);
+    circuit.set_primary_input_value(b, false);
+
+    assert_eq!(circuit.replace_driver(a, 0, not, 0), None);
+    let replaced = circuit.replace_driver(b, 0, not, 0).unwrap();
+    assert_eq!((replaced.from, replaced.to), (a, not));
+    assert_eq!(circuit.connections(), vec![(b, not, 0)]);
+    assert_eq!(circuit.driver(not, 0).map(|c| c.from), Some(b));
+
+    circuit.settle(10).unwrap();
+    assert_eq!(circuit.get_output(not), Logic::One);
+}
+
+/// Adds a chain of `length` inverters driven by a new input gate.
+fn add_inverter_chain(circuit: &mut Circuit, length: usize) -> (usize, usize) {
+    let input = circuit.add_gate(GateType::Input, 0);
+    let mut last = input;
+    for _ in 0..length {
+        let not = circuit.add_gate(GateType::Not, 1);
+        circuit.connect(last, not, 0);
+        last = not;
+    }
+    (input, last)
+}
+
+#[test]
+fn test_propagate_only_visits_fanout_cone() {
+    let mut circuit = Circuit::new();
+    let (a, a_out) = add_inverter_chain(&mut circuit, 5);
+    let (b, b_out) = add_inverter_chain(&mut circuit, 50);
+    circuit.set_primary_input_value(a, false);
+    circuit.set_primary_input_value(b, false);
+    circuit.settle(DEFAULT_MAX_PASSES).unwrap();
+
+    circuit.set_primary_input_value(a, true);
+    assert_eq!(circuit.propagate(DEFAULT_MAX_PASSES), Ok(5));
+    assert_eq!(circuit.get_output(a_out), false);
+    assert_eq!(circuit.get_output(b_out), false);
+
+    // Nothing changed since the last propagation
+    assert_eq!(circuit.propagate(DEFAULT_MAX_PASSES), Ok(0));
+    circuit.set_primary_input_value(a, true);
+    assert_eq!(circuit.propagate(DEFAULT_MAX_PASSES), Ok(0));
+}
+
+#[test]
+fn test_propagate_stops_at_unchanged_outputs() {
+    let mut circuit = Circuit::new();
+    let a = circuit.add_gate(GateType::Input, 0);
+    let b = circuit.add_gate(GateType::Input, 0);
+    let and = circuit.add_gate(GateType::And, 2);
+    let not = circuit.add_gate(GateType::Not, 1);
+    circuit.connect(a, and, 0);
+    circuit.connect(b, and, 1);
+    circuit.connect(and, not, 0);
+    circuit.set_primary_input_value(a, false);
+    circuit.set_primary_input_value(b, false);
+    circuit.settle(DEFAULT_MAX_PASSES).unwrap();
+
+    // The AND output stays 0, so the inverter is not evaluated again
+    circuit.set_primary_input_value(b, true);
+    assert_eq!(circuit.propagate(DEFAULT_MAX_PASSES), Ok(1));
+    assert_eq!(circuit.get_output(not), true);
+
+    circuit.set_primary_input_value(a, true);
+    assert_eq!(circuit.propagate(DEFAULT_MAX_PASSES), Ok(2));
+    assert_eq!(circuit.get_output(not), false);
+}
+
+#[test]
+fn test_propagate_matches_settle_with_feedback() {
+    let mut circuit = Circuit::new();
+    let (set, reset, q, q_bar) = build_sr_latch(&mut circuit);
+    circuit.set_primary_input_value(set, false);
+    circuit.set_primary_input_value(reset, true);
+    circuit.settle(DEFAULT_MAX_PASSES).unwrap();
+    assert_eq!(circuit.get_output(q), false);
+
+    circuit.set_primary_input_value(reset, false);
+    circuit.set_primary_input_value(set, true);
+    assert!(circuit.propagate(DEFAULT_MAX_PASSES).is_ok());
+    assert_eq!(circuit.get_output(q), true);
+    assert_eq!(circuit.get_output(q_bar), false);
+
+    circuit.set_primary_input_value(set, false);
+    assert!(circuit.propagate(DEFAULT_MAX_PASSES).is_ok());
+    assert_eq!(circuit.get_output(q), true);
+}
+
+#[test]
+fn test_propagate_reports_oscillation() {
+    let mut circuit = Circuit::new();
+    let enable = circuit.add_gate(GateType::Input, 0);
+    let and = circuit.add_gate(GateType::And, 2);
+    let not = circuit.add_gate(GateType::Not, 1);
+    circuit.connect(enable, and, 0);
+    circuit.connect(not, and, 1);
+    circuit.connect(and, not, 0);
+    circuit.set_primary_input_value(enable, false);
+    circuit.settle(DEFAULT_MAX_PASSES).unwrap();
+
+    circuit.set_primary_input_value(enable, true);
+    let oscillation = circuit.propagate(10).unwrap_err();
+    assert_eq!(oscillation.passes, 10);
+    assert!(!oscillation.unstable.is_empty());
+}
+
+#[test]
+fn test_propagate_after_netlist_edits() {
+    let mut circuit = Circuit::new();
+    let a = circuit.add_gate(GateType::Input, 0);
+    circuit.set_primary_input_value(a, true);
+    let not = circuit.add_gate(GateType::Not, 1);
+    circuit.propagate(DEFAULT_MAX_PASSES).unwrap();
+    assert_eq!(circuit.get_output(not), Logic::X);
+
+    circuit.connect(a, not, 0);
+    circuit.propagate(DEFAULT_MAX_PASSES).unwrap();
+    assert_eq!(circuit.get_output(not), false);
+
+    circuit.disconnect(a, not, 0);
+    circuit.propagate(DEFAULT_MAX_PASSES).unwrap();
+    assert_eq!(circuit.get_output(not), Logic::X);
+}
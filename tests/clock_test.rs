@@ -0,0 +1,112 @@
+use digital_logic_simulator::bus::Bus;
+use digital_logic_simulator::circuit::Circuit;
+use digital_logic_simulator::error::CircuitError;
+use digital_logic_simulator::gate::{ClockTiming, GateType};
+use digital_logic_simulator::logic::Logic;
+
+#[test]
+fn test_clock_timing() {
+    let timing = ClockTiming { period: 4, high: 1 };
+    assert!(timing.is_valid());
+    assert_eq!(timing.duty_cycle(), 0.25);
+    let levels: Vec<Logic> = (0..8).map(|tick| timing.level(tick)).collect();
+    assert_eq!(levels, [0, 0, 0, 1, 0, 0, 0, 1].map(|bit| Logic::from(bit == 1)));
+
+    assert_eq!(ClockTiming::default(), ClockTiming { period: 2, high: 1 });
+    assert!(!ClockTiming { period: 2, high: 2 }.is_valid());
+    assert!(!ClockTiming { period: 1, high: 0 }.is_valid());
+}
+
+#[test]
+fn test_tick_drives_readers() {
+    let mut circuit = Circuit::new();
+    let clock = circuit.add_clock(2, 1);
+    let not = circuit.add_gate(GateType::Not, 1);
+    circuit.connect(clock, not, 0);
+    circuit.propagate(10).unwrap();
+    assert_eq!(circuit.ticks(), 0);
+    assert_eq!(circuit.get_output(clock), false);
+    assert_eq!(circuit.get_output(not), true);
+
+    circuit.tick(1).unwrap();
+    assert_eq!(circuit.ticks(), 1);
+    assert_eq!(circuit.get_output(clock), true);
+    assert_eq!(circuit.get_output(not), false);
+
+    circuit.tick(3).unwrap();
+    assert_eq!(circuit.ticks(), 4);
+    assert_eq!(circuit.get_output(not), true);
+}
+
+#[test]
+fn test_run_cycles_samples_each_period() {
+    let mut circuit = Circuit::new();
+    let fast = circuit.add_clock(2, 1);
+    let slow = circuit.add_clock(4, 2);
+    let or = circuit.add_gate(GateType::Or, 2);
+    circuit.connect(fast, or, 0);
+    circuit.connect(slow, or, 1);
+
+    // The fast clock is low at the end of each of its periods
+    let samples = circuit.run_cycles(fast, 4, &[slow, or]).unwrap();
+    let expected: Vec<Vec<Bus>> = [1, 0, 1, 0].iter().map(|&bit| vec![Bus::from(bit == 1); 2]).collect();
+    assert_eq!(samples, expected);
+    assert_eq!(circuit.ticks(), 8);
+}
+
+#[test]
+fn test_clock_errors() {
+    let mut circuit = Circuit::new();
+    let invalid = ClockTiming { period: 3, high: 0 };
+    assert_eq!(
+        circuit.try_add_gate(GateType::Clock(invalid), 0),
+        Err(CircuitError::InvalidClockTiming { period: 3, high: 0 })
+    );
+    assert!(circuit.try_add_gate(GateType::Clock(ClockTiming::default()), 1).is_err());
+
+    let input = circuit.add_gate(GateType::Input, 0);
+    assert_eq!(
+        circuit.try_set_clock_timing(input, ClockTiming::default()),
+        Err(CircuitError::NotAClock(input))
+    );
+    let clock = circuit.add_clock(2, 1);
+    assert_eq!(
+        circuit.try_set_clock_timing(clock, invalid),
+        Err(CircuitError::InvalidClockTiming { period: 3, high: 0 })
+    );
+
+    // A clock that is already high changes level with its new timing
+    circuit.tick(1).unwrap();
+    circuit.set_clock_timing(clock, ClockTiming { period: 4, high: 1 });
+    assert_eq!(circuit.gate(clock).gate_type, GateType::Clock(ClockTiming { period: 4, high: 1 }));
+    assert_eq!(circuit.get_output(clock), false);
+}
+
+#[test]
+#[should_panic(expected = "Gate 0 is not a clock")]
+fn test_run_cycles_requires_a_clock() {
+    let mut circuit = Circuit::new();
+    let input = circuit.add_gate(GateType::Input, 0);
+    let _ = circuit.run_cycles(input, 1, &[]);
+}
+
+#[test]
+fn test_try_run_cycles_reports_invalid_gates() {
+    let mut circuit = Circuit::new();
+    let input = circuit.add_gate(GateType::Input, 0);
+    let clock = circuit.add_clock(2, 1);
+    assert_eq!(circuit.try_run_cycles(input, 1, &[]), Err(CircuitError::NotAClock(input)));
+    assert_eq!(circuit.try_run_cycles(7, 1, &[]), Err(CircuitError::InvalidGate(7)));
+    assert_eq!(circuit.try_run_cycles(clock, 1, &[input, 7]), Err(CircuitError::InvalidGate(7)));
+    // Nothing ran before the outputs were checked
+    assert_eq!(circuit.ticks(), 0);
+    assert_eq!(circuit.try_run_cycles(clock, 2, &[clock]), Ok(Ok(vec![vec![Bus::from(false)]; 2])));
+}
+
+#[test]
+fn test_clock_timing_survives_round_trip() {
+    let mut circuit = Circuit::new();
+    let clock = circuit.add_clock(5, 2);
+    let loaded = Circuit::from_json(&circuit.to_json().unwrap()).unwrap();
+    assert_eq!(loaded.gate(clock).gate_type, GateType::Clock(ClockTiming { period: 5, high: 2 }));
+}
@@ -0,0 +1,110 @@
+use digital_logic_simulator::bus::Bus;
+use digital_logic_simulator::circuit::Circuit;
+use digital_logic_simulator::cnf::{Cnf, CnfError, Origin};
+use digital_logic_simulator::expression::Expr;
+use digital_logic_simulator::gate::GateType;
+
+/// Builds the expression as a circuit whose result is the gate named `f`.
+fn circuit_for(text: &str) -> Circuit {
+    let (mut circuit, output) = text.parse::<Expr>().unwrap().to_circuit();
+    circuit.set_name(output, "f");
+    circuit
+}
+
+/// Returns every satisfying assignment, as the value of each variable from 1.
+fn models(cnf: &Cnf) -> Vec<Vec<bool>> {
+    let n = cnf.variable_count();
+    (0..1u64 << n)
+        .map(|bits| (0..n).map(|v| (bits >> v) & 1 == 1).collect::<Vec<bool>>())
+        .filter(|assignment| {
+            cnf.clauses.iter().all(|clause| {
+                clause.iter().any(|&literal| assignment[literal.unsigned_abs() as usize - 1] == (literal > 0))
+            })
+        })
+        .collect()
+}
+
+#[test]
+fn test_models_match_simulation() {
+    let mut circuit = Circuit::new();
+    let a = circuit.add_gate(GateType::Input, 0);
+    let b = circuit.add_gate(GateType::Input, 0);
+    let c = circuit.add_gate(GateType::Input, 0);
+    let sum = circuit.add_gate(GateType::Xor, 3);
+    let carry = circuit.add_gate(GateType::And, 2);
+    let any = circuit.add_gate(GateType::Or, 2);
+    let not = circuit.add_gate(GateType::Not, 1);
+    for (index, input) in [a, b, c].into_iter().enumerate() {
+        circuit.connect(input, sum, index);
+    }
+    circuit.connect(a, carry, 0);
+    circuit.connect(b, carry, 1);
+    circuit.connect(a, any, 0);
+    circuit.connect(c, any, 1);
+    circuit.connect(sum, not, 0);
+
+    let cnf = circuit.to_cnf().unwrap();
+    let models = models(&cnf);
+    // Exactly one consistent state per input combination
+    assert_eq!(models.len(), 8);
+    let variable = |gate| cnf.variable(0, gate, 0).unwrap() as usize - 1;
+    for model in models {
+        for input in [a, b, c] {
+            circuit.set_primary_input_value(input, model[variable(input)]);
+        }
+        circuit.evaluate();
+        for gate in [sum, carry, any, not] {
+            assert_eq!(circuit.get_output(gate), Bus::from(model[variable(gate)]), "gate {}", gate);
+        }
+    }
+}
+
+#[test]
+fn test_miter_of_equivalent_circuits_is_unsatisfiable() {
+    let left = circuit_for("a & (b | c)");
+    let right = circuit_for("c & a | a & b");
+    assert!(models(&Cnf::miter(&left, &right).unwrap()).is_empty());
+}
+
+#[test]
+fn test_miter_of_different_circuits_gives_counterexample() {
+    let left = circuit_for("a ^ b");
+    let right = circuit_for("a | b");
+    let cnf = Cnf::miter(&left, &right).unwrap();
+    let a = left.find_gate("a").unwrap();
+    let b = left.find_gate("b").unwrap();
+
+    let counterexamples: Vec<(bool, bool)> = models(&cnf)
+        .iter()
+        .map(|model| {
+            let value = |gate| model[cnf.variable(0, gate, 0).unwrap() as usize - 1];
+            (value(a), value(b))
+        })
+        .collect();
+    assert_eq!(counterexamples, vec![(true, true)]);
+
+    // The inputs of the right circuit share the variables of the left one
+    let right_a = right.find_gate("a").unwrap();
+    assert_eq!(cnf.variable(1, right_a, 0), cnf.variable(0, a, 0));
+}
+
+#[test]
+fn test_dimacs_and_mapping() {
+    let mut circuit = Circuit::new();
+    let a = circuit.add_gate(GateType::Input, 0);
+    let not = circuit.add_gate(GateType::Not, 1);
+    circuit.connect(a, not, 0);
+    circuit.set_name(a, "a");
+
+    let cnf = circuit.to_cnf().unwrap();
+    assert_eq!(cnf.to_dimacs(), "p cnf 2 2\n-2 -1 0\n2 1 0\n");
+    assert_eq!(cnf.to_mapping(), format!("c variable circuit gate bit name\n1 0 {} 0 a\n2 0 {} 0\n", a, not));
+    assert_eq!(cnf.origins[1], Origin::Gate { circuit: 0, gate: not, bit: 0, name: None });
+}
+
+#[test]
+fn test_floating_input() {
+    let mut circuit = Circuit::new();
+    let and = circuit.add_gate(GateType::And, 2);
+    assert_eq!(circuit.to_cnf(), Err(CnfError::Floating { gate: and, input_index: 0 }));
+}
@@ -0,0 +1,101 @@
+use digital_logic_simulator::bus::Bus;
+use digital_logic_simulator::circuit::Circuit;
+use digital_logic_simulator::equivalence::{Equivalence, EquivalenceError, OutputMismatch};
+use digital_logic_simulator::expression::Expr;
+use digital_logic_simulator::gate::GateType;
+
+/// Builds the expression as a circuit whose result is the gate named `f`.
+fn circuit_for(text: &str) -> Circuit {
+    let (mut circuit, output) = text.parse::<Expr>().unwrap().to_circuit();
+    circuit.set_name(output, "f");
+    circuit
+}
+
+#[test]
+fn test_equivalent_circuits() {
+    let mut factored = circuit_for("a & (b | c)");
+    let mut expanded = circuit_for("c & a | a & b");
+    assert_eq!(factored.check_equivalence(&mut expanded), Ok(Equivalence::Equivalent));
+
+    let mut de_morgan = circuit_for("!(!a | !(b | c))");
+    assert_eq!(factored.check_equivalence(&mut de_morgan), Ok(Equivalence::Equivalent));
+}
+
+#[test]
+fn test_counterexample() {
+    let mut left = circuit_for("a & b | c");
+    let mut right = circuit_for("a & (b | c)");
+    let Ok(Equivalence::Different(counterexample)) = left.check_equivalence(&mut right) else {
+        panic!("circuits differ when a is 0 and c is 1");
+    };
+
+    assert_eq!(
+        counterexample.inputs,
+        vec![
+            ("a".to_string(), Bus::from(false)),
+            ("b".to_string(), Bus::from(false)),
+            ("c".to_string(), Bus::from(true)),
+        ]
+    );
+    assert_eq!(
+        counterexample.outputs,
+        vec![OutputMismatch { name: "f".to_string(), left: Bus::from(true), right: Bus::from(false) }]
+    );
+    assert_eq!(counterexample.to_string(), "for a = 0, b = 0, c = 1: f is 1 and 0");
+}
+
+#[test]
+fn test_bus_circuits_and_many_vectors() {
+    // Two 5-bit inputs give 1024 combinations, spread over several packed passes
+    let build = |swap: bool| {
+        let mut circuit = Circuit::new();
+        let x = circuit.add_bus_gate(GateType::Input, 0, 5);
+        let y = circuit.add_bus_gate(GateType::Input, 0, 5);
+        let and = circuit.add_bus_gate(GateType::And, 2, 5);
+        circuit.connect(if swap { y } else { x }, and, 0);
+        circuit.connect(if swap { x } else { y }, and, 1);
+        circuit.set_name(x, "x");
+        circuit.set_name(y, "y");
+        circuit.set_name(and, "z");
+        circuit
+    };
+    assert_eq!(build(false).check_equivalence(&mut build(true)), Ok(Equivalence::Equivalent));
+
+    let mut broken = build(false);
+    let y = broken.find_gate("y").unwrap();
+    let and = broken.find_gate("z").unwrap();
+    let not = broken.add_bus_gate(GateType::Not, 1, 5);
+    broken.connect(y, not, 0);
+    broken.replace_driver(not, 0, and, 1);
+    assert!(matches!(build(false).check_equivalence(&mut broken), Ok(Equivalence::Different(_))));
+}
+
+#[test]
+fn test_interfaces_must_match() {
+    let mut left = circuit_for("a & b");
+    assert_eq!(
+        left.check_equivalence(&mut circuit_for("a & c")),
+        Err(EquivalenceError::InputMismatch("b".to_string()))
+    );
+
+    let mut renamed = circuit_for("a & b");
+    renamed.set_name(2, "g");
+    assert_eq!(
+        left.check_equivalence(&mut renamed),
+        Err(EquivalenceError::OutputMismatch("f".to_string()))
+    );
+
+    let mut unnamed = circuit_for("a & b");
+    let extra = unnamed.add_gate(GateType::Input, 0);
+    assert_eq!(left.check_equivalence(&mut unnamed), Err(EquivalenceError::UnnamedInput(extra)));
+
+    let mut wide = Circuit::new();
+    let a = wide.add_bus_gate(GateType::Input, 0, 2);
+    let b = wide.add_gate(GateType::Input, 0);
+    wide.set_name(a, "a");
+    wide.set_name(b, "b");
+    assert_eq!(
+        left.check_equivalence(&mut wide),
+        Err(EquivalenceError::WidthMismatch { name: "a".to_string(), left: 1, right: 2 })
+    );
+}
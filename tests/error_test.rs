@@ -0,0 +1,144 @@
+use digital_logic_simulator::bus::Bus;
+use digital_logic_simulator::circuit::Circuit;
+use digital_logic_simulator::error::CircuitError;
+use digital_logic_simulator::gate::GateType;
+use digital_logic_simulator::subcircuit::Subcircuit;
+use std::collections::HashMap;
+
+#[test]
+fn test_unknown_gate_ids_are_reported() {
+    let mut circuit = Circuit::new();
+    circuit.add_gate(GateType::Input, 0);
+
+    assert_eq!(circuit.try_get_output(3), Err(CircuitError::InvalidGate(3)));
+    assert_eq!(circuit.try_gate(1).err(), Some(CircuitError::InvalidGate(1)));
+    assert_eq!(circuit.try_set_delay(5, 2), Err(CircuitError::InvalidGate(5)));
+    assert_eq!(circuit.try_set_primary_input_value(2, true), Err(CircuitError::InvalidGate(2)));
+    assert_eq!(
+        circuit.try_evaluate_gate(4, &mut HashMap::new()),
+        Err(CircuitError::InvalidGate(4))
+    );
+    assert_eq!(circuit.try_connect(0, 7, 0), Err(CircuitError::InvalidGate(7)));
+    assert_eq!(circuit.try_add_subcircuit(0), Err(CircuitError::InvalidSubcircuit(0)));
+}
+
+#[test]
+fn test_invalid_input_counts_are_rejected() {
+    let mut circuit = Circuit::new();
+    assert_eq!(
+        circuit.try_add_gate(GateType::Not, 2),
+        Err(CircuitError::InvalidInputCount { gate_type: GateType::Not, input_count: 2 })
+    );
+    assert_eq!(
+        circuit.try_add_gate(GateType::Input, 1),
+        Err(CircuitError::InvalidInputCount { gate_type: GateType::Input, input_count: 1 })
+    );
+    assert_eq!(
+        circuit.try_add_gate(GateType::And, 0),
+        Err(CircuitError::InvalidInputCount { gate_type: GateType::And, input_count: 0 })
+    );
+    assert_eq!(circuit.gate_count(), 0);
+    assert_eq!(circuit.try_add_gate(GateType::And, 3), Ok(0));
+}
+
+#[test]
+fn test_connect_validates_ports() {
+    let mut circuit = Circuit::new();
+    let a = circuit.add_gate(GateType::Input, 0);
+    let not = circuit.add_gate(GateType::Not, 1);
+    let wide = circuit.add_bus_gate(GateType::Input, 0, 4);
+
+    assert_eq!(
+        circuit.try_connect(a, not, 1),
+        Err(CircuitError::InvalidInputIndex { gate: not, input_index: 1, input_count: 1 })
+    );
+    assert_eq!(
+        circuit.try_connect(not, a, 0),
+        Err(CircuitError::InvalidInputIndex { gate: a, input_index: 0, input_count: 0 })
+    );
+    assert_eq!(
+        circuit.try_connect_port(a, 1, not, 0),
+        Err(CircuitError::InvalidOutputIndex { gate: a, output_index: 1, output_count: 1 })
+    );
+    assert_eq!(
+        circuit.try_connect(wide, not, 0),
+        Err(CircuitError::ConnectionWidthMismatch { from: wide, output_index: 0, width: 4, to: not, input_index: 0 })
+    );
+    assert!(circuit.connections().is_empty());
+
+    assert_eq!(circuit.try_connect(a, not, 0), Ok(()));
+    assert_eq!(circuit.connections(), vec![(a, not, 0)]);
+}
+
+#[test]
+fn test_input_values_are_validated() {
+    let mut circuit = Circuit::new();
+    let a = circuit.add_bus_gate(GateType::Input, 0, 4);
+    let not = circuit.add_bus_gate(GateType::Not, 1, 4);
+
+    assert_eq!(circuit.try_set_primary_input_value(not, Bus::from_u64(1, 4)), Err(CircuitError::NotAnInput(not)));
+    assert_eq!(
+        circuit.try_set_primary_input_value(a, true),
+        Err(CircuitError::InputWidthMismatch { gate: a, expected: 4, actual: 1 })
+    );
+    assert_eq!(circuit.try_set_primary_input_value(a, Bus::from_u64(9, 4)), Ok(()));
+    assert_eq!(circuit.try_get_output(a), Ok(Bus::from_u64(9, 4)));
+}
+
+#[test]
+fn test_schedule_input_validation() {
+    let mut circuit = Circuit::new();
+    let a = circuit.add_gate(GateType::Input, 0);
+    circuit.run_until(10);
+
+    assert_eq!(circuit.try_schedule_input(a, true, 5), Err(CircuitError::EventInPast { time: 5, now: 10 }));
+    assert_eq!(
+        circuit.try_schedule_input(a, Bus::from_u64(0, 2), 12),
+        Err(CircuitError::InputWidthMismatch { gate: a, expected: 1, actual: 2 })
+    );
+    assert_eq!(circuit.try_schedule_input(a, true, 12), Ok(()));
+    assert!(circuit.has_pending_events());
+}
+
+#[test]
+fn test_subcircuit_gate_type_checks_port_count() {
+    let mut inner = Circuit::new();
+    let x = inner.add_gate(GateType::Input, 0);
+    let y = inner.add_gate(GateType::Not, 1);
+    inner.connect(x, y, 0);
+    let mut def = Subcircuit::new("inverter", inner);
+    def.add_input("x", x);
+    def.add_output("y", y);
+
+    let mut circuit = Circuit::new();
+    let id = circuit.define_subcircuit(def);
+    assert_eq!(
+        circuit.try_add_gate(GateType::Subcircuit(id), 2),
+        Err(CircuitError::InvalidInputCount { gate_type: GateType::Subcircuit(id), input_count: 2 })
+    );
+    let instance = circuit.try_add_gate(GateType::Subcircuit(id), 1).unwrap();
+    assert_eq!(circuit.gate(instance).gate_type, GateType::Subcircuit(id));
+    assert_eq!(circuit.validate(), Ok(()));
+}
+
+#[test]
+fn test_error_messages() {
+    assert_eq!(CircuitError::NotAnInput(3).to_string(), "Gate 3 is not an input gate");
+    assert_eq!(
+        CircuitError::InvalidInputIndex { gate: 1, input_index: 2, input_count: 2 }.to_string(),
+        "Gate 1 has no input 2 (it has 2 inputs)"
+    );
+    assert_eq!(
+        CircuitError::ConnectionWidthMismatch { from: 0, output_index: 1, width: 8, to: 2, input_index: 0 }.to_string(),
+        "Cannot connect 8-bit output 1 of gate 0 to input 0 of gate 2"
+    );
+}
+
+#[test]
+#[should_panic(expected = "Gate 1 has no input 2 (it has 2 inputs)")]
+fn test_connect_panics_on_invalid_input_index() {
+    let mut circuit = Circuit::new();
+    let a = circuit.add_gate(GateType::Input, 0);
+    let and = circuit.add_gate(GateType::And, 2);
+    circuit.connect(a, and, 2);
+}
@@ -0,0 +1,122 @@
+use digital_logic_simulator::bus::Bus;
+use digital_logic_simulator::circuit::Circuit;
+use digital_logic_simulator::event::{EventQueue, Transition};
+use digital_logic_simulator::gate::GateType;
+
+#[test]
+fn test_event_queue_orders_by_time_then_schedule_order() {
+    let mut queue = EventQueue::new();
+    queue.push(5, 0, 0, Bus::from(true));
+    queue.push(2, 1, 0, Bus::from(true));
+    queue.push(2, 2, 0, Bus::from(false));
+
+    assert_eq!(queue.next_time(), Some(2));
+    assert_eq!(queue.pop_at(5), None);
+    assert_eq!(queue.pop_at(2).map(|e| e.gate), Some(1));
+    assert_eq!(queue.pop_at(2).map(|e| e.gate), Some(2));
+    assert_eq!(queue.pop_at(2), None);
+    assert_eq!(queue.len(), 1);
+}
+
+#[test]
+fn test_delays_add_up_along_a_path() {
+    let mut circuit = Circuit::new();
+
+    let a = circuit.add_gate(GateType::Input, 0);
+    let not1 = circuit.add_gate(GateType::Not, 1);
+    let not2 = circuit.add_gate(GateType::Not, 1);
+    circuit.connect(a, not1, 0);
+    circuit.connect(not1, not2, 0);
+    circuit.set_delay(not1, 3);
+    circuit.set_delay(not2, 4);
+    circuit.set_primary_input_value(a, false);
+    circuit.evaluate();
+
+    circuit.schedule_input(a, true, 1);
+    circuit.run_until(7);
+    assert_eq!(circuit.now(), 7);
+    assert_eq!(circuit.get_output(not1), false);
+    assert_eq!(circuit.get_output(not2), false);
+
+    circuit.run_until(8);
+    assert_eq!(circuit.get_output(not2), true);
+    assert!(!circuit.has_pending_events());
+}
+
+#[test]
+fn test_step_processes_one_time_point() {
+    let mut circuit = Circuit::new();
+
+    let a = circuit.add_gate(GateType::Input, 0);
+    let not1 = circuit.add_gate(GateType::Not, 1);
+    circuit.connect(a, not1, 0);
+    circuit.set_delay(not1, 2);
+    circuit.set_primary_input_value(a, false);
+    circuit.evaluate();
+
+    circuit.schedule_input(a, true, 0);
+    assert_eq!(circuit.step(), Some(0));
+    assert_eq!(circuit.get_output(not1), true);
+    assert_eq!(circuit.step(), Some(2));
+    assert_eq!(circuit.get_output(not1), false);
+    assert_eq!(circuit.step(), None);
+}
+
+#[test]
+fn test_unequal_path_delays_produce_glitch() {
+    let mut circuit = Circuit::new();
+
+    // a AND NOT a is always false in zero-delay evaluation
+    let a = circuit.add_gate(GateType::Input, 0);
+    let not1 = circuit.add_gate(GateType::Not, 1);
+    let and1 = circuit.add_gate(GateType::And, 2);
+    circuit.connect(a, not1, 0);
+    circuit.connect(a, and1, 0);
+    circuit.connect(not1, and1, 1);
+    circuit.set_delay(not1, 2);
+    circuit.set_delay(and1, 1);
+    circuit.set_primary_input_value(a, false);
+    circuit.evaluate();
+
+    circuit.schedule_input(a, true, 10);
+    circuit.run_until(20);
+
+    let and_history: Vec<Transition> = circuit
+        .history()
+        .iter()
+        .filter(|t| t.gate == and1)
+        .cloned()
+        .collect();
+    assert_eq!(
+        and_history,
+        vec![
+            Transition { time: 11, gate: and1, output_index: 0, value: Bus::from(true) },
+            Transition { time: 13, gate: and1, output_index: 0, value: Bus::from(false) },
+        ]
+    );
+    assert_eq!(circuit.get_output(and1), false);
+}
+
+#[test]
+#[should_panic(expected = "Gate 1 is not an input gate")]
+fn test_schedule_input_on_non_input_gate_panics() {
+    let mut circuit = Circuit::new();
+    circuit.add_gate(GateType::Input, 0);
+    let not1 = circuit.add_gate(GateType::Not, 1);
+    circuit.schedule_input(not1, true, 0);
+}
+
+#[test]
+fn test_removing_gate_drops_its_events() {
+    let mut circuit = Circuit::new();
+    let a = circuit.add_gate(GateType::Input, 0);
+    let b = circuit.add_gate(GateType::Input, 0);
+    circuit.schedule_input(a, true, 5);
+    circuit.schedule_input(b, true, 8);
+
+    circuit.remove_gate(a);
+    assert_eq!(circuit.step(), Some(8));
+    assert_eq!(circuit.history().len(), 1);
+    assert_eq!(circuit.history()[0].gate, b);
+    assert!(!circuit.has_pending_events());
+}
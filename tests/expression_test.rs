@@ -0,0 +1,103 @@
+use digital_logic_simulator::circuit::Circuit;
+use digital_logic_simulator::error::CircuitError;
+use digital_logic_simulator::expression::{Expr, ExpressionError, ParseError};
+use digital_logic_simulator::gate::GateType;
+use digital_logic_simulator::logic::Logic;
+use std::collections::HashMap;
+
+fn var(name: &str) -> Expr {
+    Expr::Var(name.to_string())
+}
+
+#[test]
+fn test_parse_precedence() {
+    let expr: Expr = "(a & b) | !c ^ d".parse().unwrap();
+    assert_eq!(
+        expr,
+        Expr::Or(vec![
+            Expr::And(vec![var("a"), var("b")]),
+            Expr::Xor(vec![Expr::Not(Box::new(var("c"))), var("d")]),
+        ])
+    );
+    assert_eq!(expr.to_string(), "a & b | !c ^ d");
+    assert_eq!(expr.variables(), vec!["a", "b", "c", "d"]);
+
+    let chain: Expr = "x1 & ~(x2|_y) & x1".parse().unwrap();
+    assert_eq!(chain.to_string(), "x1 & !(x2 | _y) & x1");
+    assert_eq!(chain.to_string().parse::<Expr>().unwrap(), chain);
+}
+
+#[test]
+fn test_parse_errors() {
+    assert_eq!("a &".parse::<Expr>(), Err(ParseError::UnexpectedEnd));
+    assert_eq!("(a | b".parse::<Expr>(), Err(ParseError::UnexpectedEnd));
+    assert_eq!(
+        "a b".parse::<Expr>(),
+        Err(ParseError::Expected { position: 2, expected: "an operator" })
+    );
+    assert_eq!(
+        "a & | b".parse::<Expr>(),
+        Err(ParseError::Expected { position: 4, expected: "an operand" })
+    );
+    assert_eq!(
+        "a + b".parse::<Expr>(),
+        Err(ParseError::UnexpectedCharacter { position: 2, character: '+' })
+    );
+}
+
+#[test]
+fn test_circuit_matches_expression() {
+    let expr: Expr = "(a & b) | !c ^ d".parse().unwrap();
+    let (mut circuit, output) = expr.to_circuit();
+
+    let inputs: Vec<_> = ["a", "b", "c", "d"].iter().map(|name| circuit.find_gate(name).unwrap()).collect();
+    assert_eq!(inputs, vec![0, 1, 2, 3]);
+    let table = circuit.truth_table(&[output]).unwrap();
+    assert_eq!(table.input_labels, vec!["a", "b", "c", "d"]);
+    for row in &table.rows {
+        let values: HashMap<String, Logic> = table
+            .input_labels
+            .iter()
+            .zip(&row.inputs)
+            .map(|(name, value)| (name.clone(), value.bit(0)))
+            .collect();
+        assert_eq!(row.outputs[0].bit(0), expr.evaluate(&values));
+    }
+}
+
+#[test]
+fn test_shared_inputs_are_reused() {
+    let mut circuit = Circuit::new();
+    let first = "a & b".parse::<Expr>().unwrap().add_to(&mut circuit);
+    let second = "b | c".parse::<Expr>().unwrap().add_to(&mut circuit);
+
+    assert_eq!(circuit.find_gate("b"), Some(1));
+    assert_eq!(circuit.gate_count(), 5);
+    assert_eq!(circuit.expression(first).unwrap().to_string(), "a & b");
+    assert_eq!(circuit.expression(second).unwrap().to_string(), "b | c");
+}
+
+#[test]
+fn test_expression_from_hand_built_gates() {
+    let mut circuit = Circuit::new();
+    let a = circuit.add_gate(GateType::Input, 0);
+    let b = circuit.add_gate(GateType::Input, 0);
+    circuit.set_name(a, "a");
+    let xor = circuit.add_gate(GateType::Xor, 2);
+    let not = circuit.add_gate(GateType::Not, 1);
+    circuit.connect(a, xor, 0);
+    circuit.connect(b, xor, 1);
+    circuit.connect(xor, not, 0);
+
+    assert_eq!(circuit.expression(not).unwrap().to_string(), "!(a ^ in1)");
+    assert_eq!(circuit.expression(9), Err(ExpressionError::Circuit(CircuitError::InvalidGate(9))));
+
+    let or = circuit.add_gate(GateType::Or, 2);
+    circuit.connect(not, or, 0);
+    assert_eq!(circuit.expression(or), Err(ExpressionError::Floating { gate: or, input_index: 1 }));
+    circuit.connect(or, or, 1);
+    assert_eq!(circuit.expression(or), Err(ExpressionError::Feedback(or)));
+
+    let bus = circuit.add_bus_gate(GateType::Input, 0, 4);
+    assert_eq!(circuit.expression(bus), Err(ExpressionError::Unsupported(bus)));
+}
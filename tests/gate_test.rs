@@ -1,60 +1,118 @@
+use digital_logic_simulator::bus::Bus;
 use digital_logic_simulator::gate::{Gate, GateType};
+use digital_logic_simulator::logic::Logic::{One, X, Z, Zero};
 
 #[test]
 fn test_input_gate_returns_stored_output() {
     let gate = Gate {
         gate_type: GateType::Input,
         input_count: 0,
-        output: true,
+        width: 1,
+        offset: 0,
+        outputs: vec![Bus::from(One)],
+        delay: 0,
+        name: None,
     };
 
-    assert_eq!(gate.evaluate_with_inputs(&[]), true);
+    assert_eq!(gate.evaluate_with_inputs(&[]), One);
 
     let gate = Gate {
         gate_type: GateType::Input,
         input_count: 0,
-        output: false,
+        width: 1,
+        offset: 0,
+        outputs: vec![Bus::from(Zero)],
+        delay: 0,
+        name: None,
     };
 
-    assert_eq!(gate.evaluate_with_inputs(&[]), false);
+    assert_eq!(gate.evaluate_with_inputs(&[]), Zero);
 }
 
 #[test]
 fn test_and_gate() {
     let gate = Gate::new(GateType::And, 2);
-    assert_eq!(gate.evaluate_with_inputs(&[true, true]), true);
-    assert_eq!(gate.evaluate_with_inputs(&[true, false]), false);
-    assert_eq!(gate.evaluate_with_inputs(&[false, false]), false);
+    assert_eq!(gate.evaluate_with_inputs(&[One, One]), One);
+    assert_eq!(gate.evaluate_with_inputs(&[One, Zero]), Zero);
+    assert_eq!(gate.evaluate_with_inputs(&[Zero, Zero]), Zero);
 }
 
 #[test]
 fn test_or_gate() {
     let gate = Gate::new(GateType::Or, 2);
-    assert_eq!(gate.evaluate_with_inputs(&[false, false]), false);
-    assert_eq!(gate.evaluate_with_inputs(&[true, false]), true);
-    assert_eq!(gate.evaluate_with_inputs(&[true, true]), true);
+    assert_eq!(gate.evaluate_with_inputs(&[Zero, Zero]), Zero);
+    assert_eq!(gate.evaluate_with_inputs(&[One, Zero]), One);
+    assert_eq!(gate.evaluate_with_inputs(&[One, One]), One);
 }
 
 #[test]
 fn test_not_gate() {
     let gate = Gate::new(GateType::Not, 1);
-    assert_eq!(gate.evaluate_with_inputs(&[true]), false);
-    assert_eq!(gate.evaluate_with_inputs(&[false]), true);
+    assert_eq!(gate.evaluate_with_inputs(&[One]), Zero);
+    assert_eq!(gate.evaluate_with_inputs(&[Zero]), One);
 }
 
 #[test]
 fn test_not_gate_with_invalid_input_length() {
     let gate = Gate::new(GateType::Not, 1);
-    assert_eq!(gate.evaluate_with_inputs(&[]), false);            // invalid
-    assert_eq!(gate.evaluate_with_inputs(&[true, false]), false); // invalid
+    assert_eq!(gate.evaluate_with_inputs(&[]), X);          // invalid
+    assert_eq!(gate.evaluate_with_inputs(&[One, Zero]), X); // invalid
 }
 
 #[test]
 fn test_xor_gate() {
     let gate = Gate::new(GateType::Xor, 2);
-    assert_eq!(gate.evaluate_with_inputs(&[false, false]), false);
-    assert_eq!(gate.evaluate_with_inputs(&[true, false]), true);
-    assert_eq!(gate.evaluate_with_inputs(&[true, true]), false);
-    assert_eq!(gate.evaluate_with_inputs(&[true, false, true]), false); // 2 true
-    assert_eq!(gate.evaluate_with_inputs(&[true, true, true]), true);   // 3 true
+    assert_eq!(gate.evaluate_with_inputs(&[Zero, Zero]), Zero);
+    assert_eq!(gate.evaluate_with_inputs(&[One, Zero]), One);
+    assert_eq!(gate.evaluate_with_inputs(&[One, One]), Zero);
+    assert_eq!(gate.evaluate_with_inputs(&[One, Zero, One]), Zero); // 2 One
+    assert_eq!(gate.evaluate_with_inputs(&[One, One, One]), One);   // 3 One
+}
+
+#[test]
+fn test_unknown_inputs_propagate() {
+    let and_gate = Gate::new(GateType::And, 2);
+    assert_eq!(and_gate.evaluate_with_inputs(&[One, X]), X);
+    assert_eq!(and_gate.evaluate_with_inputs(&[Zero, X]), Zero);
+    assert_eq!(and_gate.evaluate_with_inputs(&[One, Z]), X);
+
+    let or_gate = Gate::new(GateType::Or, 2);
+    assert_eq!(or_gate.evaluate_with_inputs(&[Zero, Z]), X);
+    assert_eq!(or_gate.evaluate_with_inputs(&[One, X]), One);
+
+    let not_gate = Gate::new(GateType::Not, 1);
+    assert_eq!(not_gate.evaluate_with_inputs(&[Z]), X);
+
+    let xor_gate = Gate::new(GateType::Xor, 2);
+    assert_eq!(xor_gate.evaluate_with_inputs(&[One, X]), X);
+}
+
+#[test]
+fn test_new_gate_output_is_unknown() {
+    let gate = Gate::new(GateType::And, 2);
+    assert_eq!(*gate.output(), X);
+}
+
+#[test]
+fn test_bitwise_gate_on_buses() {
+    let gate = Gate::with_width(GateType::And, 2, 4);
+    let output = gate.evaluate_bus(&[Bus::from_u64(0b1100, 4), Bus::from_u64(0b1010, 4)]);
+    assert_eq!(output, Bus::from_u64(0b1000, 4));
+
+    let gate = Gate::with_width(GateType::Not, 1, 4);
+    assert_eq!(gate.evaluate_bus(&[Bus::from_u64(0b0101, 4)]), Bus::from_u64(0b1010, 4));
+}
+
+#[test]
+fn test_splitter_and_merger() {
+    let mut splitter = Gate::with_width(GateType::Splitter, 1, 4);
+    splitter.offset = 4;
+    assert_eq!(splitter.evaluate_bus(&[Bus::from_u64(0xA5, 8)]), Bus::from_u64(0xA, 4));
+    assert!(splitter.accepts_input_width(8));
+    assert!(!splitter.accepts_input_width(6));
+
+    let merger = Gate::with_width(GateType::Merger, 2, 8);
+    assert_eq!(merger.input_width(), Some(4));
+    let output = merger.evaluate_bus(&[Bus::from_u64(0x5, 4), Bus::from_u64(0xA, 4)]);
+    assert_eq!(output, Bus::from_u64(0xA5, 8));
 }
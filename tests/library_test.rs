@@ -0,0 +1,187 @@
+use digital_logic_simulator::bus::Bus;
+use digital_logic_simulator::circuit::{Circuit, DEFAULT_MAX_PASSES};
+use digital_logic_simulator::connection::GateId;
+use digital_logic_simulator::error::CircuitError;
+use digital_logic_simulator::gate::GateType;
+use digital_logic_simulator::library::Component;
+use digital_logic_simulator::subcircuit::Subcircuit;
+
+/// A circuit with one instance of `component`, an input gate on each of its
+/// inputs and a buffer reading each of its outputs.
+struct Harness {
+    circuit: Circuit,
+    instance: GateId,
+    inputs: Vec<GateId>,
+    outputs: Vec<GateId>,
+}
+
+impl Harness {
+    fn new(component: Component) -> Self {
+        let mut circuit = Circuit::new();
+        let instance = circuit.add_component(component);
+        let GateType::Subcircuit(id) = circuit.gate(instance).gate_type else {
+            panic!("Components are subcircuit instances");
+        };
+        let definition = circuit.subcircuit(id).clone();
+        let mut inputs = vec![];
+        for (input_index, width) in definition.input_widths().into_iter().enumerate() {
+            let input = circuit.add_bus_gate(GateType::Input, 0, width);
+            circuit.set_primary_input_value(input, Bus::from_u64(0, width));
+            circuit.connect(input, instance, input_index);
+            inputs.push(input);
+        }
+        let mut outputs = vec![];
+        for (output_index, width) in definition.output_widths().into_iter().enumerate() {
+            let buffer = circuit.add_bus_gate(GateType::Or, 1, width);
+            circuit.connect_port(instance, output_index, buffer, 0);
+            outputs.push(buffer);
+        }
+        Self { circuit, instance, inputs, outputs }
+    }
+
+    /// Drives the inputs with `values` and returns the value of every output.
+    fn run(&mut self, values: &[u64]) -> Vec<u64> {
+        for (&input, &value) in self.inputs.iter().zip(values) {
+            let width = self.circuit.gate(input).width;
+            self.circuit.set_primary_input_value(input, Bus::from_u64(value, width));
+        }
+        self.circuit.propagate(DEFAULT_MAX_PASSES).unwrap();
+        self.outputs.iter().map(|&output| self.circuit.get_output(output).to_u64().unwrap()).collect()
+    }
+
+    /// Every combination of input values, with the first input counting fastest.
+    fn input_combinations(&self) -> Vec<Vec<u64>> {
+        let widths: Vec<usize> = self.inputs.iter().map(|&input| self.circuit.gate(input).width).collect();
+        let total: usize = widths.iter().sum();
+        (0..1u64 << total)
+            .map(|mut combination| {
+                widths
+                    .iter()
+                    .map(|&width| {
+                        let value = combination & ((1 << width) - 1);
+                        combination >>= width;
+                        value
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+#[test]
+fn test_multiplexer_and_demultiplexer() {
+    let mut mux = Harness::new(Component::Multiplexer { select_width: 2, width: 3 });
+    assert_eq!(mux.circuit.input_names(mux.instance), ["D0", "D1", "D2", "D3", "S"]);
+    for select in 0..4 {
+        assert_eq!(mux.run(&[1, 2, 5, 7, select]), [[1, 2, 5, 7][select as usize]]);
+    }
+
+    let mut demux = Harness::new(Component::Demultiplexer { select_width: 2, width: 3 });
+    assert_eq!(demux.circuit.output_names(demux.instance), ["Y0", "Y1", "Y2", "Y3"]);
+    for select in 0..4 {
+        let expected: Vec<u64> = (0..4).map(|i| if i == select { 5 } else { 0 }).collect();
+        assert_eq!(demux.run(&[5, select]), expected);
+    }
+}
+
+#[test]
+fn test_decoder_and_priority_encoder() {
+    let mut decoder = Harness::new(Component::Decoder { select_width: 3 });
+    for address in 0..8 {
+        let expected: Vec<u64> = (0..8).map(|i| (i == address) as u64).collect();
+        assert_eq!(decoder.run(&[address]), expected);
+    }
+
+    let mut encoder = Harness::new(Component::PriorityEncoder { select_width: 2 });
+    assert_eq!(encoder.circuit.output_names(encoder.instance), ["Y", "V"]);
+    for values in encoder.input_combinations() {
+        let highest = values.iter().rposition(|&value| value == 1);
+        let expected = [highest.unwrap_or(0) as u64, highest.is_some() as u64];
+        assert_eq!(encoder.run(&values), expected, "inputs {:?}", values);
+    }
+}
+
+#[test]
+fn test_adders() {
+    for component in [Component::RippleCarryAdder { width: 4 }, Component::CarryLookaheadAdder { width: 4 }] {
+        let mut adder = Harness::new(component);
+        assert_eq!(adder.circuit.input_names(adder.instance), ["A", "B", "CIN"]);
+        for values in adder.input_combinations() {
+            let total = values.iter().sum::<u64>();
+            assert_eq!(adder.run(&values), [total & 0xF, total >> 4], "{} of {:?}", component.name(), values);
+        }
+    }
+}
+
+#[test]
+fn test_comparator() {
+    let mut comparator = Harness::new(Component::Comparator { width: 3 });
+    assert_eq!(comparator.circuit.output_names(comparator.instance), ["LT", "EQ", "GT"]);
+    for values in comparator.input_combinations() {
+        let (a, b) = (values[0], values[1]);
+        assert_eq!(comparator.run(&values), [(a < b) as u64, (a == b) as u64, (a > b) as u64]);
+    }
+}
+
+#[test]
+fn test_expand_into_primitive_gates() {
+    for component in Component::ALL {
+        let mut harness = Harness::new(component);
+        let combinations = harness.input_combinations();
+        let before: Vec<Vec<u64>> = combinations.iter().map(|values| harness.run(values)).collect();
+
+        let added = harness.circuit.expand(harness.instance);
+        assert!(!harness.circuit.contains_gate(harness.instance));
+        assert!(added.iter().all(|&id| harness.circuit.contains_gate(id)));
+        assert!(
+            harness
+                .circuit
+                .gate_ids()
+                .all(|id| !matches!(harness.circuit.gate(id).gate_type, GateType::Subcircuit(_))),
+            "{} was not expanded",
+            component.name()
+        );
+        let after: Vec<Vec<u64>> = combinations.iter().map(|values| harness.run(values)).collect();
+        assert_eq!(before, after, "{} changed when expanded", component.name());
+    }
+}
+
+#[test]
+fn test_component_definitions_and_errors() {
+    let mut circuit = Circuit::new();
+    let first = circuit.add_component(Component::RippleCarryAdder { width: 8 });
+    let second = circuit.add_component(Component::RippleCarryAdder { width: 8 });
+    let other = circuit.add_component(Component::RippleCarryAdder { width: 4 });
+    assert_eq!(circuit.gate(first).gate_type, circuit.gate(second).gate_type);
+    assert_ne!(circuit.gate(first).gate_type, circuit.gate(other).gate_type);
+    assert_eq!(circuit.gate(first).width, 8);
+    assert_eq!(Component::Multiplexer { select_width: 3, width: 16 }.name(), "MUX 8:1 [16]");
+
+    let invalid = Component::Multiplexer { select_width: 0, width: 1 };
+    assert_eq!(circuit.try_add_component(invalid), Err(CircuitError::InvalidComponent(invalid)));
+    assert!(circuit.try_add_component(Component::Comparator { width: 65 }).is_err());
+
+    let input = circuit.add_gate(GateType::Input, 0);
+    assert_eq!(circuit.try_expand(input), Err(CircuitError::NotAnInstance(input)));
+}
+
+#[test]
+fn test_components_do_not_reuse_user_definitions_with_their_name() {
+    let mut circuit = Circuit::new();
+    let component = Component::Comparator { width: 4 };
+    let mut inner = Circuit::new();
+    let input = inner.add_gate(GateType::Input, 0);
+    let mut user = Subcircuit::new(component.name(), inner);
+    user.add_input("x", input);
+    user.add_output("x", input);
+    let user = circuit.define_subcircuit(user);
+
+    let instance = circuit.add_component(component);
+    assert_ne!(circuit.gate(instance).gate_type, GateType::Subcircuit(user));
+    assert_eq!(circuit.output_names(instance), ["LT", "EQ", "GT"]);
+
+    // The definition stays tagged with its component across a round trip
+    let mut loaded = Circuit::from_json(&circuit.to_json().unwrap()).unwrap();
+    let again = loaded.add_component(component);
+    assert_eq!(loaded.gate(again).gate_type, circuit.gate(instance).gate_type);
+}
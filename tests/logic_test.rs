@@ -0,0 +1,48 @@
+use digital_logic_simulator::logic::Logic;
+
+#[test]
+fn test_bool_conversions() {
+    assert_eq!(Logic::from(true), Logic::One);
+    assert_eq!(Logic::from(false), Logic::Zero);
+    assert_eq!(Logic::One.to_bool(), Some(true));
+    assert_eq!(Logic::Z.to_bool(), None);
+    assert!(Logic::One == true);
+    assert!(Logic::X != false);
+}
+
+#[test]
+fn test_dominant_values_decide_unknowns() {
+    assert_eq!(Logic::Zero & Logic::X, Logic::Zero);
+    assert_eq!(Logic::One & Logic::Z, Logic::X);
+    assert_eq!(Logic::One | Logic::X, Logic::One);
+    assert_eq!(Logic::Zero | Logic::Z, Logic::X);
+    assert_eq!(Logic::One ^ Logic::X, Logic::X);
+    assert_eq!(!Logic::Z, Logic::X);
+}
+
+#[test]
+fn test_reductions_over_slices() {
+    assert_eq!(Logic::all(&[]), Logic::One);
+    assert_eq!(Logic::any(&[]), Logic::Zero);
+    assert_eq!(Logic::parity(&[Logic::One, Logic::One, Logic::One]), Logic::One);
+    assert_eq!(Logic::parity(&[Logic::One, Logic::Z]), Logic::X);
+}
+
+#[test]
+fn test_display() {
+    let text: String = [Logic::Zero, Logic::One, Logic::X, Logic::Z]
+        .iter()
+        .map(|v| v.to_string())
+        .collect();
+    assert_eq!(text, "01XZ");
+}
+
+#[test]
+fn test_resolve_multiple_drivers() {
+    assert_eq!(Logic::Z.resolve(Logic::One), Logic::One);
+    assert_eq!(Logic::Zero.resolve(Logic::Z), Logic::Zero);
+    assert_eq!(Logic::One.resolve(Logic::One), Logic::One);
+    assert_eq!(Logic::One.resolve(Logic::Zero), Logic::X);
+    assert_eq!(Logic::X.resolve(Logic::One), Logic::X);
+    assert_eq!(Logic::Z.resolve(Logic::Z), Logic::Z);
+}
@@ -0,0 +1,262 @@
+use digital_logic_simulator::bus::Bus;
+use digital_logic_simulator::circuit::{Circuit, DEFAULT_MAX_PASSES};
+use digital_logic_simulator::connection::GateId;
+use digital_logic_simulator::error::CircuitError;
+use digital_logic_simulator::gate::GateType;
+use digital_logic_simulator::logic::Logic;
+use digital_logic_simulator::memory::{self, ImageError, ImageFormat, LoadError, MemoryKind, MemorySpec};
+use digital_logic_simulator::storage::StorageKind;
+
+/// A memory with an input gate on every input, all starting at `0` except
+/// for chip select and read.
+fn memory_with_inputs(kind: MemoryKind, address_width: usize, data_width: usize) -> (Circuit, GateId, Vec<GateId>) {
+    let mut circuit = Circuit::new();
+    let memory = match kind {
+        MemoryKind::Rom => circuit.add_rom(address_width, data_width),
+        MemoryKind::Ram => circuit.add_ram(address_width, data_width),
+    };
+    let mut inputs = vec![];
+    for input_index in 0..kind.input_count() {
+        let width = match input_index {
+            memory::ADDRESS => address_width,
+            memory::DATA => data_width,
+            _ => 1,
+        };
+        let input = circuit.add_bus_gate(GateType::Input, 0, width);
+        let active = matches!(input_index, memory::CHIP_SELECT | memory::READ);
+        circuit.set_primary_input_value(input, Bus::from_u64(active as u64, width));
+        circuit.connect(input, memory, input_index);
+        inputs.push(input);
+    }
+    circuit.settle(DEFAULT_MAX_PASSES).unwrap();
+    (circuit, memory, inputs)
+}
+
+#[test]
+fn test_rom_reads_while_selected() {
+    let (mut circuit, rom, inputs) = memory_with_inputs(MemoryKind::Rom, 2, 4);
+    let revision = circuit.revision();
+    for (address, value) in [3, 5, 6, 13].into_iter().enumerate() {
+        circuit.write_memory(rom, address, Bus::from_u64(value, 4));
+    }
+    // Memory contents are not part of the netlist
+    assert_eq!(circuit.revision(), revision);
+    circuit.set_primary_input_value(inputs[memory::ADDRESS], Bus::from_u64(2, 2));
+    circuit.propagate(DEFAULT_MAX_PASSES).unwrap();
+    assert_eq!(circuit.get_output(rom), Bus::from_u64(6, 4));
+
+    // Words 1 and 3 only differ in their top bit
+    circuit.set_primary_input_value(inputs[memory::ADDRESS], "X1".parse::<Bus>().unwrap());
+    circuit.propagate(DEFAULT_MAX_PASSES).unwrap();
+    assert_eq!(circuit.get_output(rom), "X101".parse::<Bus>().unwrap());
+
+    circuit.set_primary_input_value(inputs[memory::CHIP_SELECT], false);
+    circuit.propagate(DEFAULT_MAX_PASSES).unwrap();
+    assert_eq!(circuit.get_output(rom), Bus::filled(4, Logic::Z));
+}
+
+#[test]
+fn test_ram_writes_on_rising_clock_edge() {
+    let (mut circuit, ram, inputs) = memory_with_inputs(MemoryKind::Ram, 3, 8);
+    let mut set = |input_index: usize, value: u64| {
+        let width = circuit.gate(inputs[input_index]).width;
+        circuit.set_primary_input_value(inputs[input_index], Bus::from_u64(value, width));
+        circuit.propagate(DEFAULT_MAX_PASSES).unwrap();
+    };
+    set(memory::ADDRESS, 5);
+    set(memory::DATA, 0xA7);
+    // Without WR the clock edge changes nothing
+    set(memory::CLOCK, 1);
+    set(memory::CLOCK, 0);
+    set(memory::WRITE, 1);
+    set(memory::CLOCK, 1);
+    // The write can be read back right away, and data changes after the edge are ignored
+    set(memory::DATA, 0x11);
+    assert_eq!(circuit.get_output(ram), Bus::from_u64(0xA7, 8));
+    assert_eq!(circuit.memory(ram).word(5), &Bus::from_u64(0xA7, 8));
+    assert_eq!(circuit.memory(ram).words().iter().filter(|word| *word != &Bus::from_u64(0, 8)).count(), 1);
+}
+
+#[test]
+fn test_ram_and_registers_share_each_edge() {
+    let kind = StorageKind::D;
+    let mut circuit = Circuit::new();
+    let clock = circuit.add_clock(2, 1);
+    let next_address = circuit.add_bus_gate(GateType::Input, 0, 2);
+    let data = circuit.add_bus_gate(GateType::Input, 0, 4);
+    let write = circuit.add_gate(GateType::Input, 0);
+    let address = circuit.add_bus_gate(GateType::FlipFlop(kind), kind.input_count(), 2);
+    let ram = circuit.add_ram(2, 4);
+    let read_back = circuit.add_bus_gate(GateType::FlipFlop(kind), kind.input_count(), 4);
+    circuit.connect(next_address, address, 0);
+    circuit.connect(address, ram, memory::ADDRESS);
+    circuit.connect(data, ram, memory::DATA);
+    circuit.connect(write, ram, memory::WRITE);
+    circuit.connect(ram, read_back, 0);
+    for gate in [address, read_back] {
+        circuit.connect(clock, gate, kind.clock_index());
+    }
+    circuit.connect(clock, ram, memory::CLOCK);
+    for (word, value) in [1, 2, 3, 4].into_iter().enumerate() {
+        circuit.write_memory(ram, word, Bus::from_u64(value, 4));
+    }
+    circuit.propagate(DEFAULT_MAX_PASSES).unwrap();
+    let mut cycle = |address: u64, value: u64, enabled: bool| {
+        circuit.set_primary_input_value(next_address, Bus::from_u64(address, 2));
+        circuit.set_primary_input_value(data, Bus::from_u64(value, 4));
+        circuit.set_primary_input_value(write, enabled);
+        circuit.run_cycles(clock, 1, &[]).unwrap();
+        circuit.get_output(read_back)
+    };
+
+    // Each write goes to the address the register held before the same edge,
+    // and the register reading the RAM gets the word from before the write
+    cycle(0, 0, false);
+    assert_eq!(cycle(1, 5, true), Bus::from_u64(1, 4));
+    assert_eq!(cycle(2, 9, true), Bus::from_u64(2, 4));
+    assert_eq!(circuit.get_output(address), Bus::from_u64(2, 2));
+    let words: Vec<u64> = circuit.memory(ram).words().iter().map(|word| word.to_u64().unwrap()).collect();
+    assert_eq!(words, [5, 9, 3, 4]);
+    assert_eq!(circuit.get_output(ram), Bus::from_u64(3, 4));
+}
+
+#[test]
+fn test_intel_hex_images() {
+    let mut circuit = Circuit::new();
+    let rom = circuit.add_rom(5, 8);
+    let image = ":040000001122334452\n:020000020001FB\n:01000200AB52\n:00000001FF\n:0100000000FF\n";
+    assert_eq!(circuit.load_memory(rom, ImageFormat::IntelHex, image.as_bytes()), Ok(5));
+    let words: Vec<u64> = circuit.memory(rom).words().iter().map(|word| word.to_u64().unwrap()).collect();
+    assert_eq!(words[..4], [0x11, 0x22, 0x33, 0x44]);
+    assert_eq!(words[18], 0xAB);
+    assert_eq!(words.iter().filter(|&&word| word != 0).count(), 5);
+
+    // Wider words take several bytes, least significant first
+    let wide = circuit.add_rom(1, 16);
+    circuit.load_memory(wide, ImageFormat::IntelHex, b":040000003412CDAB3E").unwrap();
+    assert_eq!(circuit.memory(wide).word(1), &Bus::from_u64(0xABCD, 16));
+
+    let errors = [
+        (":0400000011223344FF", ImageError::ChecksumMismatch { line: 1 }),
+        ("\n0400000011223344", ImageError::InvalidRecord { line: 2 }),
+        (":03000000112233", ImageError::InvalidRecord { line: 1 }),
+        (":00000006FA", ImageError::UnsupportedRecord { line: 1, record_type: 6 }),
+        (":0100040001FA", ImageError::OutOfRange { address: 2, word_count: 2 }),
+    ];
+    for (image, error) in errors {
+        assert_eq!(circuit.load_memory(wide, ImageFormat::IntelHex, image.as_bytes()), Err(error));
+    }
+    // A failed load leaves the contents alone
+    assert_eq!(circuit.memory(wide).word(1), &Bus::from_u64(0xABCD, 16));
+}
+
+#[test]
+fn test_binary_and_logisim_images() {
+    let mut circuit = Circuit::new();
+    let ram = circuit.add_ram(2, 12);
+    assert_eq!(circuit.load_memory(ram, ImageFormat::Binary, &[0x34, 0xF2, 0xFF]), Ok(2));
+    // Bits beyond the word width are dropped
+    assert_eq!(circuit.memory(ram).word(0), &Bus::from_u64(0x234, 12));
+    assert_eq!(circuit.memory(ram).word(1), &Bus::from_u64(0xFF, 12));
+
+    let image = "v2.0 raw\n# boot vector\n2*7ff 1 # tail\n";
+    assert_eq!(circuit.load_memory(ram, ImageFormat::LogisimRaw, image.as_bytes()), Ok(3));
+    let words: Vec<u64> = circuit.memory(ram).words().iter().map(|word| word.to_u64().unwrap()).collect();
+    assert_eq!(words, [0x7FF, 0x7FF, 1, 0]);
+
+    let load = |circuit: &mut Circuit, image: &str| circuit.load_memory(ram, ImageFormat::LogisimRaw, image.as_bytes());
+    assert_eq!(load(&mut circuit, "1 2 3"), Err(ImageError::MissingHeader));
+    assert_eq!(
+        load(&mut circuit, "v2.0 raw\n1 g\n"),
+        Err(ImageError::InvalidValue { line: 2, token: "g".to_string() })
+    );
+    assert_eq!(
+        load(&mut circuit, "v2.0 raw\n3*0 2*1\n"),
+        Err(ImageError::OutOfRange { address: 4, word_count: 4 })
+    );
+    assert_eq!(
+        load(&mut circuit, "v2.0 raw\n4*0\n# past the end\n1\n"),
+        Err(ImageError::OutOfRange { address: 4, word_count: 4 })
+    );
+    assert_eq!(
+        load(&mut circuit, &format!("v2.0 raw\n1 {}*0\n", usize::MAX)),
+        Err(ImageError::OutOfRange { address: 4, word_count: 4 })
+    );
+    assert_eq!(
+        circuit.load_memory(ram, ImageFormat::Binary, &[0; 9]),
+        Err(ImageError::OutOfRange { address: 4, word_count: 4 })
+    );
+}
+
+#[test]
+fn test_detect_image_format() {
+    assert_eq!(ImageFormat::detect("boot.HEX", b":00000001FF"), ImageFormat::IntelHex);
+    assert_eq!(ImageFormat::detect("image", b"v2.0 raw\n0\n"), ImageFormat::LogisimRaw);
+    assert_eq!(ImageFormat::detect("image.bin", b"\x00\x01"), ImageFormat::Binary);
+}
+
+#[test]
+fn test_memory_errors() {
+    let mut circuit = Circuit::new();
+    assert_eq!(
+        circuit.try_add_bus_gate(GateType::Memory(MemorySpec { kind: MemoryKind::Rom, address_width: 17 }), 3, 8),
+        Err(CircuitError::InvalidMemorySize { address_width: 17, data_width: 8 })
+    );
+    assert!(circuit.try_add_bus_gate(GateType::Memory(MemorySpec::default()), 6, 8).is_err());
+
+    let input = circuit.add_gate(GateType::Input, 0);
+    assert_eq!(circuit.try_memory(input).err(), Some(CircuitError::NotAMemory(input)));
+    let rom = circuit.add_rom(2, 4);
+    assert_eq!(
+        circuit.try_write_memory(rom, 4, Bus::from_u64(0, 4)),
+        Err(CircuitError::InvalidAddress { gate: rom, address: 4, word_count: 4 })
+    );
+    assert_eq!(
+        circuit.try_write_memory(rom, 0, true),
+        Err(CircuitError::WordWidthMismatch { gate: rom, expected: 4, actual: 1 })
+    );
+    assert!(circuit.try_connect(input, rom, memory::ADDRESS).is_err());
+    circuit.connect(input, rom, memory::READ);
+
+    let image = b"v2.0 raw\n5*1\n";
+    assert_eq!(
+        circuit.try_load_memory(input, ImageFormat::LogisimRaw, image),
+        Err(LoadError::Circuit(CircuitError::NotAMemory(input)))
+    );
+    assert_eq!(
+        circuit.try_load_memory(9, ImageFormat::LogisimRaw, image),
+        Err(LoadError::Circuit(CircuitError::InvalidGate(9)))
+    );
+    assert_eq!(
+        circuit.try_load_memory(rom, ImageFormat::LogisimRaw, image),
+        Err(LoadError::Image(ImageError::OutOfRange { address: 4, word_count: 4 }))
+    );
+    assert_eq!(circuit.try_load_memory(rom, ImageFormat::LogisimRaw, b"v2.0 raw\n4*1\n"), Ok(4));
+}
+
+#[test]
+#[should_panic(expected = "Gate 0 is not a memory")]
+fn test_load_memory_requires_a_memory() {
+    let mut circuit = Circuit::new();
+    let input = circuit.add_gate(GateType::Input, 0);
+    let _ = circuit.load_memory(input, ImageFormat::Binary, &[]);
+}
+
+#[test]
+fn test_rom_contents_are_saved_and_tabulated() {
+    let mut circuit = Circuit::new();
+    let rom = circuit.add_rom(1, 2);
+    let address = circuit.add_gate(GateType::Input, 0);
+    circuit.connect(address, rom, memory::ADDRESS);
+    circuit.write_memory(rom, 0, Bus::from_u64(2, 2));
+    circuit.write_memory(rom, 1, Bus::from_u64(1, 2));
+
+    let mut loaded = Circuit::from_json(&circuit.to_json().unwrap()).unwrap();
+    assert_eq!(loaded.memory(rom), circuit.memory(rom));
+    assert_eq!(loaded.input_names(rom), ["A", "CS", "RD"]);
+    assert_eq!(loaded.output_names(rom), ["DOUT"]);
+
+    let table = loaded.truth_table(&[rom]).unwrap();
+    let outputs: Vec<Bus> = table.rows.iter().map(|row| row.outputs[0].clone()).collect();
+    assert_eq!(outputs, [Bus::from_u64(2, 2), Bus::from_u64(1, 2)]);
+}
@@ -0,0 +1,72 @@
+use digital_logic_simulator::circuit::Circuit;
+use digital_logic_simulator::expression::Expr;
+use digital_logic_simulator::gate::GateType;
+use digital_logic_simulator::minimize::{Implicant, SumOfProducts};
+
+fn names(names: &[&str]) -> Vec<String> {
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+#[test]
+fn test_majority() {
+    let sop = SumOfProducts::minimize(names(&["a", "b", "c"]), &[3, 5, 6, 7], &[]);
+    assert_eq!(sop.to_string(), "a & b | a & c | b & c");
+    assert_eq!(sop.literal_count(), 6);
+}
+
+#[test]
+fn test_classic_example_with_dont_cares() {
+    // f(a, b, c, d) = m(4, 8, 10, 11, 12, 15) + d(9, 14)
+    let sop = SumOfProducts::minimize(names(&["a", "b", "c", "d"]), &[4, 8, 10, 11, 12, 15], &[9, 14]);
+    assert_eq!(sop.terms.len(), 3);
+    assert_eq!(sop.literal_count(), 7);
+    for minterm in 0..16 {
+        let covered = sop.terms.iter().any(|term| term.covers(minterm));
+        if [4, 8, 10, 11, 12, 15].contains(&minterm) {
+            assert!(covered, "minterm {}", minterm);
+        } else if ![9, 14].contains(&minterm) {
+            assert!(!covered, "minterm {}", minterm);
+        }
+    }
+}
+
+#[test]
+fn test_constants() {
+    let zero = SumOfProducts::minimize(names(&["a", "b"]), &[], &[]);
+    assert_eq!(zero.to_string(), "0");
+    assert_eq!(zero.to_expr().unwrap().to_string(), "a & !a");
+
+    let one = SumOfProducts::minimize(names(&["a", "b"]), &[0, 1, 2], &[3]);
+    assert_eq!(one.terms, vec![Implicant { care: 0, value: 0 }]);
+    assert_eq!(one.to_string(), "1");
+    assert_eq!(one.to_expr().unwrap().to_string(), "a | !a");
+
+    assert_eq!(SumOfProducts::minimize(vec![], &[0], &[]).to_expr(), None);
+}
+
+#[test]
+fn test_minimize_and_synthesize_gate() {
+    // a & b | a & !b | !a & b & c simplifies to a | b & c
+    let expr: Expr = "a & b | a & !b | !a & b & c".parse().unwrap();
+    let (mut circuit, output) = expr.to_circuit();
+    let sop = circuit.minimize(output).unwrap().pop().unwrap();
+    assert_eq!(sop.to_string(), "a | b & c");
+
+    let (mut smaller, smaller_output) = sop.synthesize().unwrap();
+    assert!(smaller.gate_count() < circuit.gate_count());
+    let original = circuit.truth_table(&[output]).unwrap();
+    let synthesized = smaller.truth_table(&[smaller_output]).unwrap();
+    assert_eq!(original.rows, synthesized.rows);
+}
+
+#[test]
+fn test_bus_outputs_give_one_function_per_bit() {
+    let mut circuit = Circuit::new();
+    let x = circuit.add_bus_gate(GateType::Input, 0, 2);
+    let not = circuit.add_bus_gate(GateType::Not, 1, 2);
+    circuit.connect(x, not, 0);
+    circuit.set_name(x, "x");
+
+    let bits: Vec<String> = circuit.minimize(not).unwrap().iter().map(|sop| sop.to_string()).collect();
+    assert_eq!(bits, vec!["!x_0", "!x_1"]);
+}
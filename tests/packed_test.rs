@@ -0,0 +1,199 @@
+use digital_logic_simulator::bus::Bus;
+use digital_logic_simulator::circuit::{Circuit, DEFAULT_MAX_PASSES};
+use digital_logic_simulator::error::CircuitError;
+use digital_logic_simulator::gate::{Gate, GateType};
+use digital_logic_simulator::logic::Logic;
+use digital_logic_simulator::packed::{self, PackedLogic};
+use digital_logic_simulator::subcircuit::Subcircuit;
+use std::collections::HashMap;
+
+const ALL: [Logic; 4] = [Logic::Zero, Logic::One, Logic::X, Logic::Z];
+
+/// Packs every pair of signals into lanes `0..16` of two words.
+fn all_pairs() -> (PackedLogic, PackedLogic) {
+    let mut a = PackedLogic::X;
+    let mut b = PackedLogic::X;
+    for (i, (x, y)) in ALL.iter().flat_map(|&x| ALL.iter().map(move |&y| (x, y))).enumerate() {
+        a.set_lane(i, x);
+        b.set_lane(i, y);
+    }
+    (a, b)
+}
+
+#[test]
+fn test_operators_match_scalar_logic() {
+    let (a, b) = all_pairs();
+    let (and, or, xor, not, resolved) = (a & b, a | b, a ^ b, !a, a.resolve(b));
+    for i in 0..16 {
+        let (x, y) = (a.lane(i), b.lane(i));
+        assert_eq!(and.lane(i), x & y, "{} & {}", x, y);
+        assert_eq!(or.lane(i), x | y, "{} | {}", x, y);
+        assert_eq!(xor.lane(i), x ^ y, "{} ^ {}", x, y);
+        assert_eq!(not.lane(i), !x, "!{}", x);
+        assert_eq!(resolved.lane(i), x.resolve(y), "{} resolve {}", x, y);
+    }
+}
+
+#[test]
+fn test_lane_round_trip() {
+    let word = PackedLogic::from_lanes(&ALL);
+    assert_eq!((0..4).map(|i| word.lane(i)).collect::<Vec<_>>(), ALL);
+    assert_eq!(word.lane(4), Logic::X);
+    assert_eq!(PackedLogic::splat(Logic::Z).lane(63), Logic::Z);
+}
+
+#[test]
+fn test_counting_enumerates_vectors() {
+    let low = PackedLogic::counting(0, 0);
+    let high = PackedLogic::counting(6, 64);
+    assert_eq!(low.ones(), 0xAAAA_AAAA_AAAA_AAAA);
+    assert_eq!(high.ones(), !0);
+    assert_eq!(PackedLogic::counting(5, 0).ones(), 0xFFFF_FFFF_0000_0000);
+}
+
+#[test]
+fn test_gate_packed_evaluation_matches_scalar() {
+    let (a, b) = all_pairs();
+    for gate_type in [GateType::And, GateType::Or, GateType::Xor] {
+        let gate = Gate::new(gate_type, 2);
+        let word = gate.evaluate_packed(&[a, b]);
+        for i in 0..16 {
+            assert_eq!(word.lane(i), gate.evaluate_with_inputs(&[a.lane(i), b.lane(i)]));
+        }
+    }
+}
+
+/// Builds a 1-bit full adder and returns `(a, b, cin, sum, cout)`.
+fn full_adder(circuit: &mut Circuit) -> (usize, usize, usize, usize, usize) {
+    let a = circuit.add_gate(GateType::Input, 0);
+    let b = circuit.add_gate(GateType::Input, 0);
+    let cin = circuit.add_gate(GateType::Input, 0);
+    let sum = circuit.add_gate(GateType::Xor, 3);
+    let ab = circuit.add_gate(GateType::And, 2);
+    let a_xor_b = circuit.add_gate(GateType::Xor, 2);
+    let carry_through = circuit.add_gate(GateType::And, 2);
+    let cout = circuit.add_gate(GateType::Or, 2);
+    circuit.connect(a, sum, 0);
+    circuit.connect(b, sum, 1);
+    circuit.connect(cin, sum, 2);
+    circuit.connect(a, ab, 0);
+    circuit.connect(b, ab, 1);
+    circuit.connect(a, a_xor_b, 0);
+    circuit.connect(b, a_xor_b, 1);
+    circuit.connect(a_xor_b, carry_through, 0);
+    circuit.connect(cin, carry_through, 1);
+    circuit.connect(ab, cout, 0);
+    circuit.connect(carry_through, cout, 1);
+    (a, b, cin, sum, cout)
+}
+
+#[test]
+fn test_exhaustive_full_adder_in_one_pass() {
+    let mut circuit = Circuit::new();
+    let (a, b, cin, sum, cout) = full_adder(&mut circuit);
+    let inputs = HashMap::from([
+        (a, vec![PackedLogic::counting(0, 0)]),
+        (b, vec![PackedLogic::counting(1, 0)]),
+        (cin, vec![PackedLogic::counting(2, 0)]),
+    ]);
+    let outputs = circuit.simulate_packed(&inputs, DEFAULT_MAX_PASSES).unwrap();
+
+    for vector in 0..8u64 {
+        let total = (vector & 1) + ((vector >> 1) & 1) + ((vector >> 2) & 1);
+        let lane = vector as usize;
+        assert_eq!(outputs[&sum][0][0].lane(lane), Logic::from(total & 1 == 1));
+        assert_eq!(outputs[&cout][0][0].lane(lane), Logic::from(total >= 2));
+    }
+    // The circuit itself is left untouched
+    assert_eq!(circuit.get_output(sum), Logic::X);
+}
+
+#[test]
+fn test_packed_buses_and_subcircuits_match_scalar_simulation() {
+    let mut inner = Circuit::new();
+    let x = inner.add_bus_gate(GateType::Input, 0, 4);
+    let y = inner.add_bus_gate(GateType::Input, 0, 4);
+    let xor = inner.add_bus_gate(GateType::Xor, 2, 4);
+    inner.connect(x, xor, 0);
+    inner.connect(y, xor, 1);
+    let mut def = Subcircuit::new("xor4", inner);
+    def.add_input("x", x);
+    def.add_input("y", y);
+    def.add_output("z", xor);
+
+    let mut circuit = Circuit::new();
+    let a = circuit.add_bus_gate(GateType::Input, 0, 4);
+    let b = circuit.add_bus_gate(GateType::Input, 0, 4);
+    let id = circuit.define_subcircuit(def);
+    let instance = circuit.add_subcircuit(id);
+    let high = circuit.add_splitter(2, 2);
+    circuit.connect(a, instance, 0);
+    circuit.connect(b, instance, 1);
+    circuit.connect(instance, high, 0);
+
+    let vectors: Vec<(u64, u64)> = (0..64).map(|i| (i % 16, (i * 7) % 16)).collect();
+    let pack = |f: &dyn Fn(&(u64, u64)) -> u64| -> Vec<PackedLogic> {
+        (0..4)
+            .map(|bit| {
+                let lanes: Vec<Logic> = vectors.iter().map(|v| Logic::from((f(v) >> bit) & 1 == 1)).collect();
+                PackedLogic::from_lanes(&lanes)
+            })
+            .collect()
+    };
+    let inputs = HashMap::from([(a, pack(&|v| v.0)), (b, pack(&|v| v.1))]);
+    let outputs = circuit.simulate_packed(&inputs, DEFAULT_MAX_PASSES).unwrap();
+
+    for (lane, &(va, vb)) in vectors.iter().enumerate() {
+        circuit.set_primary_input_value(a, Bus::from_u64(va, 4));
+        circuit.set_primary_input_value(b, Bus::from_u64(vb, 4));
+        circuit.settle(DEFAULT_MAX_PASSES).unwrap();
+        assert_eq!(packed::lane_bus(&outputs[&instance][0], lane), circuit.get_output(instance));
+        assert_eq!(packed::lane_bus(&outputs[&high][0], lane), circuit.get_output(high));
+    }
+}
+
+#[test]
+fn test_packed_feedback_and_oscillation() {
+    let mut circuit = Circuit::new();
+    let enable = circuit.add_gate(GateType::Input, 0);
+    let and = circuit.add_gate(GateType::And, 2);
+    let not = circuit.add_gate(GateType::Not, 1);
+    circuit.connect(enable, and, 0);
+    circuit.connect(not, and, 1);
+    circuit.connect(and, not, 0);
+    circuit.set_primary_input_value(enable, false);
+    circuit.settle(DEFAULT_MAX_PASSES).unwrap();
+
+    let disabled = HashMap::from([(enable, vec![PackedLogic::from_bits(0)])]);
+    let outputs = circuit.simulate_packed(&disabled, DEFAULT_MAX_PASSES).unwrap();
+    assert_eq!(outputs[&not][0][0].ones(), !0);
+
+    // Enabling a single lane is enough to make the loop oscillate
+    let one_lane = HashMap::from([(enable, vec![PackedLogic::from_bits(1 << 5)])]);
+    let oscillation = circuit.simulate_packed(&one_lane, 10).unwrap_err();
+    assert_eq!(oscillation.passes, 10);
+}
+
+#[test]
+#[should_panic(expected = "Gate 3 is not an input gate")]
+fn test_packed_values_only_drive_inputs() {
+    let mut circuit = Circuit::new();
+    let (_, _, _, sum, _) = full_adder(&mut circuit);
+    circuit.simulate_packed(&HashMap::from([(sum, vec![PackedLogic::X])]), 1).ok();
+}
+
+#[test]
+fn test_try_simulate_packed_reports_invalid_inputs() {
+    let mut circuit = Circuit::new();
+    let (a, _, _, sum, _) = full_adder(&mut circuit);
+    let simulate = |circuit: &mut Circuit, gate, value: Vec<PackedLogic>| {
+        circuit.try_simulate_packed(&HashMap::from([(gate, value)]), 1).err()
+    };
+    assert_eq!(simulate(&mut circuit, sum, vec![PackedLogic::X]), Some(CircuitError::NotAnInput(sum)));
+    assert_eq!(simulate(&mut circuit, 99, vec![PackedLogic::X]), Some(CircuitError::InvalidGate(99)));
+    assert_eq!(
+        simulate(&mut circuit, a, vec![PackedLogic::X; 2]),
+        Some(CircuitError::InputWidthMismatch { gate: a, expected: 1, actual: 2 })
+    );
+    assert!(circuit.try_simulate_packed(&HashMap::from([(a, vec![PackedLogic::X])]), 1).unwrap().is_ok());
+}
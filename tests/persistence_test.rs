@@ -0,0 +1,268 @@
+use digital_logic_simulator::bus::Bus;
+use digital_logic_simulator::circuit::Circuit;
+use digital_logic_simulator::error::CircuitError;
+use digital_logic_simulator::gate::GateType;
+use digital_logic_simulator::logic::Logic;
+use digital_logic_simulator::persistence::{self, FileError, GatePosition, FORMAT_VERSION};
+use digital_logic_simulator::subcircuit::Subcircuit;
+
+/// Builds a circuit with a bus datapath and a half adder subcircuit instance.
+fn sample_circuit() -> Circuit {
+    let mut half_adder = Circuit::new();
+    let a = half_adder.add_gate(GateType::Input, 0);
+    let b = half_adder.add_gate(GateType::Input, 0);
+    let sum = half_adder.add_gate(GateType::Xor, 2);
+    let carry = half_adder.add_gate(GateType::And, 2);
+    half_adder.connect(a, sum, 0);
+    half_adder.connect(b, sum, 1);
+    half_adder.connect(a, carry, 0);
+    half_adder.connect(b, carry, 1);
+    let mut def = Subcircuit::new("half adder", half_adder);
+    def.add_input("a", a);
+    def.add_input("b", b);
+    def.add_output("sum", sum);
+    def.add_output("carry", carry);
+
+    let mut circuit = Circuit::new();
+    let x = circuit.add_bus_gate(GateType::Input, 0, 4);
+    let y = circuit.add_bus_gate(GateType::Input, 0, 4);
+    let and = circuit.add_bus_gate(GateType::And, 2, 4);
+    circuit.connect(x, and, 0);
+    circuit.connect(y, and, 1);
+
+    let p = circuit.add_gate(GateType::Input, 0);
+    let q = circuit.add_gate(GateType::Input, 0);
+    let id = circuit.define_subcircuit(def);
+    let adder = circuit.add_subcircuit(id);
+    circuit.connect(p, adder, 0);
+    circuit.connect(q, adder, 1);
+
+    circuit.set_primary_input_value(x, Bus::from_u64(0b1100, 4));
+    circuit.set_primary_input_value(y, Bus::from_u64(0b1010, 4));
+    circuit.set_primary_input_value(p, true);
+    circuit.set_primary_input_value(q, true);
+    circuit.settle(10).unwrap();
+    circuit
+}
+
+#[test]
+fn test_round_trip_preserves_structure_and_values() {
+    let circuit = sample_circuit();
+    let json = circuit.to_json().unwrap();
+    let mut loaded = Circuit::from_json(&json).unwrap();
+
+    assert_eq!(loaded.gate_count(), circuit.gate_count());
+    assert_eq!(loaded.connections(), circuit.connections());
+    assert_eq!(loaded.get_output(0), Bus::from_u64(0b1100, 4));
+    assert_eq!(loaded.get_output(2), Bus::from_u64(0b1000, 4));
+    assert_eq!(loaded.subcircuit(0).name(), "half adder");
+
+    // Loaded circuits keep simulating, including their subcircuit instances.
+    loaded.set_primary_input_value(1, Bus::from_u64(0b0100, 4));
+    loaded.settle(10).unwrap();
+    assert_eq!(loaded.get_output(2), Bus::from_u64(0b0100, 4));
+    assert_eq!(loaded.get_port_output(5, 0), Logic::Zero);
+    assert_eq!(loaded.get_port_output(5, 1), Logic::One);
+}
+
+#[test]
+fn test_layout_round_trip() {
+    let circuit = sample_circuit();
+    let layout = vec![
+        GatePosition { gate: 0, x: 10.0, y: 20.0 },
+        GatePosition { gate: 2, x: 150.5, y: 40.0 },
+    ];
+    let json = persistence::write_document(&circuit, &layout).unwrap();
+    let (_, loaded_layout) = persistence::read_document(&json).unwrap();
+    assert_eq!(loaded_layout, layout);
+}
+
+#[test]
+fn test_document_records_current_version() {
+    let json = Circuit::new().to_json().unwrap();
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["version"], FORMAT_VERSION);
+}
+
+#[test]
+fn test_rejects_newer_version() {
+    let json = Circuit::new().to_json().unwrap();
+    let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    value["version"] = (FORMAT_VERSION + 1).into();
+
+    match Circuit::from_json(&value.to_string()) {
+        Err(FileError::UnsupportedVersion(v)) => assert_eq!(v, FORMAT_VERSION + 1),
+        other => panic!("expected unsupported version, got {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn test_rejects_malformed_documents() {
+    assert!(matches!(Circuit::from_json("not json"), Err(FileError::Format(_))));
+    assert!(matches!(Circuit::from_json("{\"circuit\": {}}"), Err(FileError::Format(_))));
+}
+
+#[test]
+fn test_save_and_load_file() {
+    let circuit = sample_circuit();
+    let path = std::env::temp_dir().join(format!("dls_persistence_{}.json", std::process::id()));
+    circuit.save(&path).unwrap();
+    let loaded = Circuit::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(loaded.connections(), circuit.connections());
+    assert_eq!(loaded.get_output(0), circuit.get_output(0));
+}
+
+#[test]
+fn test_load_missing_file() {
+    let path = std::env::temp_dir().join("dls_persistence_does_not_exist.json");
+    assert!(matches!(Circuit::load(path), Err(FileError::Io(_))));
+}
+
+#[test]
+fn test_rejects_inconsistent_circuit() {
+    let mut circuit = Circuit::new();
+    let a = circuit.add_gate(GateType::Input, 0);
+    let not = circuit.add_gate(GateType::Not, 1);
+    circuit.connect(a, not, 0);
+    let mut value: serde_json::Value = serde_json::from_str(&circuit.to_json().unwrap()).unwrap();
+    value["circuit"]["connections"][0]["input_index"] = 3.into();
+
+    match Circuit::from_json(&value.to_string()) {
+        Err(FileError::Invalid(err)) => {
+            assert_eq!(err, CircuitError::InvalidInputIndex { gate: not, input_index: 3, input_count: 1 })
+        }
+        other => panic!("expected invalid circuit, got {:?}", other.map(|_| ())),
+    }
+}
+
+/// Loads `sample_circuit` after applying a hand edit to its circuit, expecting
+/// the result to be rejected as invalid.
+fn invalid_edit(edit: impl FnOnce(&mut serde_json::Value)) -> CircuitError {
+    let mut value: serde_json::Value = serde_json::from_str(&sample_circuit().to_json().unwrap()).unwrap();
+    edit(&mut value["circuit"]);
+    match Circuit::from_json(&value.to_string()) {
+        Err(FileError::Invalid(err)) => err,
+        other => panic!("expected invalid circuit, got {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn test_rejects_subcircuits_with_invalid_ports() {
+    // The half adder has input ports on gates 0 and 1 and output ports on gates 2 and 3
+    assert_eq!(
+        invalid_edit(|circuit| circuit["subcircuits"][0]["outputs"][0]["gate"] = 99.into()),
+        CircuitError::InvalidPortGate { subcircuit: 0, gate: 99 }
+    );
+    assert_eq!(
+        invalid_edit(|circuit| circuit["subcircuits"][0]["inputs"][1]["gate"] = 2.into()),
+        CircuitError::PortNotAnInput { subcircuit: 0, gate: 2 }
+    );
+}
+
+#[test]
+fn test_rejects_instances_that_do_not_match_their_definition() {
+    // Gate 5 is the half adder instance
+    assert_eq!(
+        invalid_edit(|circuit| {
+            circuit["instances"]["5"]["gates"].as_object_mut().unwrap().remove("0");
+        }),
+        CircuitError::InstanceMismatch { gate: 5, port_gate: 0 }
+    );
+    assert_eq!(
+        invalid_edit(|circuit| circuit["instances"]["5"]["gates"]["3"]["gate_type"] = "Or".into()),
+        CircuitError::InstanceMismatch { gate: 5, port_gate: 3 }
+    );
+}
+
+#[test]
+fn test_rejects_invalid_clock_timing() {
+    let mut circuit = Circuit::new();
+    let clock = circuit.add_clock(2, 1);
+    let mut value: serde_json::Value = serde_json::from_str(&circuit.to_json().unwrap()).unwrap();
+    value["circuit"]["gates"][clock.to_string()]["gate_type"]["Clock"]["period"] = 0.into();
+
+    match Circuit::from_json(&value.to_string()) {
+        Err(FileError::Invalid(err)) => assert_eq!(err, CircuitError::InvalidClockTiming { period: 0, high: 1 }),
+        other => panic!("expected invalid clock timing, got {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn test_migrates_version_1_documents() {
+    let json = r#"{
+        "version": 1,
+        "circuit": {
+            "gates": [
+                {"gate_type": "Input", "input_count": 0, "width": 1, "offset": 0, "outputs": ["1"], "delay": 1},
+                {"gate_type": "Not", "input_count": 1, "width": 1, "offset": 0, "outputs": ["X"], "delay": 1}
+            ],
+            "connections": [{"from": 0, "output_index": 0, "to": 1, "input_index": 0}],
+            "subcircuits": [],
+            "instances": {}
+        },
+        "layout": [{"gate": 1, "x": 5.0, "y": 6.0}]
+    }"#;
+
+    let (mut circuit, layout) = persistence::read_document(json).unwrap();
+    assert_eq!(circuit.gate_ids().collect::<Vec<_>>(), vec![0, 1]);
+    assert_eq!(layout, vec![GatePosition { gate: 1, x: 5.0, y: 6.0 }]);
+    circuit.settle(10).unwrap();
+    assert_eq!(circuit.get_output(1), Logic::Zero);
+    assert_eq!(circuit.add_gate(GateType::Input, 0), 2);
+}
+
+#[test]
+fn test_removed_gate_ids_survive_round_trip() {
+    let mut circuit = sample_circuit();
+    circuit.remove_gate(1);
+    let mut loaded = Circuit::from_json(&circuit.to_json().unwrap()).unwrap();
+
+    assert!(!loaded.contains_gate(1));
+    assert_eq!(loaded.gate_ids().collect::<Vec<_>>(), circuit.gate_ids().collect::<Vec<_>>());
+    assert_eq!(loaded.add_gate(GateType::Input, 0), 6);
+}
+
+#[test]
+fn test_gate_names_survive_round_trip() {
+    let mut circuit = sample_circuit();
+    circuit.set_name(0, "x");
+    let json = circuit.to_json().unwrap();
+    let loaded = Circuit::from_json(&json).unwrap();
+
+    assert_eq!(loaded.find_gate("x"), Some(0));
+    assert_eq!(loaded.gate(1).name, None);
+    assert!(!json.contains("null"));
+}
+
+#[test]
+fn test_inputs_with_several_drivers_report_contention() {
+    let mut circuit = Circuit::new();
+    let a = circuit.add_gate(GateType::Input, 0);
+    let b = circuit.add_gate(GateType::Input, 0);
+    let not = circuit.add_gate(GateType::Not, 1);
+    circuit.connect(a, not, 0);
+    circuit.set_primary_input_value(a, true);
+    circuit.set_primary_input_value(b, false);
+
+    // Older versions allowed a second driver on the same input
+    let mut value: serde_json::Value = serde_json::from_str(&circuit.to_json().unwrap()).unwrap();
+    value["circuit"]["connections"]
+        .as_array_mut()
+        .unwrap()
+        .push(serde_json::json!({"from": b, "output_index": 0, "to": not, "input_index": 0}));
+    let mut loaded = Circuit::from_json(&value.to_string()).unwrap();
+
+    loaded.settle(10).unwrap();
+    assert_eq!(loaded.get_output(not), Logic::X);
+    let contention = loaded.contention();
+    assert_eq!(contention.len(), 1);
+    assert_eq!(contention[0].gate, not);
+    assert_eq!(contention[0].drivers, vec![(a, 0), (b, 0)]);
+
+    loaded.set_primary_input_value(b, true);
+    loaded.settle(10).unwrap();
+    assert_eq!(loaded.get_output(not), Logic::Zero);
+    assert!(loaded.contention().is_empty());
+}
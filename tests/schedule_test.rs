@@ -0,0 +1,99 @@
+use digital_logic_simulator::circuit::Circuit;
+use digital_logic_simulator::gate::GateType;
+use digital_logic_simulator::logic::Logic;
+use digital_logic_simulator::schedule::Cycle;
+
+#[test]
+fn test_levels_follow_signal_depth() {
+    let mut circuit = Circuit::new();
+    let a = circuit.add_gate(GateType::Input, 0);
+    let b = circuit.add_gate(GateType::Input, 0);
+    let and = circuit.add_gate(GateType::And, 2);
+    let not = circuit.add_gate(GateType::Not, 1);
+    let or = circuit.add_gate(GateType::Or, 2);
+    circuit.connect(a, and, 0);
+    circuit.connect(b, and, 1);
+    circuit.connect(and, not, 0);
+    circuit.connect(not, or, 0);
+    circuit.connect(a, or, 1);
+
+    assert_eq!(circuit.levelize(), Ok(vec![vec![a, b], vec![and], vec![not], vec![or]]));
+    assert_eq!(circuit.schedule().order(), &[a, b, and, not, or]);
+    assert_eq!(circuit.schedule().fanout(a), &[and, or]);
+    assert_eq!(circuit.schedule().fanin(or).len(), 2);
+}
+
+#[test]
+fn test_feedback_loop_is_reported() {
+    let mut circuit = Circuit::new();
+    let set = circuit.add_gate(GateType::Input, 0);
+    let reset = circuit.add_gate(GateType::Input, 0);
+    let or_q = circuit.add_gate(GateType::Or, 2);
+    let q = circuit.add_gate(GateType::Not, 1);
+    let or_q_bar = circuit.add_gate(GateType::Or, 2);
+    let q_bar = circuit.add_gate(GateType::Not, 1);
+    let out = circuit.add_gate(GateType::Not, 1);
+    circuit.connect(reset, or_q, 0);
+    circuit.connect(q_bar, or_q, 1);
+    circuit.connect(or_q, q, 0);
+    circuit.connect(set, or_q_bar, 0);
+    circuit.connect(q, or_q_bar, 1);
+    circuit.connect(or_q_bar, q_bar, 0);
+    circuit.connect(q, out, 0);
+
+    let cycle = circuit.levelize().unwrap_err();
+    assert_eq!(cycle, Cycle { gates: vec![or_q, q, or_q_bar, q_bar] });
+    assert_eq!(cycle.to_string(), "feedback loop through gates 2 -> 3 -> 4 -> 5");
+
+    // Feedback circuits are still evaluated, in depth-first order
+    assert_eq!(circuit.schedule().order().len(), circuit.gate_count());
+    circuit.set_primary_input_value(set, true);
+    circuit.set_primary_input_value(reset, false);
+    circuit.settle(10).unwrap();
+    assert_eq!(circuit.get_output(q), Logic::One);
+    assert_eq!(circuit.get_output(out), Logic::Zero);
+}
+
+#[test]
+fn test_schedule_is_rebuilt_after_netlist_changes() {
+    let mut circuit = Circuit::new();
+    let a = circuit.add_gate(GateType::Input, 0);
+    let not = circuit.add_gate(GateType::Not, 1);
+    assert_eq!(circuit.levelize(), Ok(vec![vec![a, not]]));
+
+    circuit.connect(a, not, 0);
+    assert_eq!(circuit.levelize(), Ok(vec![vec![a], vec![not]]));
+
+    assert!(circuit.try_connect(not, not, 0).is_err());
+    let extra = circuit.add_gate(GateType::Not, 1);
+    circuit.connect(not, extra, 0);
+    assert_eq!(circuit.levelize(), Ok(vec![vec![a], vec![not], vec![extra]]));
+
+    circuit.remove_gate(not);
+    assert_eq!(circuit.levelize(), Ok(vec![vec![a, extra]]));
+}
+
+#[test]
+fn test_self_loop_is_a_cycle() {
+    let mut circuit = Circuit::new();
+    let enable = circuit.add_gate(GateType::Input, 0);
+    let and = circuit.add_gate(GateType::And, 2);
+    circuit.connect(enable, and, 0);
+    circuit.connect(and, and, 1);
+    assert_eq!(circuit.levelize(), Err(Cycle { gates: vec![and] }));
+}
+
+#[test]
+fn test_long_chains_evaluate_without_recursion() {
+    let mut circuit = Circuit::new();
+    let input = circuit.add_gate(GateType::Input, 0);
+    let mut last = input;
+    for _ in 0..5_000 {
+        let not = circuit.add_gate(GateType::Not, 1);
+        circuit.connect(last, not, 0);
+        last = not;
+    }
+    circuit.set_primary_input_value(input, true);
+    circuit.evaluate();
+    assert_eq!(circuit.get_output(last), Logic::One);
+}
@@ -0,0 +1,72 @@
+use digital_logic_simulator::circuit::Circuit;
+use digital_logic_simulator::gate::GateType;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+/// Saves a half adder with inputs `a`, `b` and outputs `sum`, `carry`.
+fn save_half_adder(tag: &str) -> PathBuf {
+    let mut circuit = Circuit::new();
+    let a = circuit.add_gate(GateType::Input, 0);
+    let b = circuit.add_gate(GateType::Input, 0);
+    let sum = circuit.add_gate(GateType::Xor, 2);
+    let carry = circuit.add_gate(GateType::And, 2);
+    for gate in [sum, carry] {
+        circuit.connect(a, gate, 0);
+        circuit.connect(b, gate, 1);
+    }
+    for (gate, name) in [(a, "a"), (b, "b"), (sum, "sum"), (carry, "carry")] {
+        circuit.set_name(gate, name);
+    }
+    let path = std::env::temp_dir().join(format!("dls_simulate_{}_{}.json", tag, std::process::id()));
+    circuit.save(&path).unwrap();
+    path
+}
+
+fn simulate(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_simulate"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin.as_bytes()).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn test_vectors_from_stdin() {
+    let path = save_half_adder("stdin");
+    let output = simulate(&[path.to_str().unwrap()], "# half adder\na=0 b=1 -> sum=1\n\na=1 b=1 -> carry=1 sum=-\n");
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "a=0 b=1 sum=1 carry=0\na=1 b=1 sum=0 carry=1\n"
+    );
+}
+
+#[test]
+fn test_mismatch_exits_with_failure() {
+    let path = save_half_adder("mismatch");
+    let vectors = std::env::temp_dir().join(format!("dls_simulate_vectors_{}.txt", std::process::id()));
+    std::fs::write(&vectors, "a=1 b=1 -> sum=1\na=1 b=0 -> sum=1\n").unwrap();
+    let output = simulate(&[path.to_str().unwrap(), vectors.to_str().unwrap()], "");
+    std::fs::remove_file(&path).unwrap();
+    std::fs::remove_file(&vectors).unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "row 1 (line 1): sum is 0, expected 1\n");
+}
+
+#[test]
+fn test_invalid_vectors_are_errors() {
+    let path = save_half_adder("invalid");
+    let output = simulate(&[path.to_str().unwrap()], "a=1 c=0\n");
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "simulate: line 1: no gate named 'c'\n");
+}
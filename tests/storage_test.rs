@@ -0,0 +1,295 @@
+use digital_logic_simulator::bus::Bus;
+use digital_logic_simulator::circuit::{Circuit, DEFAULT_MAX_PASSES};
+use digital_logic_simulator::connection::GateId;
+use digital_logic_simulator::error::CircuitError;
+use digital_logic_simulator::gate::GateType;
+use digital_logic_simulator::logic::Logic;
+use digital_logic_simulator::packed::PackedLogic;
+use digital_logic_simulator::storage::{StorageKind, Q, Q_BAR};
+use std::collections::HashMap;
+
+/// A storage element with an input gate on every input, all starting at `0`
+/// except for the enable.
+struct Fixture {
+    circuit: Circuit,
+    element: GateId,
+    inputs: Vec<GateId>,
+}
+
+impl Fixture {
+    fn new(gate_type: GateType) -> Self {
+        let (GateType::FlipFlop(kind) | GateType::Latch(kind)) = gate_type else {
+            panic!("not a storage element");
+        };
+        let mut circuit = Circuit::new();
+        let element = circuit.add_gate(gate_type, kind.input_count());
+        let mut inputs = vec![];
+        for input_index in 0..kind.input_count() {
+            let input = circuit.add_gate(GateType::Input, 0);
+            circuit.set_primary_input_value(input, input_index == kind.enable_index());
+            circuit.connect(input, element, input_index);
+            inputs.push(input);
+        }
+        circuit.settle(DEFAULT_MAX_PASSES).unwrap();
+        Self { circuit, element, inputs }
+    }
+
+    /// Sets the input with the given port index and settles the circuit.
+    fn set(&mut self, input_index: usize, value: bool) -> &mut Self {
+        self.circuit.set_primary_input_value(self.inputs[input_index], value);
+        self.circuit.propagate(DEFAULT_MAX_PASSES).unwrap();
+        self
+    }
+
+    fn q(&self) -> Logic {
+        self.circuit.get_port_output(self.element, Q).bit(0)
+    }
+
+    fn q_bar(&self) -> Logic {
+        self.circuit.get_port_output(self.element, Q_BAR).bit(0)
+    }
+}
+
+#[test]
+fn test_d_flip_flop_captures_on_rising_edge() {
+    let kind = StorageKind::D;
+    let mut ff = Fixture::new(GateType::FlipFlop(kind));
+    assert_eq!(ff.q(), Logic::X);
+    ff.set(kind.clear_index(), true).set(kind.clear_index(), false);
+    assert_eq!((ff.q(), ff.q_bar()), (Logic::Zero, Logic::One));
+
+    ff.set(0, true);
+    assert_eq!(ff.q(), Logic::Zero);
+    ff.set(kind.clock_index(), true);
+    assert_eq!((ff.q(), ff.q_bar()), (Logic::One, Logic::Zero));
+
+    // Data changes while the clock is high or falling are ignored
+    ff.set(0, false).set(kind.clock_index(), false);
+    assert_eq!(ff.q(), Logic::One);
+    ff.set(kind.clock_index(), true);
+    assert_eq!(ff.q(), Logic::Zero);
+
+    // Preset overrides the clock
+    ff.set(kind.preset_index(), true);
+    assert_eq!(ff.q(), Logic::One);
+}
+
+#[test]
+fn test_enable_and_unconnected_controls() {
+    let kind = StorageKind::D;
+    let mut ff = Fixture::new(GateType::FlipFlop(kind));
+    ff.set(kind.preset_index(), true).set(kind.preset_index(), false);
+    ff.circuit.disconnect(ff.inputs[kind.enable_index()], ff.element, kind.enable_index());
+    ff.circuit.propagate(DEFAULT_MAX_PASSES).unwrap();
+
+    // A floating enable leaves the flip-flop enabled
+    ff.set(kind.clock_index(), true);
+    assert_eq!(ff.q(), Logic::Zero);
+
+    let enable = ff.circuit.add_gate(GateType::Input, 0);
+    ff.circuit.set_primary_input_value(enable, false);
+    ff.circuit.connect(enable, ff.element, kind.enable_index());
+    ff.set(kind.clock_index(), false).set(0, true).set(kind.clock_index(), true);
+    assert_eq!(ff.q(), Logic::Zero);
+}
+
+#[test]
+fn test_jk_sr_and_t_kinds() {
+    let kind = StorageKind::JK;
+    let mut jk = Fixture::new(GateType::FlipFlop(kind));
+    let pulse = |ff: &mut Fixture| {
+        ff.set(kind.clock_index(), true).set(kind.clock_index(), false);
+        ff.q()
+    };
+    jk.set(0, true);
+    assert_eq!(pulse(&mut jk), Logic::One);
+    jk.set(1, true);
+    assert_eq!(pulse(&mut jk), Logic::Zero);
+    assert_eq!(pulse(&mut jk), Logic::One);
+    jk.set(0, false);
+    assert_eq!(pulse(&mut jk), Logic::Zero);
+
+    let kind = StorageKind::SR;
+    let mut sr = Fixture::new(GateType::Latch(kind));
+    sr.set(kind.clock_index(), true).set(0, true);
+    assert_eq!(sr.q(), Logic::One);
+    sr.set(0, false);
+    assert_eq!(sr.q(), Logic::One);
+    sr.set(1, true);
+    assert_eq!(sr.q(), Logic::Zero);
+    sr.set(0, true);
+    assert_eq!((sr.q(), sr.q_bar()), (Logic::X, Logic::X));
+
+    let kind = StorageKind::T;
+    let mut t = Fixture::new(GateType::FlipFlop(kind));
+    t.set(kind.clear_index(), true).set(kind.clear_index(), false).set(0, true);
+    let levels: Vec<Logic> = (0..3)
+        .map(|_| {
+            t.set(kind.clock_index(), true).set(kind.clock_index(), false);
+            t.q()
+        })
+        .collect();
+    assert_eq!(levels, [Logic::One, Logic::Zero, Logic::One]);
+}
+
+#[test]
+fn test_d_latch_is_transparent_while_clock_is_high() {
+    let kind = StorageKind::D;
+    let mut latch = Fixture::new(GateType::Latch(kind));
+    latch.set(kind.clock_index(), true).set(0, true);
+    assert_eq!(latch.q(), Logic::One);
+    latch.set(0, false);
+    assert_eq!(latch.q(), Logic::Zero);
+    latch.set(kind.clock_index(), false).set(0, true);
+    assert_eq!(latch.q(), Logic::Zero);
+}
+
+#[test]
+fn test_ripple_counter_with_clock() {
+    let kind = StorageKind::T;
+    let mut circuit = Circuit::new();
+    let clock = circuit.add_clock(2, 1);
+    let one = circuit.add_gate(GateType::Input, 0);
+    let clear = circuit.add_gate(GateType::Input, 0);
+    circuit.set_primary_input_value(one, true);
+    circuit.set_primary_input_value(clear, true);
+    let low = circuit.add_flip_flop(kind);
+    let high = circuit.add_flip_flop(kind);
+    for ff in [low, high] {
+        circuit.connect(one, ff, 0);
+        circuit.connect(clear, ff, kind.clear_index());
+    }
+    circuit.connect(clock, low, kind.clock_index());
+    // The high bit toggles whenever the low bit falls
+    circuit.connect_port(low, Q_BAR, high, kind.clock_index());
+    circuit.propagate(DEFAULT_MAX_PASSES).unwrap();
+    circuit.set_primary_input_value(clear, false);
+
+    let samples = circuit.run_cycles(clock, 5, &[high, low]).unwrap();
+    let counts: Vec<u64> = samples
+        .iter()
+        .map(|bits| bits.iter().fold(0, |acc, bit| acc << 1 | bit.to_u64().unwrap()))
+        .collect();
+    assert_eq!(counts, [1, 2, 3, 0, 1]);
+}
+
+#[test]
+fn test_shift_register_stages_share_each_edge() {
+    let kind = StorageKind::D;
+    let mut circuit = Circuit::new();
+    let data = circuit.add_gate(GateType::Input, 0);
+    let clock = circuit.add_gate(GateType::Input, 0);
+    let clear = circuit.add_gate(GateType::Input, 0);
+    let first = circuit.add_flip_flop(kind);
+    let second = circuit.add_flip_flop(kind);
+    circuit.connect(data, first, 0);
+    circuit.connect_port(first, Q, second, 0);
+    for ff in [first, second] {
+        circuit.connect(clock, ff, kind.clock_index());
+        circuit.connect(clear, ff, kind.clear_index());
+    }
+    circuit.set_primary_input_value(clock, false);
+    circuit.set_primary_input_value(clear, true);
+    circuit.settle(DEFAULT_MAX_PASSES).unwrap();
+    circuit.set_primary_input_value(clear, false);
+    circuit.set_primary_input_value(data, true);
+    circuit.settle(DEFAULT_MAX_PASSES).unwrap();
+    let stages = |circuit: &Circuit| (circuit.get_output(first).bit(0), circuit.get_output(second).bit(0));
+    assert_eq!(stages(&circuit), (Logic::Zero, Logic::Zero));
+
+    // The clock rises in lane 1 only
+    let clocks = HashMap::from([(clock, vec![PackedLogic::from_lanes(&[Logic::Zero, Logic::One])])]);
+    let values = circuit.simulate_packed(&clocks, DEFAULT_MAX_PASSES).unwrap();
+    let lanes = |gate: GateId| (values[&gate][Q][0].lane(0), values[&gate][Q][0].lane(1));
+    assert_eq!(lanes(first), (Logic::Zero, Logic::One));
+    assert_eq!(lanes(second), (Logic::Zero, Logic::Zero));
+
+    // Each edge moves the data one stage on, through settle and propagate alike
+    circuit.set_primary_input_value(clock, true);
+    circuit.settle(DEFAULT_MAX_PASSES).unwrap();
+    assert_eq!(stages(&circuit), (Logic::One, Logic::Zero));
+    circuit.set_primary_input_value(clock, false);
+    circuit.set_primary_input_value(data, false);
+    circuit.propagate(DEFAULT_MAX_PASSES).unwrap();
+    circuit.set_primary_input_value(clock, true);
+    circuit.propagate(DEFAULT_MAX_PASSES).unwrap();
+    assert_eq!(stages(&circuit), (Logic::Zero, Logic::One));
+}
+
+#[test]
+fn test_synchronous_counter_with_clock() {
+    let kind = StorageKind::T;
+    let mut circuit = Circuit::new();
+    let clock = circuit.add_clock(2, 1);
+    let one = circuit.add_gate(GateType::Input, 0);
+    let clear = circuit.add_gate(GateType::Input, 0);
+    circuit.set_primary_input_value(one, true);
+    circuit.set_primary_input_value(clear, true);
+    let bits: Vec<GateId> = (0..3).map(|_| circuit.add_flip_flop(kind)).collect();
+    for &ff in &bits {
+        circuit.connect(clock, ff, kind.clock_index());
+        circuit.connect(clear, ff, kind.clear_index());
+    }
+    // Each bit toggles when all lower bits are set before the edge
+    circuit.connect(one, bits[0], 0);
+    circuit.connect(bits[0], bits[1], 0);
+    let carry = circuit.add_gate(GateType::And, 2);
+    circuit.connect(bits[0], carry, 0);
+    circuit.connect(bits[1], carry, 1);
+    circuit.connect(carry, bits[2], 0);
+    circuit.propagate(DEFAULT_MAX_PASSES).unwrap();
+    circuit.set_primary_input_value(clear, false);
+
+    let outputs: Vec<GateId> = bits.iter().rev().copied().collect();
+    let samples = circuit.run_cycles(clock, 9, &outputs).unwrap();
+    let counts: Vec<u64> = samples
+        .iter()
+        .map(|bits| bits.iter().fold(0, |acc, bit| acc << 1 | bit.to_u64().unwrap()))
+        .collect();
+    assert_eq!(counts, [1, 2, 3, 4, 5, 6, 7, 0, 1]);
+}
+
+#[test]
+fn test_register_widths() {
+    let kind = StorageKind::D;
+    let mut circuit = Circuit::new();
+    let register = circuit.add_bus_gate(GateType::FlipFlop(kind), kind.input_count(), 4);
+    let data = circuit.add_bus_gate(GateType::Input, 0, 4);
+    let clock = circuit.add_gate(GateType::Input, 0);
+    assert!(circuit.try_connect(clock, register, 0).is_err());
+    assert_eq!(
+        circuit.try_connect(data, register, kind.clock_index()),
+        Err(CircuitError::ConnectionWidthMismatch {
+            from: data,
+            output_index: 0,
+            width: 4,
+            to: register,
+            input_index: kind.clock_index(),
+        })
+    );
+    circuit.connect(data, register, 0);
+    circuit.connect(clock, register, kind.clock_index());
+
+    circuit.set_primary_input_value(clock, false);
+    circuit.set_primary_input_value(data, Bus::from_u64(0b1010, 4));
+    circuit.settle(DEFAULT_MAX_PASSES).unwrap();
+    circuit.set_primary_input_value(clock, true);
+    circuit.settle(DEFAULT_MAX_PASSES).unwrap();
+    assert_eq!(circuit.get_port_output(register, Q), Bus::from_u64(0b1010, 4));
+    assert_eq!(circuit.get_port_output(register, Q_BAR), Bus::from_u64(0b0101, 4));
+}
+
+#[test]
+fn test_truth_table_of_d_latch() {
+    let kind = StorageKind::D;
+    let mut circuit = Circuit::new();
+    let latch = circuit.add_latch(kind);
+    let data = circuit.add_gate(GateType::Input, 0);
+    let enable = circuit.add_gate(GateType::Input, 0);
+    circuit.connect(data, latch, 0);
+    circuit.connect(enable, latch, kind.clock_index());
+
+    let table = circuit.truth_table(&[latch]).unwrap();
+    let outputs: Vec<Logic> = table.rows.iter().map(|row| row.outputs[0].bit(0)).collect();
+    assert_eq!(outputs, [Logic::X, Logic::Zero, Logic::X, Logic::One]);
+}
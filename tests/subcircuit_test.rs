@@ -0,0 +1,321 @@
+use digital_logic_simulator::bus::Bus;
+use digital_logic_simulator::circuit::Circuit;
+use digital_logic_simulator::connection::GateId;
+use digital_logic_simulator::error::CircuitError;
+use digital_logic_simulator::gate::GateType;
+use digital_logic_simulator::storage::StorageKind;
+use digital_logic_simulator::subcircuit::Subcircuit;
+
+/// Builds a 1-bit full adder with inputs `a`, `b`, `cin` and outputs `sum`, `cout`.
+fn full_adder() -> Subcircuit {
+    let mut circuit = Circuit::new();
+    let a = circuit.add_gate(GateType::Input, 0);
+    let b = circuit.add_gate(GateType::Input, 0);
+    let cin = circuit.add_gate(GateType::Input, 0);
+    let sum = circuit.add_gate(GateType::Xor, 3);
+    let ab = circuit.add_gate(GateType::And, 2);
+    let a_xor_b = circuit.add_gate(GateType::Xor, 2);
+    let carry_through = circuit.add_gate(GateType::And, 2);
+    let cout = circuit.add_gate(GateType::Or, 2);
+
+    circuit.connect(a, sum, 0);
+    circuit.connect(b, sum, 1);
+    circuit.connect(cin, sum, 2);
+    circuit.connect(a, ab, 0);
+    circuit.connect(b, ab, 1);
+    circuit.connect(a, a_xor_b, 0);
+    circuit.connect(b, a_xor_b, 1);
+    circuit.connect(a_xor_b, carry_through, 0);
+    circuit.connect(cin, carry_through, 1);
+    circuit.connect(ab, cout, 0);
+    circuit.connect(carry_through, cout, 1);
+
+    let mut adder = Subcircuit::new("full adder", circuit);
+    adder.add_input("a", a);
+    adder.add_input("b", b);
+    adder.add_input("cin", cin);
+    adder.add_output("sum", sum);
+    adder.add_output("cout", cout);
+    adder
+}
+
+/// Builds a 4-bit ripple-carry adder from four full adder instances.
+///
+/// Returns the circuit with inputs `a`, `b` (4 bits each) and the merged 4-bit sum and carry out.
+fn four_bit_adder() -> (Circuit, usize, usize, usize, usize) {
+    let mut circuit = Circuit::new();
+    let adder = circuit.define_subcircuit(full_adder());
+
+    let a = circuit.add_bus_gate(GateType::Input, 0, 4);
+    let b = circuit.add_bus_gate(GateType::Input, 0, 4);
+    let carry_in = circuit.add_gate(GateType::Input, 0);
+    let sum = circuit.add_merger(4, 1);
+
+    let sum_port = circuit.subcircuit(adder).output_index("sum").unwrap();
+    let cout_port = circuit.subcircuit(adder).output_index("cout").unwrap();
+
+    let mut carry = (carry_in, 0);
+    for bit in 0..4 {
+        let a_bit = circuit.add_splitter(bit, 1);
+        let b_bit = circuit.add_splitter(bit, 1);
+        circuit.connect(a, a_bit, 0);
+        circuit.connect(b, b_bit, 0);
+
+        let stage = circuit.add_subcircuit(adder);
+        circuit.connect(a_bit, stage, 0);
+        circuit.connect(b_bit, stage, 1);
+        circuit.connect_port(carry.0, carry.1, stage, 2);
+        circuit.connect_port(stage, sum_port, sum, bit);
+        carry = (stage, cout_port);
+    }
+
+    let carry_out = circuit.add_gate(GateType::Or, 1);
+    circuit.connect_port(carry.0, carry.1, carry_out, 0);
+    circuit.set_primary_input_value(carry_in, false);
+
+    (circuit, a, b, sum, carry_out)
+}
+
+/// Builds a definition with inputs `x`, `y` and output `!x & y`, whose
+/// inverter is a nested subcircuit instance added before the other gates.
+fn nested_definition() -> Subcircuit {
+    let mut inverter = Circuit::new();
+    let a = inverter.add_gate(GateType::Input, 0);
+    let not = inverter.add_gate(GateType::Not, 1);
+    inverter.connect(a, not, 0);
+    let mut inverter_definition = Subcircuit::new("inverter", inverter);
+    inverter_definition.add_input("a", a);
+    inverter_definition.add_output("y", not);
+
+    let mut circuit = Circuit::new();
+    let inverter_id = circuit.define_subcircuit(inverter_definition);
+    let x = circuit.add_gate(GateType::Input, 0);
+    let inverted = circuit.add_subcircuit(inverter_id);
+    let y = circuit.add_gate(GateType::Input, 0);
+    let and = circuit.add_gate(GateType::And, 2);
+    circuit.connect(x, inverted, 0);
+    circuit.connect(inverted, and, 0);
+    circuit.connect(y, and, 1);
+
+    let mut definition = Subcircuit::new("nested", circuit);
+    definition.add_input("x", x);
+    definition.add_input("y", y);
+    definition.add_output("out", and);
+    definition
+}
+
+/// Builds a definition with inputs `x`, `y` and output `!x & y`, whose gate
+/// ids have a gap left by a removed gate.
+fn gapped_definition() -> Subcircuit {
+    let mut circuit = Circuit::new();
+    let x = circuit.add_gate(GateType::Input, 0);
+    let removed = circuit.add_gate(GateType::Or, 2);
+    let y = circuit.add_gate(GateType::Input, 0);
+    let not = circuit.add_gate(GateType::Not, 1);
+    let and = circuit.add_gate(GateType::And, 2);
+    circuit.remove_gate(removed);
+    circuit.connect(x, not, 0);
+    circuit.connect(not, and, 0);
+    circuit.connect(y, and, 1);
+
+    let mut definition = Subcircuit::new("gapped", circuit);
+    definition.add_input("x", x);
+    definition.add_input("y", y);
+    definition.add_output("out", and);
+    definition
+}
+
+/// Instantiates `definition` with an input gate on each port and a buffer
+/// reading its output, and returns the circuit, the instance and the buffer.
+fn instantiate(definition: Subcircuit) -> (Circuit, GateId, GateId) {
+    let mut circuit = Circuit::new();
+    let id = circuit.define_subcircuit(definition);
+    let x = circuit.add_gate(GateType::Input, 0);
+    let y = circuit.add_gate(GateType::Input, 0);
+    let instance = circuit.add_subcircuit(id);
+    let buffer = circuit.add_gate(GateType::Or, 1);
+    circuit.connect(x, instance, 0);
+    circuit.connect(y, instance, 1);
+    circuit.connect(instance, buffer, 0);
+    (circuit, instance, buffer)
+}
+
+#[test]
+fn test_named_ports() {
+    let adder = full_adder();
+    assert_eq!(adder.name(), "full adder");
+    assert_eq!(adder.input_index("cin"), Some(2));
+    assert_eq!(adder.output_index("cout"), Some(1));
+    assert_eq!(adder.output_index("carry"), None);
+    assert_eq!(adder.input_widths(), vec![1, 1, 1]);
+}
+
+#[test]
+fn test_ports_must_name_existing_gates() {
+    let mut adder = full_adder();
+    let sum = adder.output_index("sum").map(|index| adder.outputs()[index].gate).unwrap();
+    assert_eq!(adder.try_add_output("carry", 99), Err(CircuitError::InvalidGate(99)));
+    assert_eq!(adder.try_add_input("d", 99), Err(CircuitError::InvalidGate(99)));
+    assert_eq!(adder.try_add_input("d", sum), Err(CircuitError::NotAnInput(sum)));
+    assert_eq!(adder.outputs().len(), 2);
+    assert_eq!(adder.inputs().len(), 3);
+}
+
+#[test]
+fn test_single_instance() {
+    let mut circuit = Circuit::new();
+    let adder = circuit.define_subcircuit(full_adder());
+
+    let a = circuit.add_gate(GateType::Input, 0);
+    let b = circuit.add_gate(GateType::Input, 0);
+    let cin = circuit.add_gate(GateType::Input, 0);
+    let instance = circuit.add_subcircuit(adder);
+    circuit.connect(a, instance, 0);
+    circuit.connect(b, instance, 1);
+    circuit.connect(cin, instance, 2);
+    assert_eq!(circuit.gate(instance).output_count(), 2);
+
+    circuit.set_primary_input_value(a, true);
+    circuit.set_primary_input_value(b, true);
+    circuit.set_primary_input_value(cin, false);
+    circuit.evaluate();
+
+    assert_eq!(circuit.get_port_output(instance, 0), false);
+    assert_eq!(circuit.get_port_output(instance, 1), true);
+}
+
+#[test]
+fn test_port_names() {
+    let mut circuit = Circuit::new();
+    let adder = circuit.define_subcircuit(full_adder());
+    let instance = circuit.add_subcircuit(adder);
+    let and = circuit.add_gate(GateType::And, 2);
+    let latch = circuit.add_latch(StorageKind::SR);
+
+    assert_eq!(circuit.input_names(instance), ["a", "b", "cin"]);
+    assert_eq!(circuit.output_names(instance), ["sum", "cout"]);
+    assert_eq!(circuit.input_names(and), ["In0", "In1"]);
+    assert_eq!(circuit.output_names(and), ["Out"]);
+    assert_eq!(circuit.input_names(latch), ["S", "R", "CLK", "EN", "PRE", "CLR"]);
+    assert_eq!(circuit.output_names(latch), ["Q", "Q̅"]);
+    assert_eq!(circuit.try_output_names(99), Err(CircuitError::InvalidGate(99)));
+}
+
+#[test]
+fn test_four_bit_adder_from_full_adders() {
+    let (mut circuit, a, b, sum, carry_out) = four_bit_adder();
+
+    for (x, y) in [(0u64, 0u64), (3, 4), (7, 9), (15, 1), (15, 15)] {
+        circuit.set_primary_input_value(a, Bus::from_u64(x, 4));
+        circuit.set_primary_input_value(b, Bus::from_u64(y, 4));
+        circuit.evaluate();
+
+        assert_eq!(circuit.get_output(sum).to_u64(), Some((x + y) & 0xF), "{} + {}", x, y);
+        assert_eq!(circuit.get_output(carry_out), x + y > 15, "{} + {}", x, y);
+    }
+}
+
+#[test]
+fn test_flatten_matches_hierarchical_evaluation() {
+    let (mut circuit, a, b, sum, carry_out) = four_bit_adder();
+    let mut flat = circuit.flatten();
+
+    for gate_id in 0..flat.gate_count() {
+        assert!(!matches!(flat.gate(gate_id).gate_type, GateType::Subcircuit(_)));
+    }
+
+    // Gates outside of instances keep their relative order; the four instances
+    // were all added before the carry output gate
+    let flat_sum = sum;
+    let flat_carry_out = carry_out - 4;
+
+    for (x, y) in [(1u64, 2u64), (8, 8), (15, 15)] {
+        circuit.set_primary_input_value(a, Bus::from_u64(x, 4));
+        circuit.set_primary_input_value(b, Bus::from_u64(y, 4));
+        circuit.evaluate();
+        flat.set_primary_input_value(a, Bus::from_u64(x, 4));
+        flat.set_primary_input_value(b, Bus::from_u64(y, 4));
+        flat.evaluate();
+
+        assert_eq!(flat.get_output(flat_sum), circuit.get_output(sum));
+        assert_eq!(flat.get_output(flat_carry_out), circuit.get_output(carry_out));
+    }
+}
+
+#[test]
+fn test_flatten_nested_and_gapped_definitions() {
+    for definition in [nested_definition(), gapped_definition()] {
+        let name = definition.name().to_string();
+        let (mut circuit, _, buffer) = instantiate(definition);
+        let mut flat = circuit.flatten();
+        // The instance was the only gate before the buffer to be removed
+        let hierarchical = circuit.truth_table(&[buffer]).unwrap();
+        let flattened = flat.truth_table(&[buffer - 1]).unwrap();
+        assert_eq!(flattened.rows, hierarchical.rows, "{}", name);
+        let outputs: Vec<u64> = hierarchical.rows.iter().map(|row| row.outputs[0].to_u64().unwrap()).collect();
+        assert_eq!(outputs, [0, 1, 0, 0], "{}", name);
+    }
+}
+
+#[test]
+fn test_expand_nested_and_gapped_definitions() {
+    for definition in [nested_definition(), gapped_definition()] {
+        let name = definition.name().to_string();
+        let (mut circuit, instance, buffer) = instantiate(definition);
+        let hierarchical = circuit.truth_table(&[buffer]).unwrap();
+        circuit.expand(instance);
+        let expanded = circuit.truth_table(&[buffer]).unwrap();
+        assert_eq!(expanded.rows, hierarchical.rows, "{}", name);
+    }
+}
+
+#[test]
+fn test_instances_keep_independent_state() {
+    // An SR latch made of cross-coupled NOR gates
+    let mut latch = Circuit::new();
+    let set = latch.add_gate(GateType::Input, 0);
+    let reset = latch.add_gate(GateType::Input, 0);
+    let or_q = latch.add_gate(GateType::Or, 2);
+    let q = latch.add_gate(GateType::Not, 1);
+    let or_q_bar = latch.add_gate(GateType::Or, 2);
+    let q_bar = latch.add_gate(GateType::Not, 1);
+    latch.connect(reset, or_q, 0);
+    latch.connect(q_bar, or_q, 1);
+    latch.connect(or_q, q, 0);
+    latch.connect(set, or_q_bar, 0);
+    latch.connect(q, or_q_bar, 1);
+    latch.connect(or_q_bar, q_bar, 0);
+
+    let mut definition = Subcircuit::new("sr latch", latch);
+    definition.add_input("s", set);
+    definition.add_input("r", reset);
+    definition.add_output("q", q);
+
+    let mut circuit = Circuit::new();
+    let id = circuit.define_subcircuit(definition);
+    let s1 = circuit.add_gate(GateType::Input, 0);
+    let s2 = circuit.add_gate(GateType::Input, 0);
+    let r = circuit.add_gate(GateType::Input, 0);
+    let first = circuit.add_subcircuit(id);
+    let second = circuit.add_subcircuit(id);
+    circuit.connect(s1, first, 0);
+    circuit.connect(r, first, 1);
+    circuit.connect(s2, second, 0);
+    circuit.connect(r, second, 1);
+
+    circuit.set_primary_input_value(s1, true);
+    circuit.set_primary_input_value(s2, false);
+    circuit.set_primary_input_value(r, true);
+    circuit.evaluate();
+    assert_eq!(circuit.get_output(first), false);
+    assert_eq!(circuit.get_output(second), false);
+
+    // Set only the first latch, then release everything
+    circuit.set_primary_input_value(r, false);
+    circuit.evaluate();
+    circuit.set_primary_input_value(s1, false);
+    circuit.evaluate();
+
+    assert_eq!(circuit.get_output(first), true);
+    assert_eq!(circuit.get_output(second), false);
+}
@@ -0,0 +1,105 @@
+use digital_logic_simulator::bus::Bus;
+use digital_logic_simulator::circuit::Circuit;
+use digital_logic_simulator::error::CircuitError;
+use digital_logic_simulator::gate::GateType;
+use digital_logic_simulator::truth_table::{TruthTable, TruthTableError, MAX_INPUT_BITS};
+
+/// Builds `a XOR b` and `a AND b`, returning the circuit and the two output gates.
+fn half_adder() -> (Circuit, usize, usize) {
+    let mut circuit = Circuit::new();
+    let a = circuit.add_gate(GateType::Input, 0);
+    let b = circuit.add_gate(GateType::Input, 0);
+    let sum = circuit.add_gate(GateType::Xor, 2);
+    let carry = circuit.add_gate(GateType::And, 2);
+    for gate in [sum, carry] {
+        circuit.connect(a, gate, 0);
+        circuit.connect(b, gate, 1);
+    }
+    (circuit, sum, carry)
+}
+
+#[test]
+fn test_half_adder_rows() {
+    let (mut circuit, sum, carry) = half_adder();
+    let table = circuit.truth_table(&[sum, carry]).unwrap();
+
+    assert_eq!(table.inputs, vec![0, 1]);
+    assert_eq!(table.rows.len(), 4);
+    let expected = [
+        (false, false, false, false),
+        (false, true, true, false),
+        (true, false, true, false),
+        (true, true, false, true),
+    ];
+    for (row, (a, b, s, c)) in table.rows.iter().zip(expected) {
+        assert_eq!(row.inputs, vec![Bus::from(a), Bus::from(b)]);
+        assert_eq!(row.outputs, vec![Bus::from(s), Bus::from(c)]);
+    }
+}
+
+#[test]
+fn test_exports() {
+    let (mut circuit, sum, carry) = half_adder();
+    let mut table = circuit.truth_table(&[sum, carry]).unwrap();
+    table.input_labels = vec!["a".into(), "b".into()];
+    table.output_labels = vec!["sum".into(), "carry".into()];
+
+    assert_eq!(table.to_csv(), "a,b,sum,carry\n0,0,0,0\n0,1,1,0\n1,0,1,0\n1,1,0,1\n");
+    assert_eq!(
+        table.to_markdown(),
+        "| a | b | sum | carry |\n|---|---|---|---|\n| 0 | 0 | 0 | 0 |\n| 0 | 1 | 1 | 0 |\n| 1 | 0 | 1 | 0 |\n| 1 | 1 | 0 | 1 |\n"
+    );
+    assert_eq!(
+        table.to_text(),
+        "a b | sum carry\n---------------\n0 0 | 0   0\n0 1 | 1   0\n1 0 | 1   0\n1 1 | 0   1\n"
+    );
+}
+
+#[test]
+fn test_bus_inputs_and_more_than_64_rows() {
+    // 2-bit and 5-bit inputs give 128 rows, spread over two packed passes
+    let mut circuit = Circuit::new();
+    let a = circuit.add_bus_gate(GateType::Input, 0, 2);
+    let b = circuit.add_bus_gate(GateType::Input, 0, 5);
+    let low = circuit.add_splitter(0, 2);
+    let and = circuit.add_bus_gate(GateType::And, 2, 2);
+    circuit.connect(b, low, 0);
+    circuit.connect(a, and, 0);
+    circuit.connect(low, and, 1);
+    let table = circuit.truth_table(&[and]).unwrap();
+
+    assert_eq!(table.rows.len(), 128);
+    for (n, row) in table.rows.iter().enumerate() {
+        let (a, b) = ((n >> 5) as u64, (n & 31) as u64);
+        assert_eq!(row.inputs, vec![Bus::from_u64(a, 2), Bus::from_u64(b, 5)]);
+        assert_eq!(row.outputs, vec![Bus::from_u64(a & b & 3, 2)]);
+    }
+}
+
+#[test]
+fn test_errors() {
+    let (mut circuit, sum, _) = half_adder();
+    assert_eq!(circuit.truth_table(&[9]), Err(TruthTableError::Circuit(CircuitError::InvalidGate(9))));
+    assert_eq!(
+        TruthTable::generate(&mut circuit, &[sum], &[sum]),
+        Err(TruthTableError::Circuit(CircuitError::NotAnInput(sum)))
+    );
+
+    let wide = circuit.add_bus_gate(GateType::Input, 0, MAX_INPUT_BITS);
+    assert_eq!(
+        TruthTable::generate(&mut circuit, &[0, wide], &[sum]),
+        Err(TruthTableError::TooManyInputs { bits: MAX_INPUT_BITS + 1 })
+    );
+}
+
+#[test]
+fn test_revision_tracks_netlist_changes() {
+    let (mut circuit, sum, _) = half_adder();
+    let revision = circuit.revision();
+    circuit.set_primary_input_value(0, true);
+    circuit.truth_table(&[sum]).unwrap();
+    assert_eq!(circuit.revision(), revision);
+
+    circuit.disconnect(0, sum, 0);
+    assert_ne!(circuit.revision(), revision);
+}
@@ -0,0 +1,101 @@
+use digital_logic_simulator::bus::{Bus, ParseBusError};
+use digital_logic_simulator::circuit::Circuit;
+use digital_logic_simulator::gate::GateType;
+use digital_logic_simulator::logic::Logic;
+use digital_logic_simulator::vectors::{self, Mismatch, Pattern, TestVector, VectorError};
+
+/// A 2-bit AND of inputs `x` and `y` named `z`, with an unnamed single-bit input.
+fn bus_and() -> Circuit {
+    let mut circuit = Circuit::new();
+    let x = circuit.add_bus_gate(GateType::Input, 0, 2);
+    let y = circuit.add_bus_gate(GateType::Input, 0, 2);
+    let z = circuit.add_bus_gate(GateType::And, 2, 2);
+    circuit.add_gate(GateType::Input, 0);
+    circuit.connect(x, z, 0);
+    circuit.connect(y, z, 1);
+    circuit.set_name(x, "x");
+    circuit.set_name(y, "y");
+    circuit.set_name(z, "z");
+    circuit
+}
+
+#[test]
+fn test_parse() {
+    let text = "# header\n\nx=10 y=11 -> z=1-  # trailing\ny=01\n";
+    assert_eq!(
+        vectors::parse(text),
+        Ok(vec![
+            TestVector {
+                line: 3,
+                inputs: vec![("x".to_string(), "10".parse().unwrap()), ("y".to_string(), "11".parse().unwrap())],
+                expected: vec![("z".to_string(), "1-".parse().unwrap())],
+            },
+            TestVector { line: 4, inputs: vec![("y".to_string(), "01".parse().unwrap())], expected: vec![] },
+        ])
+    );
+}
+
+#[test]
+fn test_pattern_matching() {
+    let pattern: Pattern = "1-X".parse().unwrap();
+    assert_eq!(pattern.bits, vec![Some(Logic::X), None, Some(Logic::One)]);
+    assert_eq!(pattern.to_string(), "1-X");
+    assert!(pattern.matches(&"10X".parse().unwrap()));
+    assert!(pattern.matches(&"11X".parse().unwrap()));
+    assert!(!pattern.matches(&"110".parse().unwrap()));
+    assert!(!pattern.matches(&"1X".parse().unwrap()));
+    assert!(Pattern::from(Bus::from(true)).matches(&Bus::from(true)));
+}
+
+#[test]
+fn test_run_reports_mismatches() {
+    let mut circuit = bus_and();
+    // Inputs keep their values from one line to the next
+    let vectors = vectors::parse("x=11 y=10 -> z=10\ny=01 -> z=11\nx=00 -> z=0-\n").unwrap();
+    let report = circuit.run_vectors(&vectors).unwrap();
+
+    assert!(!report.passed());
+    assert_eq!(
+        report.mismatches,
+        vec![Mismatch {
+            row: 2,
+            line: 2,
+            name: "z".to_string(),
+            expected: "11".parse().unwrap(),
+            actual: "01".parse().unwrap(),
+        }]
+    );
+    assert_eq!(report.mismatches[0].to_string(), "row 2 (line 2): z is 01, expected 11");
+    assert_eq!(
+        report.rows[2].values,
+        vec![("x".to_string(), "00".parse().unwrap()), ("z".to_string(), "00".parse().unwrap())]
+    );
+}
+
+#[test]
+fn test_unnamed_gates_and_passing_run() {
+    let mut circuit = bus_and();
+    let vectors = vectors::parse("in3=1 x=01 y=11 -> z=01\n").unwrap();
+    let report = circuit.run_vectors(&vectors).unwrap();
+    assert!(report.passed());
+    assert_eq!(report.rows[0].values[0], ("in3".to_string(), Bus::from(true)));
+}
+
+#[test]
+fn test_errors() {
+    let mut circuit = bus_and();
+    let mut run = |text: &str| circuit.run_vectors(&vectors::parse(text)?);
+
+    assert_eq!(run("x=01\nx"), Err(VectorError::Malformed { line: 2, token: "x".to_string() }));
+    assert_eq!(
+        run("x=0a"),
+        Err(VectorError::InvalidValue { line: 1, name: "x".to_string(), error: ParseBusError { invalid: 'a' } })
+    );
+    assert_eq!(run("w=1"), Err(VectorError::UnknownGate { line: 1, name: "w".to_string() }));
+    assert_eq!(run("out0=1"), Err(VectorError::UnknownGate { line: 1, name: "out0".to_string() }));
+    assert_eq!(run("z=11"), Err(VectorError::NotAnInput { line: 1, name: "z".to_string() }));
+    assert_eq!(
+        run("x=01 -> z=1"),
+        Err(VectorError::WidthMismatch { line: 1, name: "z".to_string(), width: 2, found: 1 })
+    );
+}